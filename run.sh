@@ -1,9 +1,11 @@
 #!/bin/bash
 
-FOLDER="./beam-planning/test_cases"
+FOLDERS="./beam-planning/test_cases ./beam-planning/big_tests"
 
-for TEST in $(ls $FOLDER) ; do 
-    echo "Running $TEST"
-    FILE="$FOLDER/$TEST"
-    cat $FILE | cargo run --features "elevation,analysis" | python "./beam-planning/evaluate.py" "$FILE"
-done
\ No newline at end of file
+for FOLDER in $FOLDERS ; do
+    for TEST in $(ls $FOLDER) ; do 
+        echo "Running $TEST"
+        FILE="$FOLDER/$TEST"
+        cargo run --release -q -- solve --analysis "$FILE" | python "./beam-planning/evaluate.py" "$FILE"
+    done
+done
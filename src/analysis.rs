@@ -0,0 +1,754 @@
+use std::fmt;
+
+use crate::{
+    constraints::Constraints,
+    geodesy::Geodetic,
+    scenario::{Scenario, BANDS},
+    solution::Solution,
+    solver::Plan,
+    terminal::Terminal,
+};
+
+// Summary statistics of a solved scenario, for judging solution quality beyond
+// the raw coverage number the evaluator reports.
+pub struct Analysis {
+    pub users: usize,
+    pub covered: usize, // Users with a beam
+    pub saturated: i32,
+    pub unassigned: i32,
+    pub max_possible: usize,           // Beams over all satellites
+    pub max_possible_utilization: f64, // max_possible / number of users
+    pub uncovered: usize,              // Users who can't see any satellite
+    pub masked: usize,                 // Of those, the ones who could but for their horizon mask
+    // Links a user could otherwise use, on a band both ends carry and within
+    // the satellite's scan, that the strict rule, checking every interferer,
+    // rejects only because of interferers below that user's horizon: what
+    // earth_blockage wins back. Only with earth_blockage.
+    pub occluded_rejections: Option<usize>,
+    pub max_visible_utilization: f64, // max_possible / (number of users - uncovered users)
+    pub average_elevation: f64,
+    pub elevations: Histogram,  // Dishy elevation of every assigned beam
+    pub scan_angles: Histogram, // Degrees off nadir of every assigned beam
+    pub slant_ranges: Vec<f64>, // User to satellite distance, sorted, in km
+    pub beams_per_satellite: Histogram,
+    pub band_beams: [usize; 4],         // Indexed in BANDS order
+    pub band_saturated: [usize; 4],     // Satellites at their cap on each band
+    pub latitudes: Vec<LatitudeBand>,   // LATITUDE_BAND degrees wide, south to north
+    pub unserved: Vec<(i32, Geodetic)>, // Users without a beam, in input order
+    pub classes: Vec<ClassCoverage>,    // When the scenario defines terminal classes
+    pub weight: f64,                    // Summed weight of every user
+    pub covered_weight: f64,            // Of the users with a beam
+    pub priorities: Vec<TierCoverage>,  // Highest first, when users are weighted
+    pub slack: Slack,
+}
+
+// The least clearance, in degrees, of any assignment from each angular limit,
+// measured without margins; negative means a violation. Elevation is measured
+// from each user's horizon mask where it is above min_elevation. None when there was
+// nothing to measure, e.g. no two beams sharing a band on a satellite.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Slack {
+    pub elevation: Option<f64>,
+    pub beam_angle: Option<f64>,
+    pub interference_angle: Option<f64>, // Ignoring occluded interferers with earth_blockage
+    pub geo_arc: Option<f64>,            // Only with a geo_arc constraint
+    pub scan: Option<f64>,               // Only for satellites with a max_scan
+}
+
+pub const LATITUDE_BAND: f64 = 10.0;
+
+// Users without a terminal class, or with one the scenario doesn't define.
+pub const DEFAULT_CLASS: &str = "default";
+
+// Users of one terminal class, those with a beam, and those who can't see any
+// satellite with their terminal.
+pub struct ClassCoverage {
+    pub name: String,
+    pub users: usize,
+    pub covered: usize,
+    pub uncovered: usize,
+}
+
+// Users of one priority tier, those with a beam, and their summed weights.
+pub struct TierCoverage {
+    pub priority: i32,
+    pub users: usize,
+    pub covered: usize,
+    pub weight: f64,
+    pub covered_weight: f64,
+}
+
+// Users and assigned users between `south` and `south + LATITUDE_BAND`.
+pub struct LatitudeBand {
+    pub south: f64,
+    pub users: usize,
+    pub covered: usize,
+}
+
+// Fixed-width buckets over [min, max). Values outside the range are clamped
+// into the first or last bucket, so the counts always sum to the samples.
+pub struct Histogram {
+    pub min: f64,
+    pub width: f64,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    pub fn new(min: f64, max: f64, width: f64) -> Histogram {
+        let buckets = ((max - min) / width).ceil().max(1.0) as usize;
+        Histogram {
+            min,
+            width,
+            counts: vec![0; buckets],
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        let bucket = ((value - self.min) / self.width).floor().max(0.0) as usize;
+        let last = self.counts.len() - 1;
+        self.counts[bucket.min(last)] += 1;
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+// Nearest-rank percentile of an already sorted slice.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn lower(slack: &mut Option<f64>, value: f64) {
+    *slack = Some(slack.map_or(value, |slack| slack.min(value)));
+}
+
+impl Analysis {
+    // % of users assigned
+    pub fn utilization(&self) -> f64 {
+        self.covered as f64 / self.users as f64
+    }
+
+    // % of max utilization achieved;
+    pub fn success(&self) -> f64 {
+        let best_utilization = self
+            .max_visible_utilization
+            .min(self.max_possible_utilization);
+        self.utilization() / best_utilization
+    }
+}
+
+impl Scenario {
+    pub fn analyze(&self, solution: &Solution) -> Analysis {
+        self.analyze_with(solution, &Constraints::default())
+    }
+
+    // Beams referencing ids that aren't in the scenario are skipped; validate
+    // reports those.
+    pub fn analyze_with(&self, solution: &Solution, constraints: &Constraints) -> Analysis {
+        // Each satellite's beam capacity, in scenario order.
+        let payloads: Vec<_> = self
+            .satellites()
+            .iter()
+            .map(|s| self.payload(s.entity().id(), constraints))
+            .collect();
+        let capacities: Vec<usize> = payloads.iter().map(|p| p.beams(constraints)).collect();
+        let beams = capacities
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(constraints.beams);
+        let mut analysis = Analysis {
+            users: self.users().len(),
+            covered: solution.covered(),
+            saturated: 0,
+            unassigned: 0,
+            max_possible: 0,
+            max_possible_utilization: 0.0,
+            uncovered: 0,
+            masked: 0,
+            occluded_rejections: None,
+            max_visible_utilization: 0.0,
+            average_elevation: 0.0,
+            elevations: Histogram::new(constraints.min_elevation, 90.0, 5.0),
+            scan_angles: Histogram::new(0.0, 70.0, 5.0),
+            slant_ranges: Vec::new(),
+            beams_per_satellite: Histogram::new(0.0, (beams + 1) as f64, 4.0),
+            band_beams: [0; 4],
+            band_saturated: [0; 4],
+            latitudes: (0..(180.0 / LATITUDE_BAND) as usize)
+                .map(|i| LatitudeBand {
+                    south: -90.0 + i as f64 * LATITUDE_BAND,
+                    users: 0,
+                    covered: 0,
+                })
+                .collect(),
+            unserved: Vec::new(),
+            classes: Vec::new(),
+            weight: 0.0,
+            covered_weight: 0.0,
+            priorities: Vec::new(),
+            slack: Slack::default(),
+        };
+
+        // Count number of saturated satelites, number of unassigned satellites,
+        // and maximum satellite utilization.
+        self.satellites()
+            .iter()
+            .zip(payloads.iter().zip(&capacities))
+            .for_each(|(s, (payload, capacity))| {
+                let beams = solution.by_satellite(s.entity().id());
+                for (i, band) in BANDS.iter().enumerate() {
+                    let on_band = beams.iter().filter(|a| a.band == *band).count();
+                    if payload.band_beams(*band).is_some_and(|cap| on_band >= cap) {
+                        analysis.band_saturated[i] += 1;
+                    }
+                }
+                let assigned = beams.len();
+                match assigned {
+                    0 => analysis.unassigned += 1,
+                    n if n >= *capacity => analysis.saturated += 1,
+                    _ => (),
+                }
+                analysis.beams_per_satellite.add(assigned as f64);
+            });
+
+        let bare = constraints.without_margins();
+        if !self.classes().is_empty() {
+            analysis.classes = self
+                .classes()
+                .iter()
+                .map(|class| class.name())
+                .chain(Some(DEFAULT_CLASS))
+                .map(|name| ClassCoverage {
+                    name: name.to_string(),
+                    users: 0,
+                    covered: 0,
+                    uncovered: 0,
+                })
+                .collect();
+        }
+        for user in self.users() {
+            let terminal = self.terminal(user.id());
+            let visible = |terminal: Terminal| {
+                self.satellites()
+                    .iter()
+                    .any(|s| terminal.is_visible(user.position(), s.entity().position(), &bare))
+            };
+            let uncovered = !visible(terminal);
+            if uncovered {
+                analysis.uncovered += 1;
+                let unmasked = Terminal {
+                    mask: None,
+                    ..terminal
+                };
+                if terminal.mask.is_some() && visible(unmasked) {
+                    analysis.masked += 1;
+                }
+            }
+            let covered = solution.by_user(user.id()).is_some();
+            let name = terminal.class.map_or(DEFAULT_CLASS, |class| class.name());
+            if let Some(class) = analysis.classes.iter_mut().find(|c| c.name == name) {
+                class.users += 1;
+                class.covered += covered as usize;
+                class.uncovered += uncovered as usize;
+            }
+            let weight = self.weight(user.id());
+            analysis.weight += weight;
+            if covered {
+                analysis.covered_weight += weight;
+            }
+        }
+        if self.is_weighted() {
+            analysis.priorities = self
+                .tiers()
+                .into_iter()
+                .map(|(priority, users)| {
+                    let mut tier = TierCoverage {
+                        priority,
+                        users: users.len(),
+                        covered: 0,
+                        weight: 0.0,
+                        covered_weight: 0.0,
+                    };
+                    for user in &users {
+                        let weight = self.weight(user.id());
+                        tier.weight += weight;
+                        if solution.by_user(user.id()).is_some() {
+                            tier.covered += 1;
+                            tier.covered_weight += weight;
+                        }
+                    }
+                    tier
+                })
+                .collect();
+        }
+
+        let interferers = self.interference_limits(&bare);
+        if bare.earth_blockage {
+            let strict = Constraints {
+                earth_blockage: false,
+                ..bare
+            };
+            let plans: Vec<_> = self
+                .satellites()
+                .iter()
+                .map(|s| Plan::with_payload(s, self.payload(s.entity().id(), &bare)))
+                .collect();
+            let mut rejections = 0;
+            for user in self.users() {
+                let terminal = self.terminal(user.id());
+                for plan in &plans {
+                    let satellite = plan.satellite().entity().position();
+                    let usable = terminal.is_visible(user.position(), satellite, &bare)
+                        && plan.within_scan(user)
+                        && BANDS
+                            .iter()
+                            .any(|band| plan.carries(*band) && terminal.allows(*band));
+                    if usable
+                        && !plan.interference(user, &interferers, &bare)
+                        && plan.interference(user, &interferers, &strict)
+                    {
+                        rejections += 1;
+                    }
+                }
+            }
+            analysis.occluded_rejections = Some(rejections);
+        }
+
+        analysis.max_possible = capacities.iter().sum::<usize>().min(self.users().len());
+        analysis.max_possible_utilization =
+            analysis.max_possible as f64 / self.users().len() as f64;
+        analysis.max_visible_utilization = ((self.users().len() - analysis.uncovered) as f64
+            / analysis.max_possible as f64)
+            .min(1.0);
+
+        let users = self.users_by_id();
+        let satellites = self.satellites_by_id();
+        let mut sum = 0.0;
+        for a in solution.iter() {
+            let (user, satellite) = match (users.get(&a.user), satellites.get(&a.satellite)) {
+                (Some(user), Some(satellite)) => (user.position(), satellite.entity().position()),
+                _ => continue,
+            };
+            let look = user.look_angles(satellite, constraints.elevation_model);
+            let elevation = look.elevation;
+            lower(
+                &mut analysis.slack.elevation,
+                self.terminal(a.user).clearance(user, satellite, &bare),
+            );
+            for (interferer, limit) in &interferers {
+                if constraints.earth_blockage && user.is_occluded(interferer.position()) {
+                    continue;
+                }
+                let angle = user
+                    .separation(satellite, interferer.position())
+                    .to_degrees();
+                lower(&mut analysis.slack.interference_angle, angle - limit);
+            }
+            if let Some(limit) = constraints.geo_arc {
+                let angle = user.geo_arc_separation(satellite).to_degrees();
+                lower(&mut analysis.slack.geo_arc, angle - limit);
+            }
+            let scan = satellite.off_nadir(user).to_degrees();
+            if let Some(limit) = self.payload(a.satellite, &bare).max_scan {
+                lower(&mut analysis.slack.scan, limit - scan);
+            }
+            analysis.scan_angles.add(scan);
+            sum += elevation;
+            analysis.elevations.add(elevation);
+            analysis.slant_ranges.push(look.range_km);
+            if let Some(band) = BANDS.iter().position(|band| *band == a.band) {
+                analysis.band_beams[band] += 1;
+            }
+        }
+
+        for beams in solution.satellites() {
+            let satellite = match satellites.get(&beams[0].satellite) {
+                Some(satellite) => satellite.entity().position(),
+                None => continue,
+            };
+            let beams: Vec<_> = beams
+                .iter()
+                .filter_map(|a| users.get(&a.user).map(|user| (user.position(), a.band)))
+                .collect();
+            for (i, (user, band)) in beams.iter().enumerate() {
+                for (other, _) in beams[i + 1..].iter().filter(|(_, b)| b == band) {
+                    let angle = satellite.separation(user, other).to_degrees();
+                    lower(
+                        &mut analysis.slack.beam_angle,
+                        angle - constraints.beam_angle,
+                    );
+                }
+            }
+        }
+
+        analysis.average_elevation = sum / (analysis.elevations.total() as f64);
+        analysis
+            .slant_ranges
+            .sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let last = analysis.latitudes.len() - 1;
+        for user in self.users() {
+            let geodetic = self.geodetic(user.position());
+            let band = ((geodetic.latitude + 90.0) / LATITUDE_BAND) as usize;
+            let band = &mut analysis.latitudes[band.min(last)];
+            band.users += 1;
+            if solution.by_user(user.id()).is_some() {
+                band.covered += 1;
+            } else {
+                analysis.unserved.push((user.id(), geodetic));
+            }
+        }
+
+        analysis
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.total().max(1);
+        for (i, count) in self.counts.iter().enumerate() {
+            let low = self.min + i as f64 * self.width;
+            writeln!(
+                f,
+                "\t\t[{:5.1}, {:5.1}) {:7} {:5.1}%",
+                low,
+                low + self.width,
+                count,
+                *count as f64 / total as f64 * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Analysis {
+    // The alternate form (`{:#}`) also lists every user without a beam, with
+    // its latitude and longitude.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "\t{} satellites are saturated", self.saturated)?;
+        writeln!(f, "\t{} satellites are unassigned", self.unassigned)?;
+        writeln!(
+            f,
+            "\tA most, {} users can be served ({:4}%)",
+            self.max_possible,
+            self.max_possible_utilization * 100.0
+        )?;
+        writeln!(
+            f,
+            "\t\tCurrent solution covered {} ({:4}%)",
+            self.covered,
+            self.utilization() * 100.0
+        )?;
+        writeln!(f, "\t{} users are completeley uncovered", self.uncovered)?;
+        if self.masked > 0 {
+            writeln!(
+                f,
+                "\t\t{} of them only because of their horizon masks",
+                self.masked
+            )?;
+        }
+        if let Some(rejections) = self.occluded_rejections {
+            writeln!(
+                f,
+                "\t{} links are only usable because interferers below the user's horizon are ignored",
+                rejections
+            )?;
+        }
+        writeln!(
+            f,
+            "\tSolution is overall {:4}% successful, given visiblity and capacity",
+            self.success() * 100.0
+        )?;
+        writeln!(
+            f,
+            "\tAverage dishy elevation is {:4} deg",
+            self.average_elevation
+        )?;
+        writeln!(f, "\tDishy elevation (deg):")?;
+        write!(f, "{}", self.elevations)?;
+        writeln!(f, "\tSatellite scan angle off nadir (deg):")?;
+        write!(f, "{}", self.scan_angles)?;
+        writeln!(
+            f,
+            "\tSlant range (km): min {:.1} p50 {:.1} p90 {:.1} p99 {:.1} max {:.1}",
+            percentile(&self.slant_ranges, 0.0),
+            percentile(&self.slant_ranges, 50.0),
+            percentile(&self.slant_ranges, 90.0),
+            percentile(&self.slant_ranges, 99.0),
+            percentile(&self.slant_ranges, 100.0),
+        )?;
+        let slack = |slack: Option<f64>| match slack {
+            Some(slack) => format!("{:.4}", slack),
+            None => String::from("n/a"),
+        };
+        write!(
+            f,
+            "\tMinimum slack (deg): elevation {}, same-band separation {}, interferer separation {}",
+            slack(self.slack.elevation),
+            slack(self.slack.beam_angle),
+            slack(self.slack.interference_angle),
+        )?;
+        if let Some(geo_arc) = self.slack.geo_arc {
+            write!(f, ", GEO arc {:.4}", geo_arc)?;
+        }
+        if let Some(scan) = self.slack.scan {
+            write!(f, ", scan {:.4}", scan)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "\tBeams per satellite:")?;
+        write!(f, "{}", self.beams_per_satellite)?;
+        writeln!(f, "\tBeams per band:")?;
+        for (i, band) in BANDS.iter().enumerate() {
+            write!(f, "\t\t{} {:7}", band, self.band_beams[i])?;
+            if self.band_saturated[i] > 0 {
+                write!(
+                    f,
+                    " ({} satellites at their band cap)",
+                    self.band_saturated[i]
+                )?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "\tCoverage by latitude (deg):")?;
+        for band in self.latitudes.iter().filter(|band| band.users > 0) {
+            writeln!(
+                f,
+                "\t\t[{:5.1}, {:5.1}) {:7} of {:7} users ({:5.1}%)",
+                band.south,
+                band.south + LATITUDE_BAND,
+                band.covered,
+                band.users,
+                band.covered as f64 / band.users as f64 * 100.0
+            )?;
+        }
+        if !self.classes.is_empty() {
+            writeln!(f, "\tCoverage by terminal class:")?;
+        }
+        for class in self.classes.iter().filter(|class| class.users > 0) {
+            writeln!(
+                f,
+                "\t\t{:12} {:7} of {:7} users ({:5.1}%), {} see no satellite",
+                class.name,
+                class.covered,
+                class.users,
+                class.covered as f64 / class.users as f64 * 100.0,
+                class.uncovered
+            )?;
+        }
+        if !self.priorities.is_empty() {
+            writeln!(
+                f,
+                "\tCovered weight {:.1} of {:.1} ({:5.1}%)",
+                self.covered_weight,
+                self.weight,
+                self.covered_weight / self.weight * 100.0
+            )?;
+            writeln!(f, "\tCoverage by priority:")?;
+        }
+        for tier in &self.priorities {
+            writeln!(
+                f,
+                "\t\tpriority {:3} {:7} of {:7} users ({:5.1}%), weight {:.1} of {:.1}",
+                tier.priority,
+                tier.covered,
+                tier.users,
+                tier.covered as f64 / tier.users as f64 * 100.0,
+                tier.covered_weight,
+                tier.weight
+            )?;
+        }
+        if f.alternate() {
+            writeln!(f, "\tUsers without a beam:")?;
+            for (id, geodetic) in &self.unserved {
+                writeln!(
+                    f,
+                    "\t\tuser {} lat {:.5} lon {:.5}",
+                    id, geodetic.latitude, geodetic.longitude
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::new(45.0, 90.0, 5.0);
+        assert_eq!(histogram.counts.len(), 9);
+        histogram.add(45.0);
+        histogram.add(49.9);
+        histogram.add(50.0);
+        histogram.add(90.0); // Clamped into the last bucket
+        histogram.add(12.0); // Clamped into the first bucket
+        assert_eq!(histogram.counts[0], 3);
+        assert_eq!(histogram.counts[1], 1);
+        assert_eq!(histogram.counts[8], 1);
+        assert_eq!(histogram.total(), 5);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let sorted = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 50.0), 5.0);
+        assert_eq!(percentile(&sorted, 90.0), 9.0);
+        assert_eq!(percentile(&sorted, 100.0), 10.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+sat 1 6921 0 0
+user 2 0 0 6371
+user 3 111.189281412 0 6370.02966584
+sat 2 0 0 6921
+interferer 1 -42164 0 0
+",
+        );
+        let analysis = scenario.analyze(&scenario.optimize());
+        assert_eq!(analysis.covered, 3);
+        assert_eq!(analysis.utilization(), 1.0);
+        assert_eq!(analysis.elevations.total(), 3);
+        assert_eq!(analysis.slant_ranges.len(), 3);
+        assert_eq!(analysis.band_beams, [3, 0, 0, 0]);
+        assert_eq!(analysis.latitudes[9].users, 1);
+        assert_eq!(analysis.latitudes[9].covered, 1);
+        assert_eq!(analysis.latitudes[17].users, 2);
+        assert_eq!(analysis.latitudes[17].covered, 2);
+        assert!(analysis.unserved.is_empty());
+        // User 3 sees satellite 2 at 77.6 degrees, and users 2 and 3 share
+        // satellite 2 and band A about 11.4 degrees apart.
+        assert!((analysis.slack.elevation.unwrap() - 32.59).abs() < 0.01);
+        assert!((analysis.slack.beam_angle.unwrap() - 1.41).abs() < 0.01);
+        assert!((analysis.slack.interference_angle.unwrap() - 67.16).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_margins() {
+        use crate::constraints::Margins;
+
+        let scenario = Scenario::from_str(include_str!(
+            "../beam-planning/test_cases/07_eighteen_planes.txt"
+        ));
+        let planned = Constraints {
+            margins: Margins {
+                elevation: 0.5,
+                beam_angle: 0.5,
+                interference_angle: 0.5,
+                scan: 0.5,
+            },
+            ..Constraints::default()
+        };
+        let solution = scenario.optimize_with(Default::default(), &planned);
+        assert!(scenario.validate_with(&solution, &planned).is_empty());
+        assert!(scenario
+            .validate_with(&solution, &planned.without_margins())
+            .is_empty());
+
+        let slack = scenario.analyze(&solution).slack;
+        assert!(slack.elevation.unwrap() > 0.5);
+        assert!(slack.beam_angle.unwrap() > 0.5);
+        assert_eq!(slack.interference_angle, None); // No interferers
+        let slack = scenario.analyze(&scenario.optimize()).slack;
+        assert!(slack.beam_angle.unwrap() < 0.5);
+    }
+
+    #[test]
+    fn test_earth_blockage() {
+        // A satellite 50 degrees up to the user's north, and an interferer
+        // 5 degrees below the horizon in the same direction. Only 55 degrees
+        // apart, inside the interferer's 60, but the Earth is in the way.
+        let direction = |elevation: f64, range: f64| {
+            let (up, north) = elevation.to_radians().sin_cos();
+            (6371.0 + range * up, range * north)
+        };
+        let (sx, sy) = direction(50.0, 1000.0);
+        let (ix, iy) = direction(-5.0, 40000.0);
+        let scenario = Scenario::from_str(&format!(
+            "user 1 6371 0 0
+sat 1 {} {} 0
+interferer 1 {} {} 0 protect=60",
+            sx, sy, ix, iy
+        ));
+        let strict = Constraints::default();
+        let blockage = Constraints {
+            earth_blockage: true,
+            ..strict
+        };
+        assert!(scenario
+            .optimize_with(Default::default(), &strict)
+            .is_empty());
+        let solution = scenario.optimize_with(Default::default(), &blockage);
+        assert_eq!(solution.covered(), 1);
+        assert!(scenario.validate_with(&solution, &blockage).is_empty());
+        assert!(matches!(
+            scenario.validate_with(&solution, &strict)[..],
+            [crate::validate::Violation::Interference { .. }]
+        ));
+        assert_eq!(scenario.analyze(&solution).occluded_rejections, None);
+        let analysis = scenario.analyze_with(&solution, &blockage);
+        assert_eq!(analysis.occluded_rejections, Some(1));
+        // Links the satellite couldn't serve anyway aren't counted.
+        let limited = Scenario::from_str(&format!(
+            "user 1 6371 0 0
+sat 1 {} {} 0 max_scan=10
+interferer 1 {} {} 0 protect=60",
+            sx, sy, ix, iy
+        ));
+        let analysis = limited.analyze_with(&Solution::default(), &blockage);
+        assert_eq!(analysis.occluded_rejections, Some(0));
+        let no_band = Scenario::from_str(&format!(
+            "class ka bands=D
+user 1 6371 0 0 class=ka
+sat 1 {} {} 0 bands=A,B
+interferer 1 {} {} 0 protect=60",
+            sx, sy, ix, iy
+        ));
+        let analysis = no_band.analyze_with(&Solution::default(), &blockage);
+        assert_eq!(analysis.occluded_rejections, Some(0));
+    }
+
+    #[test]
+    fn test_occluded_slack() {
+        // A user on the equator at 0 degrees, a satellite low in the east and
+        // a GEO interferer at 95 east, behind the Earth from the user but 28
+        // degrees from the satellite, inside its 40. It only counts against
+        // the interference slack when blockage isn't modelled.
+        let geo = 42164.0;
+        let (sin, cos) = 95f64.to_radians().sin_cos();
+        let scenario = Scenario::from_str(&format!(
+            "user 1 6371 0 0
+sat 1 6771 1500 0
+interferer 1 {} {} 0 protect=40",
+            geo * cos,
+            geo * sin
+        ));
+        let solution: Solution = "sat 1 beam 1 user 1 color A".parse().unwrap();
+        let strict = Constraints::default();
+        let blockage = Constraints {
+            earth_blockage: true,
+            ..strict
+        };
+        let slack = scenario.analyze_with(&solution, &strict).slack;
+        assert!((slack.interference_angle.unwrap() + 11.62).abs() < 0.01);
+        assert_eq!(
+            scenario
+                .analyze_with(&solution, &blockage)
+                .slack
+                .interference_angle,
+            None
+        );
+    }
+}
@@ -0,0 +1,425 @@
+// Command line handling for the binary. There are no dependencies to lean on,
+// so this is a small hand-rolled parser: the first argument picks the command,
+// `--name value` (or `--name=value`) sets an option, names listed in SWITCHES
+// are boolean flags, and anything else is positional.
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+    str::FromStr,
+};
+
+use spacex_interview::{
+    constraints::Constraints, json::Format, orbit::OrbitModel, schedule, solver::Strategy,
+    time::Utc,
+};
+
+pub const COMMANDS: &[&str] = &[
+    "solve", "validate", "analyze", "passes", "generate", "convert", "diff", "bench", "help",
+];
+
+const SWITCHES: &[&str] = &[
+    "quiet",
+    "verbose",
+    "analysis",
+    "geo",
+    "look-angles",
+    "orbits",
+    "snapshot",
+    "warm-start",
+    "earth-blockage",
+    "help",
+];
+
+// Options accepted by every command that reads a scenario.
+pub const CONSTRAINT_OPTIONS: &[&str] = &[
+    "beams",
+    "min-elevation",
+    "elevation-model",
+    "beam-angle",
+    "interference-angle",
+    "geo-arc",
+    "earth-blockage",
+    "max-scan",
+    "band-beams",
+    "elevation-margin",
+    "beam-margin",
+    "interference-margin",
+    "scan-margin",
+];
+
+// Options for where a scenario's satellites are: a TLE catalog for entities
+// given by NORAD number, a time to propagate to, and the model to use.
+pub const SCENARIO_OPTIONS: &[&str] = &["tle", "time", "orbit-model", "horizons"];
+
+pub const USAGE: &str = "Usage: spacex_interview [COMMAND] [OPTIONS] [FILES]
+
+Commands:
+  solve [SCENARIO]               Plan beams and write the solution (default)
+  validate SCENARIO [SOLUTION]   Check a solution against the constraints
+  analyze SCENARIO [SOLUTION]    Report solution quality; solves if no solution
+  passes SCENARIO                When each satellite rises above, culminates
+                                 and sets for each user
+  generate users                 Write random users in a latitude band
+  generate constellation SHELL...
+                                 Write satellites for Walker shells, each
+                                 [delta|star:]INC:T/P/F:ALT_KM[:RAAN]
+                                 (as sat_orbit lines with --orbits)
+  convert [SCENARIO]             Rewrite a scenario as text or JSON
+  diff SOLUTION SOLUTION         Compare two solutions user by user
+  bench SCENARIO...              Time the solver on each scenario
+
+Files default to stdin and stdout; `-` also means stdin. Scenarios and
+solutions may be text or JSON, detected from a `.json` extension or a leading
+`{`. Scenario lines may also be `user_geo ID LAT LON [ALT_M]` or `sat_geo ID
+LAT LON ALT_KM`, after an optional `datum spherical|wgs84` line, and
+satellites `sat_orbit ID INC RAAN ARG_LAT ALT_KM` for a circular orbit
+(`interferer_orbit` for another operator's). An interferer line may end in
+`protect=DEG` for its own separation limit. Satellites and interferers may be
+`sat_tle ID` followed by the two lines of a TLE, placed by SGP4, or `sat_norad
+ID NORAD` to look one up in --tle; an `epoch UTC` line sets the scenario's
+time, which otherwise is the newest TLE epoch. A user may be followed by
+`horizon ID AZ:EL ...`, its horizon mask: the least usable elevation by
+azimuth, linear in between, above --min-elevation wherever it is higher;
+--horizons reads such lines from a side file. `class NAME min_elev=DEG
+bands=A,B` defines a terminal class, and a user line ending in `class=NAME`
+uses that elevation limit in place of --min-elevation and only those bands.
+Likewise `shell NAME beams=N bands=A,B band_beams=N|A:N,... max_scan=DEG`
+gives satellites ending in `shell=NAME` their own beam count, bands, beams per
+band and limit on how far off nadir they can point a beam, and the same keys
+on a satellite's own line override them. Users ending in `priority=N weight=W`
+are packed a tier at a time, highest priority first, so none is dropped for a
+user of a lower tier, and heavier users first within a tier; priority defaults
+to 0 and weight to 1.
+
+Options:
+  -o, --output PATH              Write to PATH instead of stdout
+  --format text|json             Output format (json if the output is *.json)
+  --strategy first|elevation     Satellite choice for each user (elevation)
+  --beams N                      Beams per satellite without beams= (32)
+  --band-beams N                 Beams per band on satellites without
+                                 band_beams= (no cap)
+  --min-elevation DEG            User elevation mask (45)
+  --elevation-model MODEL        geocentric (as evaluate.py) or geodetic (WGS84)
+  --beam-angle DEG               Same-band beam separation (10)
+  --interference-angle DEG       Separation from interferers without protect=
+                                 (20)
+  --geo-arc DEG                  Also keep beams DEG from every point of the
+                                 geostationary arc (off)
+  --earth-blockage               Ignore interferers below the user's horizon,
+                                 which evaluate.py still checks
+  --max-scan DEG                 Furthest off nadir a satellite without its own
+                                 max_scan= can point a beam (unlimited)
+  --elevation-margin DEG, --beam-margin DEG, --interference-margin DEG,
+  --scan-margin DEG              Extra clearance from each limit (0)
+  --analysis                     solve: also print the analysis to stderr, or
+                                 include it in JSON output
+  --geo                          solve, analyze: comment each beam with lat/lon,
+                                 or list unserved users with theirs (text only)
+  --look-angles                  solve: comment each beam with the user's
+                                 azimuth, elevation and range (text only)
+  --epochs START:END:STEP|T,...  solve: plan each epoch (seconds), writing an
+                                 `epoch T` line before each solution; validate
+                                 checks such a file epoch by epoch; both
+                                 report handovers per epoch (and per user
+                                 with --verbose); passes: when to sample
+                                 elevations (0:3600:10)
+  --warm-start                   solve --epochs: start each epoch from the
+                                 last plan, keeping users on their satellite
+                                 and band while still valid
+  --epoch T                      convert: move orbit and TLE entities T
+                                 seconds on
+  --snapshot                     convert: write orbit and TLE entities as sat
+                                 and interferer lines, for evaluate.py
+  --orbit-model keplerian|j2     Propagation, with or without J2 drift
+                                 (keplerian); TLEs always use SGP4
+  --tle FILE                     Element sets for sat_norad and
+                                 interferer_norad entities
+  --time UTC                     Move the scenario from its epoch to UTC
+                                 (YYYY-MM-DDTHH:MM:SSZ) before anything else
+  --horizons FILE                Horizon masks for the scenario's users, as
+                                 `horizon` lines
+  --user ID                      passes: only this user
+  --count N, --seed N, --id N    generate: how many, random seed, first id
+  --min-lat DEG, --max-lat DEG   generate: latitude band (-90 to 90)
+  --iterations N                 bench: runs per scenario (1)
+  -q, --quiet                    Only print errors
+  -v, --verbose                  Print progress and timings
+  -h, --help                     Print this message
+
+Exit codes: 0 success, 1 validation failed or solutions differ, 2 usage
+error, 3 I/O error, 4 malformed input.";
+
+#[derive(Debug)]
+pub enum Error {
+    Failed(String),
+    Usage(String),
+    Io(String, io::Error),
+    Input(String),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Failed(_) => 1,
+            Error::Usage(_) => 2,
+            Error::Io(_, _) => 3,
+            Error::Input(_) => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+// Progress messages go to stderr so stdout stays a clean solution.
+#[derive(Debug, Clone, Copy)]
+pub struct Log {
+    verbosity: Verbosity,
+}
+
+impl Log {
+    pub fn info(&self, message: impl fmt::Display) {
+        if self.verbosity >= Verbosity::Normal {
+            eprintln!("{}", message);
+        }
+    }
+
+    pub fn debug(&self, message: impl fmt::Display) {
+        if self.verbosity >= Verbosity::Verbose {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: String,
+    pub positional: Vec<String>,
+    options: HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+impl Args {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, Error> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter().peekable();
+
+        parsed.command = match args.peek() {
+            Some(command) if COMMANDS.contains(&command.as_str()) => args.next().unwrap(),
+            _ => String::from("solve"),
+        };
+
+        while let Some(arg) = args.next() {
+            let name = match arg.as_str() {
+                "-o" => "output",
+                "-q" => "quiet",
+                "-v" => "verbose",
+                "-h" => "help",
+                "-" => {
+                    parsed.positional.push(arg);
+                    continue;
+                }
+                _ => match arg.strip_prefix("--") {
+                    Some(name) => name,
+                    None if arg.starts_with('-') && arg.parse::<f64>().is_err() => {
+                        return Err(Error::Usage(format!("unknown option '{}'", arg)))
+                    }
+                    None => {
+                        parsed.positional.push(arg);
+                        continue;
+                    }
+                },
+            };
+
+            if let Some((name, value)) = name.split_once('=') {
+                parsed.options.insert(name.to_string(), value.to_string());
+            } else if SWITCHES.contains(&name) {
+                parsed.switches.insert(name.to_string());
+            } else {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Error::Usage(format!("--{} needs a value", name)))?;
+                parsed.options.insert(name.to_string(), value);
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    // Reject options the command doesn't understand, so typos aren't ignored.
+    pub fn allow(&self, options: &[&str]) -> Result<(), Error> {
+        let common = ["output", "quiet", "verbose", "help"];
+        match self
+            .options
+            .keys()
+            .chain(self.switches.iter())
+            .find(|name| !options.contains(&name.as_str()) && !common.contains(&name.as_str()))
+        {
+            Some(name) => Err(Error::Usage(format!(
+                "{} does not take --{}",
+                self.command, name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn switch(&self, name: &str) -> bool {
+        self.switches.contains(name)
+    }
+
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    pub fn parsed<T: FromStr>(&self, name: &str) -> Result<Option<T>, Error> {
+        self.option(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| Error::Usage(format!("invalid value '{}' for --{}", value, name)))
+            })
+            .transpose()
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    pub fn strategy(&self) -> Result<Strategy, Error> {
+        Ok(self.parsed("strategy")?.unwrap_or_default())
+    }
+
+    // `--format`, falling back to the output file's extension.
+    pub fn format(&self) -> Result<Format, Error> {
+        Ok(self
+            .parsed("format")?
+            .unwrap_or_else(|| Format::from_path(self.option("output"))))
+    }
+
+    pub fn epochs(&self) -> Result<Option<Vec<f64>>, Error> {
+        self.option("epochs")
+            .map(|epochs| schedule::parse_epochs(epochs).map_err(Error::Usage))
+            .transpose()
+    }
+
+    pub fn orbit_model(&self) -> Result<OrbitModel, Error> {
+        Ok(self.parsed("orbit-model")?.unwrap_or_default())
+    }
+
+    pub fn time(&self) -> Result<Option<Utc>, Error> {
+        self.option("time")
+            .map(|time| time.parse().map_err(Error::Usage))
+            .transpose()
+    }
+
+    pub fn constraints(&self) -> Result<Constraints, Error> {
+        let mut constraints = Constraints::default();
+        if let Some(beams) = self.parsed("beams")? {
+            constraints.beams = beams;
+        }
+        if let Some(elevation) = self.parsed("min-elevation")? {
+            constraints.min_elevation = elevation;
+        }
+        if let Some(model) = self.parsed("elevation-model")? {
+            constraints.elevation_model = model;
+        }
+        if let Some(angle) = self.parsed("beam-angle")? {
+            constraints.beam_angle = angle;
+        }
+        if let Some(angle) = self.parsed("interference-angle")? {
+            constraints.interference_angle = angle;
+        }
+        if let Some(angle) = self.parsed("geo-arc")? {
+            constraints.geo_arc = Some(angle);
+        }
+        if let Some(angle) = self.parsed("max-scan")? {
+            constraints.max_scan = Some(angle);
+        }
+        if let Some(beams) = self.parsed("band-beams")? {
+            constraints.band_beams = Some(beams);
+        }
+        constraints.earth_blockage = self.switch("earth-blockage");
+        if let Some(margin) = self.parsed("elevation-margin")? {
+            constraints.margins.elevation = margin;
+        }
+        if let Some(margin) = self.parsed("beam-margin")? {
+            constraints.margins.beam_angle = margin;
+        }
+        if let Some(margin) = self.parsed("interference-margin")? {
+            constraints.margins.interference_angle = margin;
+        }
+        if let Some(margin) = self.parsed("scan-margin")? {
+            constraints.margins.scan = margin;
+        }
+        Ok(constraints)
+    }
+
+    pub fn log(&self) -> Log {
+        let verbosity = if self.switch("quiet") {
+            Verbosity::Quiet
+        } else if self.switch("verbose") {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        };
+        Log { verbosity }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Failed(message) => write!(f, "{}", message),
+            Error::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            Error::Io(path, error) => write!(f, "{}: {}", path, error),
+            Error::Input(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, Error> {
+        Args::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse("validate scenario.txt -o out.txt --beams=16 --quiet -").unwrap();
+        assert_eq!(args.command, "validate");
+        assert_eq!(args.positional, vec!["scenario.txt", "-"]);
+        assert_eq!(args.option("output"), Some("out.txt"));
+        assert!(args.switch("quiet"));
+        assert_eq!(args.constraints().unwrap().beams, 16);
+
+        let args = parse("scenario.txt --strategy first --min-elevation 35").unwrap();
+        assert_eq!(args.command, "solve");
+        assert_eq!(args.strategy().unwrap(), Strategy::First);
+        assert_eq!(args.constraints().unwrap().min_elevation, 35.0);
+
+        let args = parse("scenario.txt --beam-margin 0.5 --scan-margin 2").unwrap();
+        let margins = args.constraints().unwrap().margins;
+        assert_eq!((margins.beam_angle, margins.scan), (0.5, 2.0));
+
+        let args = parse("analyze scenario.txt --earth-blockage").unwrap();
+        assert!(args.allow(CONSTRAINT_OPTIONS).is_ok());
+        assert!(args.constraints().unwrap().earth_blockage);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("solve --beams").unwrap_err().exit_code(), 2);
+        assert_eq!(parse("solve -x").unwrap_err().exit_code(), 2);
+        let args = parse("solve --strategy best").unwrap();
+        assert_eq!(args.strategy().unwrap_err().exit_code(), 2);
+        let args = parse("diff a b --beams 3").unwrap();
+        assert!(args.allow(&[]).is_err());
+        assert!(args.allow(CONSTRAINT_OPTIONS).is_ok());
+    }
+}
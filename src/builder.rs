@@ -0,0 +1,432 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    geodesy::Datum,
+    horizon::HorizonMask,
+    orbit::Orbit,
+    payload::ShellParams,
+    position::{Position, EARTH_RADIUS},
+    scenario::{Entity, EntityKind, Scenario},
+    terminal::TerminalClass,
+};
+
+// How far a user may be from the mean Earth radius, in km. Generous enough for
+// the ellipsoid's ~21km of flattening and for aircraft, tight enough to catch
+// coordinates in meters or a satellite listed as a user.
+pub const MAX_USER_ALTITUDE: f64 = 100.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    DuplicateId { kind: EntityKind, id: i32 },
+    NonFinite { kind: EntityKind, id: i32 },
+    UserNotOnSurface { id: i32, altitude: f64 },
+    SatelliteInsideEarth { id: i32, altitude: f64 },
+    UnknownEntity { kind: EntityKind, id: i32 },
+    InvalidAttribute(String),
+}
+
+// Builds a `Scenario` from code, checking each entity as it is added. Ids must
+// be unique within a kind; users, satellites and interferers may share ids,
+// as they do in the test cases. Attributes are checked as a scenario's are
+// when it is read, so an entity must be added before its attributes.
+pub struct ScenarioBuilder {
+    scenario: Scenario,
+    ids: HashSet<(EntityKind, i32)>,
+}
+
+impl Default for ScenarioBuilder {
+    fn default() -> ScenarioBuilder {
+        ScenarioBuilder {
+            scenario: Scenario::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
+impl ScenarioBuilder {
+    pub fn new() -> ScenarioBuilder {
+        ScenarioBuilder::default()
+    }
+
+    pub fn add_user(&mut self, id: i32, position: Position) -> Result<&mut Self, BuildError> {
+        self.check(EntityKind::User, id, &position)?;
+        let altitude = position.len() - EARTH_RADIUS;
+        if altitude.abs() > MAX_USER_ALTITUDE {
+            return Err(BuildError::UserNotOnSurface { id, altitude });
+        }
+        self.ids.insert((EntityKind::User, id));
+        self.scenario
+            .push(EntityKind::User, Entity::new(id, position));
+        Ok(self)
+    }
+
+    pub fn add_satellite(&mut self, id: i32, position: Position) -> Result<&mut Self, BuildError> {
+        self.check(EntityKind::Satellite, id, &position)?;
+        let altitude = position.len() - EARTH_RADIUS;
+        if altitude <= 0.0 {
+            return Err(BuildError::SatelliteInsideEarth { id, altitude });
+        }
+        self.ids.insert((EntityKind::Satellite, id));
+        self.scenario
+            .push(EntityKind::Satellite, Entity::new(id, position));
+        Ok(self)
+    }
+
+    // A satellite on a circular orbit, placed where the orbit puts it at the
+    // epoch and moved along it by `Scenario::propagate`.
+    pub fn add_orbit(&mut self, id: i32, orbit: Orbit) -> Result<&mut Self, BuildError> {
+        self.add_satellite(id, orbit.eci())?;
+        self.scenario
+            .orbits
+            .insert((EntityKind::Satellite, id), orbit);
+        Ok(self)
+    }
+
+    pub fn add_interferer(&mut self, id: i32, position: Position) -> Result<&mut Self, BuildError> {
+        self.check(EntityKind::Interferer, id, &position)?;
+        self.ids.insert((EntityKind::Interferer, id));
+        self.scenario
+            .push(EntityKind::Interferer, Entity::new(id, position));
+        Ok(self)
+    }
+
+    // Another operator's satellite, e.g. in LEO or MEO, moved along its orbit
+    // like our own.
+    pub fn add_interferer_orbit(&mut self, id: i32, orbit: Orbit) -> Result<&mut Self, BuildError> {
+        self.add_interferer(id, orbit.eci())?;
+        self.scenario
+            .orbits
+            .insert((EntityKind::Interferer, id), orbit);
+        Ok(self)
+    }
+
+    // Sets one `key=value` attribute of an entity already added, replacing
+    // any earlier value for the key. A value the scenario can't use is an
+    // error and leaves the entity's attributes as they were.
+    pub fn attribute(
+        &mut self,
+        kind: EntityKind,
+        id: i32,
+        key: &str,
+        value: &str,
+    ) -> Result<&mut Self, BuildError> {
+        if !self.ids.contains(&(kind, id)) {
+            return Err(BuildError::UnknownEntity { kind, id });
+        }
+        let previous = self.scenario.attributes(kind, id).to_vec();
+        let mut attributes = previous.clone();
+        match attributes.iter_mut().find(|(k, _)| k == key) {
+            Some(existing) => existing.1 = value.to_string(),
+            None => attributes.push((key.to_string(), value.to_string())),
+        }
+        self.scenario.set_attributes(kind, id, attributes);
+        if let Err(message) = self.scenario.check_attributes(kind, id) {
+            self.scenario.attributes.remove(&(kind, id));
+            self.scenario.set_attributes(kind, id, previous);
+            return Err(BuildError::InvalidAttribute(message));
+        }
+        Ok(self)
+    }
+
+    // A horizon mask for a user already added, replacing any earlier one.
+    pub fn horizon(&mut self, user: i32, mask: HorizonMask) -> Result<&mut Self, BuildError> {
+        if !self.ids.contains(&(EntityKind::User, user)) {
+            return Err(BuildError::UnknownEntity {
+                kind: EntityKind::User,
+                id: user,
+            });
+        }
+        self.scenario.horizons.insert(user, mask);
+        Ok(self)
+    }
+
+    // A user's priority tier, higher served first, and its weight within the
+    // tier, as its `priority` and `weight` attributes.
+    pub fn priority(&mut self, user: i32, priority: i32) -> Result<&mut Self, BuildError> {
+        self.attribute(EntityKind::User, user, "priority", &priority.to_string())
+    }
+
+    pub fn weight(&mut self, user: i32, weight: f64) -> Result<&mut Self, BuildError> {
+        self.attribute(EntityKind::User, user, "weight", &weight.to_string())
+    }
+
+    // A terminal class for users' `class` attributes to name, replacing an
+    // earlier class of the same name.
+    pub fn class(&mut self, class: TerminalClass) -> &mut Self {
+        self.scenario.add_class(class);
+        self
+    }
+
+    // Payload settings for satellites' `shell` attributes to name, replacing
+    // earlier settings of the same name. Not to be confused with `add_shell`,
+    // which adds a Walker shell's satellites.
+    pub fn shell(&mut self, shell: ShellParams) -> &mut Self {
+        self.scenario.add_shell(shell);
+        self
+    }
+
+    // The datum for reporting positions as latitude and longitude; positions
+    // are added as x/y/z either way.
+    pub fn datum(&mut self, datum: Datum) -> &mut Self {
+        self.scenario.datum = datum;
+        self
+    }
+
+    pub fn build(self) -> Scenario {
+        self.scenario
+    }
+
+    // Checks common to every kind. Ids are only recorded once an entity is
+    // added, so a rejected add can be retried with corrected values.
+    fn check(&self, kind: EntityKind, id: i32, position: &Position) -> Result<(), BuildError> {
+        if ![position.x, position.y, position.z]
+            .iter()
+            .all(|c| c.is_finite())
+        {
+            return Err(BuildError::NonFinite { kind, id });
+        }
+        if self.ids.contains(&(kind, id)) {
+            return Err(BuildError::DuplicateId { kind, id });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::DuplicateId { kind, id } => write!(f, "duplicate {} id {}", kind, id),
+            BuildError::NonFinite { kind, id } => {
+                write!(f, "{} {} has a non-finite coordinate", kind, id)
+            }
+            BuildError::UserNotOnSurface { id, altitude } => write!(
+                f,
+                "user {} is {}km from the Earth's surface (max {}km)",
+                id, altitude, MAX_USER_ALTITUDE
+            ),
+            BuildError::SatelliteInsideEarth { id, altitude } => {
+                write!(
+                    f,
+                    "sat {} is inside the Earth ({}km altitude)",
+                    id, altitude
+                )
+            }
+            BuildError::UnknownEntity { kind, id } => {
+                write!(f, "no {} {} in the scenario", kind, id)
+            }
+            BuildError::InvalidAttribute(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build() -> Result<(), BuildError> {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .add_user(1, Position::new(6371.0, 0.0, 0.0))?
+            .add_satellite(1, Position::new(6921.0, 0.0, 0.0))?
+            .add_interferer(1, Position::new(-42164.0, 0.0, 0.0))?;
+        let scenario = builder.build();
+        assert_eq!(scenario.users().len(), 1);
+        assert_eq!(scenario.satellites().len(), 1);
+        assert_eq!(scenario.interferers().len(), 1);
+
+        assert_eq!(
+            format!("{}", scenario.optimize()),
+            "sat 1 beam 1 user 1 color A"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let mut builder = ScenarioBuilder::new();
+        let surface = Position::new(6371.0, 0.0, 0.0);
+        builder.add_user(1, surface).unwrap();
+        assert_eq!(
+            builder.add_user(1, surface).err(),
+            Some(BuildError::DuplicateId {
+                kind: EntityKind::User,
+                id: 1
+            })
+        );
+        assert_eq!(
+            builder.add_user(2, Position::new(f64::NAN, 0.0, 0.0)).err(),
+            Some(BuildError::NonFinite {
+                kind: EntityKind::User,
+                id: 2
+            })
+        );
+        assert!(matches!(
+            builder.add_user(2, Position::new(6921.0, 0.0, 0.0)),
+            Err(BuildError::UserNotOnSurface { id: 2, .. })
+        ));
+        assert!(matches!(
+            builder.add_satellite(1, Position::new(0.0, 0.0, 6000.0)),
+            Err(BuildError::SatelliteInsideEarth { id: 1, .. })
+        ));
+        // Rejected entities don't reserve their ids.
+        assert!(builder.add_user(2, surface).is_ok());
+        assert!(builder
+            .add_satellite(1, Position::new(6921.0, 0.0, 0.0))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_horizon() -> Result<(), BuildError> {
+        let hills = HorizonMask::new(vec![(0.0, 65.0)]).unwrap();
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .add_user(1, Position::new(6371.0, 0.0, 0.0))?
+            .add_satellite(1, Position::new(6921.0, 0.0, 300.0))?
+            .horizon(1, hills.clone())?;
+        assert_eq!(
+            builder.horizon(2, hills.clone()).err(),
+            Some(BuildError::UnknownEntity {
+                kind: EntityKind::User,
+                id: 2
+            })
+        );
+        // The satellite is about 61 degrees up: in view, but below the mask.
+        let scenario = builder.build();
+        assert_eq!(scenario.horizons.len(), 1);
+        assert_eq!(scenario.horizon(1), Some(&hills));
+        assert_eq!(format!("{}", scenario.optimize()), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_priority_and_weight() -> Result<(), BuildError> {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .add_user(1, Position::new(6371.0, 0.0, 0.0))?
+            .add_user(2, Position::new(6371.0, 0.0, 0.0))?
+            .priority(2, 1)?
+            .weight(2, 2.5)?;
+        assert_eq!(
+            builder.weight(1, f64::NAN).err(),
+            Some(BuildError::InvalidAttribute(String::from(
+                "user 1: invalid weight 'NaN'"
+            )))
+        );
+        assert!(matches!(
+            builder.priority(3, 1),
+            Err(BuildError::UnknownEntity { id: 3, .. })
+        ));
+        let scenario = builder.build();
+        assert_eq!((scenario.priority(1), scenario.weight(1)), (0, 1.0));
+        assert_eq!((scenario.priority(2), scenario.weight(2)), (1, 2.5));
+        let tiers: Vec<i32> = scenario.tiers().iter().map(|(tier, _)| *tier).collect();
+        assert_eq!(tiers, vec![1, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_class() -> Result<(), BuildError> {
+        let class = |min_elev: &str| {
+            TerminalClass::new("maritime", vec![("min_elev".into(), min_elev.into())]).unwrap()
+        };
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .add_user(1, Position::new(6371.0, 0.0, 0.0))?
+            .add_satellite(1, Position::new(6921.0, 0.0, 300.0))?;
+        assert_eq!(
+            builder
+                .attribute(EntityKind::User, 1, "class", "maritime")
+                .err(),
+            Some(BuildError::InvalidAttribute(String::from(
+                "user 1: undefined class 'maritime'"
+            )))
+        );
+        builder.class(class("50")).class(class("70")).attribute(
+            EntityKind::User,
+            1,
+            "class",
+            "maritime",
+        )?;
+        // The satellite is about 61 degrees up, below the later definition.
+        let scenario = builder.build();
+        assert_eq!(scenario.classes(), &[class("70")]);
+        assert_eq!(format!("{}", scenario.optimize()), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_shell() -> Result<(), BuildError> {
+        let shell = |max_scan: &str| {
+            ShellParams::new("v1", vec![("max_scan".into(), max_scan.into())]).unwrap()
+        };
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .add_user(1, Position::new(6371.0, 0.0, 0.0))?
+            .add_satellite(1, Position::new(6921.0, 0.0, 300.0))?;
+        assert_eq!(
+            builder
+                .attribute(EntityKind::Satellite, 1, "shell", "v1")
+                .err(),
+            Some(BuildError::InvalidAttribute(String::from(
+                "sat 1: undefined shell 'v1'"
+            )))
+        );
+        builder.shell(shell("30")).shell(shell("20"));
+        builder.attribute(EntityKind::Satellite, 1, "shell", "v1")?;
+        // The user is about 26 degrees off nadir, past the later definition.
+        let scenario = builder.build();
+        assert_eq!(scenario.shells(), &[shell("20")]);
+        assert_eq!(format!("{}", scenario.optimize()), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_datum() -> Result<(), BuildError> {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .datum(Datum::Wgs84)
+            .add_user(1, Position::new(6371.0, 0.0, 0.0))?;
+        let scenario = builder.build();
+        assert_eq!(scenario.datum(), Datum::Wgs84);
+        assert!(scenario.to_input().starts_with("datum wgs84\n"));
+        assert_eq!(ScenarioBuilder::new().build().datum(), Datum::Spherical);
+        Ok(())
+    }
+
+    #[test]
+    fn test_attribute() -> Result<(), BuildError> {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .add_user(1, Position::new(6371.0, 0.0, 0.0))?
+            .add_interferer(1, Position::new(-42164.0, 0.0, 0.0))?
+            .attribute(EntityKind::User, 1, "weight", "2")?
+            .attribute(EntityKind::User, 1, "weight", "3")?
+            .attribute(EntityKind::Interferer, 1, "protect", "15")?;
+        assert_eq!(
+            builder.attribute(EntityKind::User, 2, "weight", "2").err(),
+            Some(BuildError::UnknownEntity {
+                kind: EntityKind::User,
+                id: 2
+            })
+        );
+        assert_eq!(
+            builder
+                .attribute(EntityKind::Interferer, 1, "protect", "-5")
+                .err(),
+            Some(BuildError::InvalidAttribute(String::from(
+                "interferer 1: invalid protect '-5'"
+            )))
+        );
+        // A rejected value leaves the earlier one in place.
+        let scenario = builder.build();
+        assert_eq!(scenario.attribute(EntityKind::User, 1, "weight"), Some("3"));
+        assert_eq!(
+            scenario.attribute(EntityKind::Interferer, 1, "protect"),
+            Some("15")
+        );
+        Ok(())
+    }
+}
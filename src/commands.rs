@@ -0,0 +1,686 @@
+// One function per subcommand. Each reads its inputs, calls into the library,
+// and writes its output; anything beyond that belongs in the library.
+use std::{
+    f64::consts::PI,
+    fs,
+    io::{self, Read, Write},
+    time::Instant,
+};
+
+use spacex_interview::{
+    constellation::Shell,
+    constraints::Constraints,
+    json::Format,
+    position::Position,
+    scenario::{Entity, Scenario},
+    schedule::Schedule,
+    solution::{Assignment, ParseError, Solution},
+};
+
+use crate::args::{Args, Error, CONSTRAINT_OPTIONS, SCENARIO_OPTIONS, USAGE};
+
+pub fn run(args: &Args) -> Result<(), Error> {
+    if args.switch("help") || args.command == "help" {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    match args.command.as_str() {
+        "solve" => solve(args),
+        "validate" => validate(args),
+        "analyze" => analyze(args),
+        "passes" => passes(args),
+        "generate" => generate(args),
+        "convert" => convert(args),
+        "diff" => diff(args),
+        "bench" => bench(args),
+        command => Err(Error::Usage(format!("unknown command '{}'", command))),
+    }
+}
+
+fn solve(args: &Args) -> Result<(), Error> {
+    args.allow(
+        &[
+            CONSTRAINT_OPTIONS,
+            SCENARIO_OPTIONS,
+            &[
+                "strategy",
+                "analysis",
+                "format",
+                "geo",
+                "look-angles",
+                "epochs",
+                "warm-start",
+            ],
+        ]
+        .concat(),
+    )?;
+    let log = args.log();
+    let constraints = args.constraints()?;
+    let format = output_format(args)?;
+    let scenario = load_scenario(args, args.positional(0))?;
+    log.info(format!(
+        "Read scenario\n\t{} users\n\t{} sats\n\t{} interferrers",
+        scenario.users().len(),
+        scenario.satellites().len(),
+        scenario.interferers().len(),
+    ));
+    if let Some(epochs) = args.epochs()? {
+        return solve_epochs(args, &scenario, &epochs, format, &constraints);
+    }
+    if args.switch("warm-start") {
+        return Err(Error::Usage(String::from("--warm-start needs --epochs")));
+    }
+
+    let start = Instant::now();
+    let solution = scenario.optimize_with(args.strategy()?, &constraints);
+    log.info(format!("Assigned {} users", solution.covered()));
+    if scenario.is_weighted() {
+        log.info(format!(
+            "Covered weight {}",
+            scenario.covered_weight(&solution)
+        ));
+    }
+    log.debug(format!("Solved in {:?}", start.elapsed()));
+
+    let analysis = if args.switch("analysis") {
+        Some(scenario.analyze_with(&solution, &constraints))
+    } else {
+        None
+    };
+    match format {
+        Format::Json => write_output(
+            args.option("output"),
+            &format!("{}\n", solution.to_json(analysis.as_ref())),
+        ),
+        Format::Text if args.switch("geo") || args.switch("look-angles") => {
+            let output = annotate(&scenario, &solution, args, &constraints);
+            write_output(args.option("output"), &output)?;
+            if let Some(analysis) = analysis {
+                log.info(format!("Analysis: \n{:#}", analysis));
+            }
+            Ok(())
+        }
+        Format::Text => {
+            write_output(args.option("output"), &format!("{}\n", solution))?;
+            if let Some(analysis) = analysis {
+                log.info(format!("Analysis: \n{}", analysis));
+            }
+            Ok(())
+        }
+    }
+}
+
+// One plan per epoch, each against the scenario propagated to it.
+fn solve_epochs(
+    args: &Args,
+    scenario: &Scenario,
+    epochs: &[f64],
+    format: Format,
+    constraints: &Constraints,
+) -> Result<(), Error> {
+    if format == Format::Json || args.switch("geo") || args.switch("look-angles") {
+        return Err(Error::Usage(String::from(
+            "--epochs only writes plain text solutions",
+        )));
+    }
+    let log = args.log();
+    let start = Instant::now();
+    let (model, strategy) = (args.orbit_model()?, args.strategy()?);
+    let schedule = if args.switch("warm-start") {
+        scenario.optimize_over_warm(epochs, model, strategy, constraints)
+    } else {
+        scenario.optimize_over(epochs, model, strategy, constraints)
+    };
+    log.debug(format!(
+        "Solved {} epochs in {:?}",
+        schedule.len(),
+        start.elapsed()
+    ));
+    log_epochs(args, &schedule, scenario.users().len());
+    write_output(args.option("output"), &format!("{}\n", schedule))
+}
+
+fn validate(args: &Args) -> Result<(), Error> {
+    args.allow(&[CONSTRAINT_OPTIONS, SCENARIO_OPTIONS].concat())?;
+    let log = args.log();
+    let scenario_path = args
+        .positional(0)
+        .ok_or_else(|| Error::Usage(String::from("validate needs a scenario")))?;
+    let scenario = load_scenario(args, Some(scenario_path))?;
+    let input = read_input(args.positional(1))?;
+    if is_schedule(&input) {
+        return validate_epochs(args, &scenario, &input);
+    }
+    let solution = parse_solution(args.positional(1), &input)?;
+
+    let violations = scenario.validate_with(&solution, &args.constraints()?);
+    let report: String = violations.iter().map(|v| format!("{}\n", v)).collect();
+    write_output(args.option("output"), &report)?;
+
+    log.info(format!(
+        "{}% of {} total users covered.",
+        solution.covered() as f64 / scenario.users().len() as f64 * 100.0,
+        scenario.users().len()
+    ));
+    if violations.is_empty() {
+        log.info("Solution passed all checks!");
+        Ok(())
+    } else {
+        Err(Error::Failed(format!(
+            "Solution failed with {} violations",
+            violations.len()
+        )))
+    }
+}
+
+fn validate_epochs(args: &Args, scenario: &Scenario, input: &str) -> Result<(), Error> {
+    let log = args.log();
+    let schedule: Schedule = input.parse().map_err(|e: ParseError| {
+        Error::Input(format!("{}: {}", args.positional(1).unwrap_or("stdin"), e))
+    })?;
+    let violations =
+        scenario.validate_schedule(&schedule, args.orbit_model()?, &args.constraints()?);
+    let report: String = violations
+        .iter()
+        .map(|(seconds, v)| format!("epoch {}: {}\n", seconds, v))
+        .collect();
+    write_output(args.option("output"), &report)?;
+
+    log_epochs(args, &schedule, scenario.users().len());
+    if violations.is_empty() {
+        log.info(format!("All {} epochs passed all checks!", schedule.len()));
+        Ok(())
+    } else {
+        Err(Error::Failed(format!(
+            "Schedule failed with {} violations",
+            violations.len()
+        )))
+    }
+}
+
+// Coverage and handovers at each epoch, and with --verbose the handovers of
+// each user.
+fn log_epochs(args: &Args, schedule: &Schedule, users: usize) {
+    let log = args.log();
+    // Nobody is handed over at the first epoch.
+    let handovers = std::iter::once(0).chain(
+        schedule
+            .handovers_per_epoch()
+            .into_iter()
+            .map(|(_, count)| count),
+    );
+    for ((seconds, solution), handovers) in schedule.epochs().iter().zip(handovers) {
+        log.info(format!(
+            "Epoch {}: {}% of {} total users covered, {} handovers.",
+            seconds,
+            solution.covered() as f64 / users as f64 * 100.0,
+            users,
+            handovers
+        ));
+    }
+    for (user, count) in schedule.handovers_per_user() {
+        log.debug(format!("User {}: {} handovers", user, count));
+    }
+}
+
+fn analyze(args: &Args) -> Result<(), Error> {
+    args.allow(
+        &[
+            CONSTRAINT_OPTIONS,
+            SCENARIO_OPTIONS,
+            &["strategy", "format", "geo"],
+        ]
+        .concat(),
+    )?;
+    let constraints = args.constraints()?;
+    let format = output_format(args)?;
+    let scenario = load_scenario(args, args.positional(0))?;
+    let solution = match args.positional(1) {
+        Some(path) => read_solution(Some(path))?,
+        None => scenario.optimize_with(args.strategy()?, &constraints),
+    };
+    let analysis = scenario.analyze_with(&solution, &constraints);
+    let report = match format {
+        Format::Json => format!("{}\n", analysis.to_json().pretty()),
+        Format::Text if args.switch("geo") => format!("{:#}", analysis),
+        Format::Text => format!("{}", analysis),
+    };
+    write_output(args.option("output"), &report)
+}
+
+// Pass tables for every user, or just --user.
+fn passes(args: &Args) -> Result<(), Error> {
+    args.allow(&[CONSTRAINT_OPTIONS, SCENARIO_OPTIONS, &["epochs", "user"]].concat())?;
+    let scenario = load_scenario(args, args.positional(0))?;
+    let epochs = match args.epochs()? {
+        Some(epochs) => epochs,
+        None => (0..=360).map(|i| i as f64 * 10.0).collect(),
+    };
+    let users: Vec<Entity> = match args.parsed::<i32>("user")? {
+        Some(id) => match scenario.users_by_id().get(&id) {
+            Some(user) => vec![**user],
+            None => return Err(Error::Usage(format!("no user {} in the scenario", id))),
+        },
+        None => scenario.users().clone(),
+    };
+    let start = Instant::now();
+    let tables = scenario.passes(&users, &epochs, args.orbit_model()?, &args.constraints()?);
+    args.log()
+        .debug(format!("Found passes in {:?}", start.elapsed()));
+    let report: String = tables.iter().map(|table| table.to_string()).collect();
+    write_output(args.option("output"), &report)
+}
+
+fn generate(args: &Args) -> Result<(), Error> {
+    args.allow(&["count", "seed", "id", "min-lat", "max-lat", "orbits"])?;
+    match args.positional(0) {
+        Some("users") => generate_users(args),
+        Some("constellation") => generate_constellation(args),
+        Some(kind) => Err(Error::Usage(format!("cannot generate '{}'", kind))),
+        None => Err(Error::Usage(String::from("generate needs a kind"))),
+    }
+}
+
+// Users uniformly distributed over the sphere's surface between two latitudes,
+// at the radius used by the test cases.
+fn generate_users(args: &Args) -> Result<(), Error> {
+    const RADIUS: f64 = 6371.0;
+    let count: usize = args
+        .parsed("count")?
+        .ok_or_else(|| Error::Usage(String::from("generate users needs --count")))?;
+    let id: i32 = args.parsed("id")?.unwrap_or(1);
+    let min_lat: f64 = args.parsed("min-lat")?.unwrap_or(-90.0);
+    let max_lat: f64 = args.parsed("max-lat")?.unwrap_or(90.0);
+    if !(-90.0..=90.0).contains(&min_lat) || !(min_lat..=90.0).contains(&max_lat) {
+        return Err(Error::Usage(String::from(
+            "latitudes must satisfy -90 <= --min-lat <= --max-lat <= 90",
+        )));
+    }
+    let mut random = Random::new(args.parsed("seed")?.unwrap_or(1));
+
+    // Uniform in sin(latitude) is uniform in area.
+    let (low, high) = (min_lat.to_radians().sin(), max_lat.to_radians().sin());
+    let lines: String = (0..count)
+        .map(|i| {
+            let z = low + (high - low) * random.next();
+            let longitude = 2.0 * PI * random.next();
+            let r = (1.0 - z * z).max(0.0).sqrt();
+            let position = Position::new(
+                RADIUS * r * longitude.cos(),
+                RADIUS * r * longitude.sin(),
+                RADIUS * z,
+            );
+            format!("user {} {}\n", id + i as i32, position)
+        })
+        .collect();
+    write_output(args.option("output"), &lines)
+}
+
+// Satellites for one or more Walker shells, numbered consecutively. With
+// --orbits they are written as orbits, so they can be propagated.
+fn generate_constellation(args: &Args) -> Result<(), Error> {
+    if args.positional.len() < 2 {
+        return Err(Error::Usage(String::from(
+            "generate constellation needs at least one shell",
+        )));
+    }
+    let mut id: i32 = args.parsed("id")?.unwrap_or(1);
+    let mut lines = String::new();
+    for spec in &args.positional[1..] {
+        let shell: Shell = spec.parse().map_err(|e| Error::Usage(format!("{}", e)))?;
+        for orbit in shell.orbits() {
+            if args.switch("orbits") {
+                lines.push_str(&format!("sat_orbit {} {}\n", id, orbit));
+            } else {
+                lines.push_str(&format!("sat {} {}\n", id, orbit.eci()));
+            }
+            id += 1;
+        }
+    }
+    write_output(args.option("output"), &lines)
+}
+
+fn convert(args: &Args) -> Result<(), Error> {
+    args.allow(&[SCENARIO_OPTIONS, &["format", "epoch", "snapshot"]].concat())?;
+    let mut scenario = load_scenario(args, args.positional(0))?;
+    if let Some(seconds) = args.parsed("epoch")? {
+        scenario = scenario.propagate(seconds, args.orbit_model()?);
+    }
+    if args.switch("snapshot") {
+        scenario.drop_orbits();
+    }
+    let output = match args.format()? {
+        Format::Json => scenario.to_json(),
+        Format::Text => scenario.to_input(),
+    };
+    write_output(args.option("output"), &format!("{}\n", output))
+}
+
+// Report users whose assignment differs between two solutions: `-` only in
+// the first, `+` only in the second, `~` on a different satellite or band.
+fn diff(args: &Args) -> Result<(), Error> {
+    args.allow(&[])?;
+    let log = args.log();
+    let (a, b) = match args.positional.as_slice() {
+        [a, b] => (a, b),
+        _ => return Err(Error::Usage(String::from("diff needs two solutions"))),
+    };
+    let (a, b) = (read_solution(Some(a))?, read_solution(Some(b))?);
+
+    let mut users: Vec<i32> = a.iter().chain(b.iter()).map(|a| a.user).collect();
+    users.sort_unstable();
+    users.dedup();
+
+    let describe = |a: &Assignment| format!("sat {} color {}", a.satellite, a.band);
+    let mut lines = Vec::new();
+    for user in users {
+        match (a.by_user(user), b.by_user(user)) {
+            (Some(a), None) => lines.push(format!("- user {} {}", user, describe(a))),
+            (None, Some(b)) => lines.push(format!("+ user {} {}", user, describe(b))),
+            (Some(a), Some(b)) if (a.satellite, a.band) != (b.satellite, b.band) => lines.push(
+                format!("~ user {} {} -> {}", user, describe(a), describe(b)),
+            ),
+            _ => (),
+        }
+    }
+
+    let report: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+    write_output(args.option("output"), &report)?;
+    log.info(format!(
+        "{} users in first, {} in second, {} differ",
+        a.covered(),
+        b.covered(),
+        lines.len()
+    ));
+    if lines.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Failed(String::from("Solutions differ")))
+    }
+}
+
+// Times the solver alone; each scenario is loaded as `solve` loads it, once,
+// before the clock starts.
+fn bench(args: &Args) -> Result<(), Error> {
+    args.allow(
+        &[
+            CONSTRAINT_OPTIONS,
+            SCENARIO_OPTIONS,
+            &["strategy", "iterations"],
+        ]
+        .concat(),
+    )?;
+    let constraints = args.constraints()?;
+    let strategy = args.strategy()?;
+    let iterations: u32 = args.parsed("iterations")?.unwrap_or(1).max(1);
+    if args.positional.is_empty() {
+        return Err(Error::Usage(String::from("bench needs a scenario")));
+    }
+
+    let mut report = String::new();
+    for path in &args.positional {
+        let scenario = load_scenario(args, Some(path))?;
+        let mut assigned = 0;
+        let start = Instant::now();
+        for _ in 0..iterations {
+            assigned = scenario.optimize_with(strategy, &constraints).covered();
+        }
+        let elapsed = start.elapsed() / iterations;
+        report.push_str(&format!(
+            "{}\t{} of {} users\t{:.3} ms\n",
+            path,
+            assigned,
+            scenario.users().len(),
+            elapsed.as_secs_f64() * 1000.0
+        ));
+    }
+    write_output(args.option("output"), &report)
+}
+
+// `--format`, refusing switches that only add comments to text output.
+fn output_format(args: &Args) -> Result<Format, Error> {
+    match args.format()? {
+        Format::Json => match ["geo", "look-angles"].iter().find(|s| args.switch(s)) {
+            Some(switch) => Err(Error::Usage(format!(
+                "--{} only applies to text output",
+                switch
+            ))),
+            None => Ok(Format::Json),
+        },
+        Format::Text => Ok(Format::Text),
+    }
+}
+
+// Solution lines, each followed by a comment with the user's and satellite's
+// location (--geo) and where the user looks to find the satellite
+// (--look-angles). evaluate.py skips comment lines, so the output still
+// validates.
+fn annotate(
+    scenario: &Scenario,
+    solution: &Solution,
+    args: &Args,
+    constraints: &Constraints,
+) -> String {
+    let users = scenario.users_by_id();
+    let satellites = scenario.satellites_by_id();
+    let mut output = String::new();
+    for a in solution.iter() {
+        output.push_str(&format!("{}\n", a));
+        let (user, satellite) = match (users.get(&a.user), satellites.get(&a.satellite)) {
+            (Some(user), Some(satellite)) => (user.position(), satellite.entity().position()),
+            _ => continue,
+        };
+        let mut notes = vec![format!("user {}", a.user), format!("sat {}", a.satellite)];
+        if args.switch("geo") {
+            let geodetic = scenario.geodetic(user);
+            notes[0].push_str(&format!(
+                " lat {:.5} lon {:.5}",
+                geodetic.latitude, geodetic.longitude
+            ));
+            notes[1].push_str(&format!(" {}", scenario.geodetic(satellite)));
+        }
+        if args.switch("look-angles") {
+            let look = user.look_angles(satellite, constraints.elevation_model);
+            notes.push(look.to_string());
+        }
+        output.push_str(&format!("# {}\n", notes.join("; ")));
+    }
+    output
+}
+
+fn read_input(path: Option<&str>) -> Result<String, Error> {
+    match path {
+        None | Some("-") => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| Error::Io(String::from("stdin"), e))?;
+            Ok(input)
+        }
+        Some(path) => fs::read_to_string(path).map_err(|e| Error::Io(path.to_string(), e)),
+    }
+}
+
+fn read_scenario(path: Option<&str>) -> Result<Scenario, Error> {
+    let input = read_input(path)?;
+    match Format::detect(path, &input) {
+        Format::Json => Scenario::from_json(&input)
+            .map_err(|e| Error::Input(format!("{}: {}", path.unwrap_or("stdin"), e))),
+        Format::Text => Scenario::parse(&input)
+            .map_err(|e| Error::Input(format!("{}: {}", path.unwrap_or("stdin"), e))),
+    }
+}
+
+// The scenario with --tle and --time applied. Entities whose element set
+// is missing or fails to propagate are input errors rather than being left
+// without a position.
+fn load_scenario(args: &Args, path: Option<&str>) -> Result<Scenario, Error> {
+    let mut scenario = read_scenario(path)?;
+    if let Some(tle_path) = args.option("tle") {
+        let catalog = read_input(Some(tle_path))?;
+        scenario
+            .load_tles(&catalog)
+            .map_err(|e| Error::Input(format!("{}: {}", tle_path, e)))?;
+    }
+    if let Some(horizons_path) = args.option("horizons") {
+        let horizons = read_input(Some(horizons_path))?;
+        scenario
+            .load_horizons(&horizons)
+            .map_err(|e| Error::Input(format!("{}: {}", horizons_path, e)))?;
+    }
+    if let Some((kind, id, norad)) = scenario.unresolved().first() {
+        return Err(Error::Input(format!(
+            "{} {} needs NORAD {}; pass --tle FILE",
+            kind, id, norad
+        )));
+    }
+    if let Some((kind, id, error)) = scenario.tle_errors().first() {
+        return Err(Error::Input(format!("{} {}: {}", kind, id, error)));
+    }
+    if let Some(time) = args.time()? {
+        scenario = scenario.at(&time, args.orbit_model()?).ok_or_else(|| {
+            Error::Usage(String::from(
+                "--time needs a scenario epoch or TLEs to start from",
+            ))
+        })?;
+        if let Some((kind, id, error)) = scenario.tle_errors().first() {
+            return Err(Error::Input(format!("{} {}: {}", kind, id, error)));
+        }
+    }
+    Ok(scenario)
+}
+
+fn read_solution(path: Option<&str>) -> Result<Solution, Error> {
+    parse_solution(path, &read_input(path)?)
+}
+
+fn parse_solution(path: Option<&str>, input: &str) -> Result<Solution, Error> {
+    let solution = match Format::detect(path, input) {
+        Format::Json => Solution::from_json(input).map_err(|e| e.to_string()),
+        Format::Text => input.parse().map_err(|e: ParseError| e.to_string()),
+    };
+    solution.map_err(|e| Error::Input(format!("{}: {}", path.unwrap_or("stdin"), e)))
+}
+
+// Multi-epoch solutions start each epoch with an `epoch <seconds>` line.
+fn is_schedule(input: &str) -> bool {
+    input.lines().any(|line| line.starts_with("epoch "))
+}
+
+fn write_output(path: Option<&str>, contents: &str) -> Result<(), Error> {
+    match path {
+        None | Some("-") => io::stdout()
+            .write_all(contents.as_bytes())
+            .map_err(|e| Error::Io(String::from("stdout"), e)),
+        Some(path) => fs::write(path, contents).map_err(|e| Error::Io(path.to_string(), e)),
+    }
+}
+
+// SplitMix64; good enough to scatter users, and reproducible from a seed.
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random(seed)
+    }
+
+    // Uniform in [0, 1).
+    fn next(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{env, path::PathBuf, process};
+
+    // A file in the temp directory, named for this test run.
+    fn scratch(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("spacex_interview_{}_{}", process::id(), name))
+    }
+
+    fn write(name: &str, contents: &str) -> String {
+        let path = scratch(name);
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    // What main would exit with, output going to a scratch file.
+    fn exit_code(command: &str) -> i32 {
+        let output = scratch("output.txt");
+        let args = format!("{} --quiet -o {}", command, output.display());
+        match Args::parse(args.split_whitespace().map(String::from)).and_then(|a| run(&a)) {
+            Ok(()) => 0,
+            Err(error) => error.exit_code(),
+        }
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let scenario = write("scenario.txt", "user 1 6371 0 0\nsat 1 6921 0 0\n");
+        let good = write("good.txt", "sat 1 beam 1 user 1 color A\n");
+        let bad = write("bad.txt", "sat 1 beam 1 user 2 color A\n");
+        assert_eq!(exit_code(&format!("solve {}", scenario)), 0);
+        assert_eq!(exit_code(&format!("validate {} {}", scenario, good)), 0);
+        assert_eq!(exit_code(&format!("validate {} {}", scenario, bad)), 1);
+        assert_eq!(exit_code("validate"), 2);
+        assert_eq!(exit_code(&format!("solve {} --strategy best", scenario)), 2);
+        let missing = scratch("missing.txt");
+        assert_eq!(exit_code(&format!("solve {}", missing.display())), 3);
+        let garbled = write("garbled.txt", "sat 1 beam one\n");
+        assert_eq!(exit_code(&format!("validate {} {}", scenario, garbled)), 4);
+    }
+
+    #[test]
+    fn test_malformed_scenarios() {
+        let lines = [
+            "user 1 abc 0 0",
+            "user 1 6371 0",
+            "user x",
+            "user_geo 1 north 0",
+            "sat_geo 1 0 0",
+            "sat_orbit 1 53 0 0",
+            "interferer_orbit 1 0 0 zero 35786",
+            "class foo min_elev=abc",
+            "class foo min_elev=NaN",
+            "user 1 6371 0 0 class=nope",
+            "sat 1 6921 0 0 beams=abc bands=Q",
+            "shell v1 beams=many",
+            "sat 1 6921 0 0 max_scan=NaN",
+            "user 1 6371 0 0 priority=high",
+            "user 1 6371 0 0 weight=-1",
+            "user 1 6371 0 0\nsat_norad 1 25544",
+            "user 1 6371 0 0\nsat_tle 1",
+            "interferer 1 0 42164 0 protect=abc",
+            "interferer 1 0 42164 0 protect=-5",
+            "user 1 6371 0 0\nhorizon 2 0:65",
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            let path = write(&format!("malformed{}.txt", i), line);
+            assert_eq!(exit_code(&format!("solve {}", path)), 4, "{}", line);
+            assert_eq!(exit_code(&format!("bench {}", path)), 4, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_bench_formats() {
+        let scenario = write(
+            "bench.json",
+            r#"{"users": [{"id": 1, "position": [6371, 0, 0]}, {"id": 2, "position": [0, 6371, 0]}],
+                "satellites": [{"id": 1, "position": [6921, 0, 0]}]}"#,
+        );
+        // Its own output file, as the other tests run alongside.
+        let output = scratch("bench_report.txt");
+        let command = format!("bench {} --quiet -o {}", scenario, output.display());
+        let args = Args::parse(command.split_whitespace().map(String::from)).unwrap();
+        assert!(run(&args).is_ok());
+        let report = fs::read_to_string(output).unwrap();
+        assert!(report.contains("\t1 of 2 users\t"), "{}", report);
+    }
+}
@@ -0,0 +1,284 @@
+// Walker constellations: `total` satellites in `planes` evenly spaced circular
+// orbits, with neighbouring planes' satellites offset by `phasing` slots of
+// 360 / total degrees. Delta patterns spread the planes' ascending nodes over
+// 360 degrees, star patterns over 180. Shells are written in Walker notation,
+// `[delta|star:]INC:T/P/F:ALT[:RAAN]`, e.g. `53:360/18/0:550`.
+use std::{fmt, str::FromStr};
+
+use crate::{
+    builder::{BuildError, ScenarioBuilder},
+    orbit::Orbit,
+    position::Position,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Pattern {
+    #[default]
+    Delta,
+    Star,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shell {
+    pub pattern: Pattern,
+    pub total: usize,
+    pub planes: usize,
+    pub phasing: usize,
+    pub inclination: f64, // Degrees
+    pub altitude: f64,    // km above position::EARTH_RADIUS
+    pub raan: f64,        // Degrees; ascending node of the first plane
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShellError {
+    Empty,
+    UnevenPlanes { total: usize, planes: usize },
+    Phasing { phasing: usize, planes: usize },
+    Invalid(String),
+}
+
+impl Shell {
+    pub fn new(
+        pattern: Pattern,
+        total: usize,
+        planes: usize,
+        phasing: usize,
+        inclination: f64,
+        altitude: f64,
+    ) -> Result<Shell, ShellError> {
+        if total == 0 || planes == 0 {
+            return Err(ShellError::Empty);
+        }
+        if total % planes != 0 {
+            return Err(ShellError::UnevenPlanes { total, planes });
+        }
+        if phasing >= planes {
+            return Err(ShellError::Phasing { phasing, planes });
+        }
+        Ok(Shell {
+            pattern,
+            total,
+            planes,
+            phasing,
+            inclination,
+            altitude,
+            raan: 0.0,
+        })
+    }
+
+    pub fn per_plane(&self) -> usize {
+        self.total / self.planes
+    }
+
+    // Every satellite's orbit, plane by plane. Each plane's first satellite
+    // is at its ascending node, plus the phasing offset.
+    pub fn orbits(&self) -> Vec<Orbit> {
+        let spread = match self.pattern {
+            Pattern::Delta => 360.0,
+            Pattern::Star => 180.0,
+        };
+        let per_plane = self.per_plane();
+        let mut orbits = Vec::with_capacity(self.total);
+        for plane in 0..self.planes {
+            let raan = spread * plane as f64 / self.planes as f64 + self.raan;
+            let phase = 360.0 * (self.phasing * plane) as f64 / self.total as f64;
+            for satellite in 0..per_plane {
+                let argument_of_latitude = 360.0 * satellite as f64 / per_plane as f64 + phase;
+                orbits.push(Orbit::new(
+                    self.inclination,
+                    raan,
+                    argument_of_latitude,
+                    self.altitude,
+                ));
+            }
+        }
+        orbits
+    }
+
+    // Every satellite's position at the epoch, in the same order.
+    pub fn positions(&self) -> Vec<Position> {
+        self.orbits().iter().map(Orbit::eci).collect()
+    }
+}
+
+impl ScenarioBuilder {
+    // Add every satellite of a shell, numbered from `first_id`.
+    pub fn add_shell(&mut self, shell: &Shell, first_id: i32) -> Result<&mut Self, BuildError> {
+        for (i, orbit) in shell.orbits().into_iter().enumerate() {
+            self.add_orbit(first_id + i as i32, orbit)?;
+        }
+        Ok(self)
+    }
+}
+
+impl FromStr for Shell {
+    type Err = ShellError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ShellError::Invalid(s.to_string());
+        let mut parts: Vec<&str> = s.split(':').collect();
+        let pattern = match parts.first() {
+            Some(&"delta") => Pattern::Delta,
+            Some(&"star") => Pattern::Star,
+            _ => {
+                parts.insert(0, "delta");
+                Pattern::Delta
+            }
+        };
+        let (inclination, walker, altitude, raan) = match parts[1..] {
+            [inclination, walker, altitude] => (inclination, walker, altitude, "0"),
+            [inclination, walker, altitude, raan] => (inclination, walker, altitude, raan),
+            _ => return Err(invalid()),
+        };
+        let walker = walker
+            .split('/')
+            .map(|n| n.parse().map_err(|_| invalid()))
+            .collect::<Result<Vec<usize>, ShellError>>()?;
+        let (total, planes, phasing) = match walker[..] {
+            [total, planes, phasing] => (total, planes, phasing),
+            _ => return Err(invalid()),
+        };
+        let number = |n: &str| n.parse::<f64>().map_err(|_| invalid());
+        let mut shell = Shell::new(
+            pattern,
+            total,
+            planes,
+            phasing,
+            number(inclination)?,
+            number(altitude)?,
+        )?;
+        shell.raan = number(raan)?;
+        Ok(shell)
+    }
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShellError::Empty => write!(f, "a shell needs at least one satellite and plane"),
+            ShellError::UnevenPlanes { total, planes } => write!(
+                f,
+                "{} satellites don't divide evenly into {} planes",
+                total, planes
+            ),
+            ShellError::Phasing { phasing, planes } => write!(
+                f,
+                "phasing factor {} must be less than the {} planes",
+                phasing, planes
+            ),
+            ShellError::Invalid(shell) => write!(
+                f,
+                "invalid shell '{}'; expected [delta|star:]INC:T/P/F:ALT[:RAAN]",
+                shell
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShellError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    // The test cases' satellites came from gen_orbit.py --satellites 360
+    // --planes 18 --lan 0 --inclination 53 --altitude 565, whose 6356 km
+    // surface puts the shell 550 km above ours. (The copy of gen_orbit.py in
+    // the repository has since drifted: it sets the argument of periapsis to
+    // -lan and flips a sign in the rotation, so it no longer reproduces them.)
+    #[test]
+    fn test_eighteen_planes() {
+        let shell: Shell = "53:360/18/0:550".parse().unwrap();
+        let positions = shell.positions();
+        for text in [
+            include_str!("../beam-planning/test_cases/07_eighteen_planes.txt"),
+            include_str!("../beam-planning/test_cases/08_eighteen_planes_northern.txt"),
+        ]
+        .iter()
+        {
+            let scenario = Scenario::from_str(text);
+            assert_eq!(scenario.satellites().len(), positions.len());
+            for (satellite, position) in scenario.satellites().iter().zip(positions.iter()) {
+                let expected = satellite.entity().position();
+                assert!(
+                    expected.sub(position).len() < 1e-9,
+                    "sat {}: {} != {}",
+                    satellite,
+                    position,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_walker() {
+        // Walker delta 53:12/3/1 at 550 km: 4 satellites per plane, 120
+        // degrees between nodes, and each plane 30 degrees ahead of the last.
+        let shell: Shell = "53:12/3/1:550".parse().unwrap();
+        let positions = shell.positions();
+        assert_eq!(positions.len(), 12);
+        for position in &positions {
+            assert!((position.len() - 6921.0).abs() < 1e-9);
+            assert!(position.z.abs() <= 6921.0 * 53f64.to_radians().sin() + 1e-9);
+        }
+        // First satellite of each plane: plane 0 at its node, plane 1 30
+        // degrees past a node at 120 degrees.
+        assert!(positions[0].sub(&Position::new(6921.0, 0.0, 0.0)).len() < 1e-9);
+        let node = Position::new(
+            6921.0 * 120f64.to_radians().cos(),
+            6921.0 * 120f64.to_radians().sin(),
+            0.0,
+        );
+        let angle = Position::angle(&positions[4], &node).to_degrees();
+        assert!((angle - 30.0).abs() < 1e-9);
+
+        // Star: nodes over 180 degrees, so plane 1 of 3 crosses the equator
+        // at 60 degrees.
+        let star: Shell = "star:90:6/3/0:550".parse().unwrap();
+        let node = Position::new(6921.0 * 0.5, 6921.0 * 0.75f64.sqrt(), 0.0);
+        assert!(star.positions()[2].sub(&node).len() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_shell() {
+        let shell: Shell = "star:86.4:66/6/2:780:15".parse().unwrap();
+        assert_eq!(shell.pattern, Pattern::Star);
+        assert_eq!((shell.total, shell.planes, shell.phasing), (66, 6, 2));
+        assert_eq!(
+            (shell.inclination, shell.altitude, shell.raan),
+            (86.4, 780.0, 15.0)
+        );
+        assert_eq!(
+            "53:10/3/0:550".parse::<Shell>(),
+            Err(ShellError::UnevenPlanes {
+                total: 10,
+                planes: 3
+            })
+        );
+        assert_eq!(
+            "53:12/3/3:550".parse::<Shell>(),
+            Err(ShellError::Phasing {
+                phasing: 3,
+                planes: 3
+            })
+        );
+        assert!("53:12/3:550".parse::<Shell>().is_err());
+        assert!("53:12/3/0".parse::<Shell>().is_err());
+        assert!("polar:53:12/3/0:550".parse::<Shell>().is_err());
+    }
+
+    #[test]
+    fn test_add_shells() -> Result<(), BuildError> {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .add_shell(&"53:12/3/1:550".parse().unwrap(), 1)?
+            .add_shell(&"97.6:4/2/0:560".parse().unwrap(), 13)?;
+        let scenario = builder.build();
+        assert_eq!(scenario.satellites().len(), 16);
+        assert_eq!(scenario.satellites()[15].entity().id(), 16);
+        assert_eq!(scenario.orbit(16).map(|o| o.inclination), Some(97.6));
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+// Physical and operational limits shared by the solver, the validator and the
+// analysis. The defaults are the values in the problem statement, and the ones
+// beam-planning/evaluate.py checks against; in particular evaluate.py checks
+// every interferer, even one the Earth hides from the user, so earth_blockage
+// is off by default.
+use crate::{
+    position::ElevationModel,
+    scenario::{Entity, EntityKind, Scenario},
+};
+
+pub const BEAMS: usize = 32;
+pub const BEAM_ANGLE: f64 = 10.0;
+pub const MIN_ELEVATION: f64 = 45.0;
+pub const INTERFERENCE_ANGLE: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constraints {
+    pub beams: usize,                    // Beams per satellite
+    pub min_elevation: f64,              // Degrees above the user's horizon
+    pub beam_angle: f64,                 // Same-band separation, seen from the satellite
+    pub interference_angle: f64,         // Separation from interferers, seen from the user
+    pub elevation_model: ElevationModel, // What min_elevation is measured from
+    pub geo_arc: Option<f64>,            // Separation from the whole GEO arc, seen from the user
+    pub earth_blockage: bool,            // Skip interferers below the user's horizon
+    pub max_scan: Option<f64>,           // Off-nadir limit for satellites without their own
+    pub band_beams: Option<usize>,       // Beams per band, likewise
+    pub margins: Margins,
+}
+
+// Extra degrees kept clear of each angular limit. Satellite positions come from
+// predictions, so the planner can use positive margins to keep an assignment
+// from landing a hair inside a limit, while validating with none.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Margins {
+    pub elevation: f64,
+    pub beam_angle: f64,
+    pub interference_angle: f64,
+    pub scan: f64, // Inside each satellite's max_scan
+}
+
+impl Default for Constraints {
+    fn default() -> Constraints {
+        Constraints {
+            beams: BEAMS,
+            min_elevation: MIN_ELEVATION,
+            beam_angle: BEAM_ANGLE,
+            interference_angle: INTERFERENCE_ANGLE,
+            elevation_model: ElevationModel::default(),
+            geo_arc: None,
+            earth_blockage: false,
+            max_scan: None,
+            band_beams: None,
+            margins: Margins::default(),
+        }
+    }
+}
+
+impl Constraints {
+    // The same limits with the margins dropped, as evaluate.py checks them.
+    pub fn without_margins(&self) -> Constraints {
+        Constraints {
+            margins: Margins::default(),
+            ..*self
+        }
+    }
+
+    pub fn elevation_limit(&self) -> f64 {
+        self.min_elevation + self.margins.elevation
+    }
+
+    pub fn beam_angle_limit(&self) -> f64 {
+        self.beam_angle + self.margins.beam_angle
+    }
+
+    pub fn interference_angle_limit(&self) -> f64 {
+        self.interference_angle + self.margins.interference_angle
+    }
+
+    // The GEO arc is kept clear with the interferers' margin.
+    pub fn geo_arc_limit(&self) -> Option<f64> {
+        self.geo_arc
+            .map(|angle| angle + self.margins.interference_angle)
+    }
+}
+
+impl Scenario {
+    // The separation, in degrees, this interferer asks of every beam, from a
+    // `protect=DEG` attribute on its line. Without one, interference_angle
+    // applies. Reading a scenario rejects angles outside 0 to 180 degrees.
+    pub fn protection_angle(&self, interferer: i32) -> Option<f64> {
+        self.attribute(EntityKind::Interferer, interferer, "protect")
+            .and_then(parse_protection)
+    }
+
+    pub(crate) fn check_protection(&self, interferer: i32) -> Result<(), String> {
+        match self.attribute(EntityKind::Interferer, interferer, "protect") {
+            Some(value) if parse_protection(value).is_none() => Err(format!(
+                "interferer {}: invalid protect '{}'",
+                interferer, value
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Every interferer with the separation beams must keep from it, margin
+    // included. With earth_blockage, callers skip the ones a user can't see.
+    pub fn interference_limits(&self, constraints: &Constraints) -> Vec<(Entity, f64)> {
+        self.interferers()
+            .iter()
+            .map(|interferer| {
+                let angle = self
+                    .protection_angle(interferer.id())
+                    .unwrap_or(constraints.interference_angle);
+                (*interferer, angle + constraints.margins.interference_angle)
+            })
+            .collect()
+    }
+}
+
+fn parse_protection(value: &str) -> Option<f64> {
+    value
+        .parse()
+        .ok()
+        .filter(|angle: &f64| (0.0..=180.0).contains(angle))
+}
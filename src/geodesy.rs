@@ -0,0 +1,216 @@
+// Conversions between geodetic coordinates (latitude, longitude, altitude) and
+// the Earth-centered, Earth-fixed x/y/z in km that the rest of the crate uses.
+// The test cases model the Earth as a sphere of EARTH_RADIUS, so that is the
+// default datum; WGS84 is available for inputs from real surveys.
+use std::{fmt, str::FromStr};
+
+use crate::{
+    position::{Position, EARTH_RADIUS},
+    scenario::Scenario,
+};
+
+// WGS84 semi-major axis in km, and flattening.
+pub const WGS84_A: f64 = 6378.137;
+pub const WGS84_F: f64 = 1.0 / 298.257223563;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Datum {
+    #[default]
+    Spherical,
+    Wgs84,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geodetic {
+    pub latitude: f64,  // Degrees, north positive
+    pub longitude: f64, // Degrees, east positive
+    pub altitude: f64,  // km above the datum's surface
+}
+
+impl Geodetic {
+    pub fn new(latitude: f64, longitude: f64, altitude: f64) -> Geodetic {
+        Geodetic {
+            latitude,
+            longitude,
+            altitude,
+        }
+    }
+
+    pub fn to_position(&self, datum: Datum) -> Position {
+        let (lat, lon) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let (x, y, z) = match datum {
+            Datum::Spherical => {
+                let r = EARTH_RADIUS + self.altitude;
+                (
+                    r * lat.cos() * lon.cos(),
+                    r * lat.cos() * lon.sin(),
+                    r * lat.sin(),
+                )
+            }
+            Datum::Wgs84 => {
+                let e2 = WGS84_F * (2.0 - WGS84_F);
+                // Prime vertical radius of curvature
+                let n = WGS84_A / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+                let h = self.altitude;
+                (
+                    (n + h) * lat.cos() * lon.cos(),
+                    (n + h) * lat.cos() * lon.sin(),
+                    (n * (1.0 - e2) + h) * lat.sin(),
+                )
+            }
+        };
+        Position::new(x, y, z)
+    }
+
+    pub fn from_position(position: &Position, datum: Datum) -> Geodetic {
+        let (x, y, z) = (position.x, position.y, position.z);
+        let p = x.hypot(y);
+        let longitude = y.atan2(x).to_degrees();
+        match datum {
+            Datum::Spherical => {
+                let r = p.hypot(z);
+                Geodetic::new(z.atan2(p).to_degrees(), longitude, r - EARTH_RADIUS)
+            }
+            Datum::Wgs84 => {
+                // Fixed-point iteration on latitude; converges to well below a
+                // millimetre in a handful of steps for anything near the Earth.
+                let e2 = WGS84_F * (2.0 - WGS84_F);
+                let mut lat = z.atan2(p * (1.0 - e2));
+                let mut n = WGS84_A;
+                for _ in 0..10 {
+                    n = WGS84_A / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+                    let h = p * lat.cos() + z * lat.sin() - WGS84_A * WGS84_A / n;
+                    lat = z.atan2(p * (1.0 - e2 * n / (n + h)));
+                }
+                // This form of the height stays accurate at the poles, where
+                // p / cos(lat) would not.
+                let h = p * lat.cos() + z * lat.sin() - WGS84_A * WGS84_A / n;
+                Geodetic::new(lat.to_degrees(), longitude, h)
+            }
+        }
+    }
+}
+
+impl Scenario {
+    // Latitude, longitude and altitude of a position, in the datum the
+    // scenario was read with.
+    pub fn geodetic(&self, position: &Position) -> Geodetic {
+        Geodetic::from_position(position, self.datum())
+    }
+}
+
+impl FromStr for Datum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spherical" => Ok(Datum::Spherical),
+            "wgs84" => Ok(Datum::Wgs84),
+            _ => Err(format!("unknown datum '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Datum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Datum::Spherical => "spherical",
+                Datum::Wgs84 => "wgs84",
+            }
+        )
+    }
+}
+
+impl fmt::Display for Geodetic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "lat {:.5} lon {:.5} alt {:.3}",
+            self.latitude, self.longitude, self.altitude
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64, tolerance: f64) {
+        assert!((a - b).abs() < tolerance, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_spherical() {
+        let position = Geodetic::new(0.0, 0.0, 550.0).to_position(Datum::Spherical);
+        assert_eq!(position, Position::new(6921.0, 0.0, 0.0));
+        let position = Geodetic::new(90.0, 0.0, 0.0).to_position(Datum::Spherical);
+        assert_close(position.z, 6371.0, 1e-3);
+
+        let geodetic = Geodetic::new(-33.865, 151.209, 0.05);
+        let round_trip =
+            Geodetic::from_position(&geodetic.to_position(Datum::Spherical), Datum::Spherical);
+        assert_close(round_trip.latitude, geodetic.latitude, 1e-4);
+        assert_close(round_trip.longitude, geodetic.longitude, 1e-4);
+        assert_close(round_trip.altitude, geodetic.altitude, 1e-3);
+    }
+
+    #[test]
+    fn test_wgs84() {
+        let equator = Geodetic::new(0.0, 90.0, 0.0).to_position(Datum::Wgs84);
+        assert_close(equator.y, WGS84_A, 1e-3);
+        let pole = Geodetic::new(90.0, 0.0, 0.0).to_position(Datum::Wgs84);
+        assert_close(pole.z, 6356.752, 1e-3);
+        let pole = Geodetic::from_position(&pole, Datum::Wgs84);
+        assert_close(pole.latitude, 90.0, 1e-6);
+        assert_close(pole.altitude, 0.0, 1e-3);
+
+        for &(lat, lon, alt) in &[(47.6, -122.3, 0.1), (-45.0, 10.0, 550.0), (5.0, 0.0, 0.0)] {
+            let position = Geodetic::new(lat, lon, alt).to_position(Datum::Wgs84);
+            let round_trip = Geodetic::from_position(&position, Datum::Wgs84);
+            assert_close(round_trip.latitude, lat, 1e-4);
+            assert_close(round_trip.longitude, lon, 1e-4);
+            assert_close(round_trip.altitude, alt, 2e-3);
+        }
+
+        // Geodetic latitude is further from the equator than geocentric.
+        let position = Geodetic::new(45.0, 0.0, 0.0).to_position(Datum::Wgs84);
+        let geocentric = Geodetic::from_position(&position, Datum::Spherical);
+        assert_close(45.0 - geocentric.latitude, 0.192, 1e-3);
+    }
+
+    #[test]
+    fn test_geo_lines() {
+        let scenario = Scenario::from_str(
+            "class maritime
+user_geo 1 0 0
+user_geo 2 0 90 1000 class=maritime
+sat_geo 1 0 0 550
+datum wgs84
+user_geo 3 90 0",
+        );
+        let users = scenario.users();
+        assert_eq!(users[0].position(), &Position::new(6371.0, 0.0, 0.0));
+        assert_close(users[1].position().y, 6372.0, 1e-3);
+        assert_eq!(
+            scenario.attribute(crate::scenario::EntityKind::User, 2, "class"),
+            Some("maritime")
+        );
+        assert_close(users[2].position().z, 6356.752, 1e-3);
+        assert_eq!(
+            scenario.satellites()[0].entity().position(),
+            &Position::new(6921.0, 0.0, 0.0)
+        );
+        assert_eq!(scenario.datum(), Datum::Wgs84);
+        assert_close(scenario.geodetic(users[2].position()).latitude, 90.0, 1e-6);
+
+        // Converting keeps the datum lat/lon are reported on.
+        let input = Scenario::from_str("datum wgs84\nuser_geo 1 47.6 -122.3").to_input();
+        assert!(input.starts_with("datum wgs84\n"), "{}", input);
+        let round_trip = Scenario::from_str(&input);
+        let user = round_trip.users()[0].position();
+        assert_close(round_trip.geodetic(user).latitude, 47.6, 1e-9);
+    }
+}
@@ -0,0 +1,234 @@
+// Per-user horizon masks. A terminal in a valley or beside a building can't
+// use the whole sky above the elevation limit, so a user may carry a mask: the
+// lowest usable elevation in each direction. A mask only ever raises the
+// limit; a user looks at least `min_elevation` (or its class's min_elev) up
+// whatever it says. `Terminal::clearance` applies it.
+//
+// In the line format a mask is `horizon <user> <az>:<el> ...`, in degrees,
+// either in the scenario, after its user, or in a side file of such lines given
+// to `load_horizons`. Either way a mask for a user the scenario doesn't have is
+// an error.
+use std::{fmt, str::FromStr};
+
+use crate::{
+    scenario::{EntityKind, Scenario},
+    solution::ParseError,
+};
+
+// Minimum elevation by azimuth, linear between the points and wrapping past
+// 360 degrees, so a single point is a flat mask.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HorizonMask {
+    points: Vec<(f64, f64)>, // (azimuth, elevation), by azimuth
+}
+
+impl HorizonMask {
+    pub fn new(mut points: Vec<(f64, f64)>) -> Result<HorizonMask, String> {
+        if points.is_empty() {
+            return Err(String::from("a horizon mask needs at least one point"));
+        }
+        for (azimuth, elevation) in &points {
+            if !(0.0..360.0).contains(azimuth) || !(-90.0..=90.0).contains(elevation) {
+                return Err(format!(
+                    "horizon point {}:{} needs 0 <= azimuth < 360 and -90 <= elevation <= 90",
+                    azimuth, elevation
+                ));
+            }
+        }
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        if points.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err(String::from("horizon azimuths must be distinct"));
+        }
+        Ok(HorizonMask { points })
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    // The mask's elevation at `azimuth` degrees.
+    pub fn elevation(&self, azimuth: f64) -> f64 {
+        let azimuth = azimuth.rem_euclid(360.0);
+        let after = self.points.partition_point(|(a, _)| *a <= azimuth);
+        let (a0, e0) = match after {
+            0 => {
+                let (a, e) = self.points[self.points.len() - 1];
+                (a - 360.0, e)
+            }
+            i => self.points[i - 1],
+        };
+        let (a1, e1) = match self.points.get(after) {
+            Some(point) => *point,
+            None => (self.points[0].0 + 360.0, self.points[0].1),
+        };
+        if a1 == a0 {
+            return e0;
+        }
+        e0 + (e1 - e0) * (azimuth - a0) / (a1 - a0)
+    }
+}
+
+impl Scenario {
+    pub fn horizon(&self, user: i32) -> Option<&HorizonMask> {
+        self.horizons.get(&user)
+    }
+
+    // Add the masks in a file of `horizon` lines, replacing any the scenario
+    // already has for those users. Returns how many were read.
+    pub fn load_horizons(&mut self, s: &str) -> Result<usize, ParseError> {
+        let mut loaded = 0;
+        for (i, line) in s.lines().enumerate() {
+            if line.contains('#') || line.trim().is_empty() {
+                continue;
+            }
+            let error = |message: String| ParseError {
+                line: i + 1,
+                message,
+            };
+            self.add_horizon_line(line).map_err(error)?;
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    pub(crate) fn add_horizon_line(&mut self, line: &str) -> Result<(), String> {
+        let (user, mask) = parse_line(line)?;
+        if !self.users.iter().any(|u| u.id() == user) {
+            return Err(format!("no user {} in the scenario", user));
+        }
+        self.horizons.insert(user, mask);
+        Ok(())
+    }
+
+    // The `horizon` line for a user, if it has a mask.
+    pub(crate) fn horizon_line(&self, kind: EntityKind, id: i32) -> Option<String> {
+        match kind {
+            EntityKind::User => self
+                .horizon(id)
+                .map(|mask| format!("horizon {} {}", id, mask)),
+            _ => None,
+        }
+    }
+}
+
+// `horizon <user> <az>:<el> ...`
+fn parse_line(line: &str) -> Result<(i32, HorizonMask), String> {
+    let mut parts = line.split_whitespace();
+    if parts.next() != Some("horizon") {
+        return Err(String::from("expected a horizon line"));
+    }
+    let user = parts
+        .next()
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| String::from("horizon needs a user id"))?;
+    let mask = parts.collect::<Vec<&str>>().join(" ").parse()?;
+    Ok((user, mask))
+}
+
+impl FromStr for HorizonMask {
+    type Err = String;
+
+    // Space-separated `azimuth:elevation` pairs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let points = s
+            .split_whitespace()
+            .map(|point| {
+                let invalid = || format!("invalid horizon point '{}'; expected AZ:EL", point);
+                let (azimuth, elevation) = point.split_once(':').ok_or_else(invalid)?;
+                Ok((
+                    azimuth.parse().map_err(|_| invalid())?,
+                    elevation.parse().map_err(|_| invalid())?,
+                ))
+            })
+            .collect::<Result<Vec<(f64, f64)>, String>>()?;
+        HorizonMask::new(points)
+    }
+}
+
+impl fmt::Display for HorizonMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let points: Vec<String> = self
+            .points
+            .iter()
+            .map(|(azimuth, elevation)| format!("{}:{}", azimuth, elevation))
+            .collect();
+        write!(f, "{}", points.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mask() {
+        let mask: HorizonMask = "270:60 0:50 90:70".parse().unwrap();
+        assert_eq!(mask.to_string(), "0:50 90:70 270:60");
+        assert_eq!(mask.elevation(0.0), 50.0);
+        assert_eq!(mask.elevation(45.0), 60.0);
+        assert_eq!(mask.elevation(180.0), 65.0);
+        // Between the last point and the first, across north.
+        assert_eq!(mask.elevation(315.0), 55.0);
+        assert_eq!(mask.elevation(-45.0), 55.0);
+        assert_eq!(
+            "10:30".parse::<HorizonMask>().unwrap().elevation(200.0),
+            30.0
+        );
+
+        assert!("".parse::<HorizonMask>().is_err());
+        assert!("0:50 0:60".parse::<HorizonMask>().is_err());
+        assert!("360:50".parse::<HorizonMask>().is_err());
+        assert!("0-50".parse::<HorizonMask>().is_err());
+    }
+
+    #[test]
+    fn test_masked_users() {
+        // Two users under a satellite 60 degrees up to their north. One has
+        // a hill to the north reaching 65 degrees.
+        let input = "user 1 6371 0 0
+user 2 6371 0 0
+sat 1 6871 0 288.7
+horizon 2 0:65 180:10";
+        let scenario = Scenario::from_str(input);
+        assert_eq!(scenario.horizon(1), None);
+        assert_eq!(scenario.horizon(2).unwrap().elevation(0.0), 65.0);
+        let solution = scenario.optimize();
+        assert_eq!(solution.covered(), 1);
+        assert!(solution.by_user(1).is_some());
+        assert_eq!(scenario.validate(&solution), vec![]);
+
+        // Served anyway, the masked user is out of sight.
+        let forced: crate::solution::Solution = "sat 1 beam 1 user 2 color A".parse().unwrap();
+        assert!(matches!(
+            scenario.validate(&forced)[..],
+            [crate::validate::Violation::NotVisible { user: 2, .. }]
+        ));
+        let analysis = scenario.analyze(&solution);
+        assert_eq!(analysis.uncovered, 1);
+        assert!(scenario
+            .to_input()
+            .contains("\nuser 2 6371 0 0\nhorizon 2 0:65 180:10\n"));
+
+        // Masks can also come from a side file.
+        let mut scenario = Scenario::from_str("user 1 6371 0 0\nsat 1 6871 0 288.7");
+        assert_eq!(scenario.load_horizons("# hills\nhorizon 1 0:65"), Ok(1));
+        assert!(scenario.optimize().is_empty());
+        assert_eq!(
+            scenario
+                .load_horizons("horizon 9 0:65")
+                .unwrap_err()
+                .message,
+            "no user 9 in the scenario"
+        );
+        // Likewise inline, where the user must come first.
+        let error = |input: &str| Scenario::parse(input).err().map(|e| e.to_string());
+        assert_eq!(
+            error("user 1 6371 0 0\nhorizon 2 0:65"),
+            Some(String::from("line 2: no user 2 in the scenario"))
+        );
+        assert_eq!(
+            error("horizon 1 0:65\nuser 1 6371 0 0"),
+            Some(String::from("line 1: no user 1 in the scenario"))
+        );
+    }
+}
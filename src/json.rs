@@ -0,0 +1,1161 @@
+// A small JSON reader and writer, enough for scenarios and solutions without
+// pulling in a dependency. Numbers keep their source text, so coordinates are
+// parsed straight to f64 and round trip exactly.
+//
+// Scenario:
+//   {"users": [{"id": 1, "position": [6371, 0, 0], "attributes": {"k": "v"}}],
+//    "satellites": [...], "interferers": [...]}
+// A satellite or interferer may give "orbit": {"inclination", "raan",
+// "argument_of_latitude", "altitude"} or "tle": [line1, line2] in place of its
+// position, or "norad": <catalog number> for one to come from a TLE file;
+// "epoch" is then the UTC time positions are for. A user may give "horizon":
+// [[azimuth, elevation], ...], its horizon mask. Terminal classes are
+// "classes": [{"name": "maritime", "attributes": {"min_elev": 35}}], and
+// satellite shells "shells" in the same form. "datum": "wgs84" reports lat/lon
+// on the ellipsoid, as after a `datum wgs84` line.
+// Solution:
+//   {"assignments": [{"satellite": 1, "beam": 1, "user": 1, "band": "A"}],
+//    "analysis": {...}}
+use std::{fmt, path::Path, str::FromStr};
+
+use crate::{
+    analysis::{percentile, Analysis},
+    geodesy::Datum,
+    horizon::HorizonMask,
+    orbit::Orbit,
+    payload::ShellParams,
+    position::Position,
+    scenario::{Attributes, Entity, EntityKind, Satellite, Scenario, BANDS},
+    solution::{Assignment, Solution},
+    terminal::TerminalClass,
+    tle::Tle,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub offset: usize, // Byte offset into the input, or 0 for structural errors
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    // A `.json` extension wins; otherwise JSON input starts with `{` or `[`.
+    pub fn detect(path: Option<&str>, contents: &str) -> Format {
+        match path.and_then(|p| Path::new(p).extension()) {
+            Some(extension) if extension == "json" => Format::Json,
+            _ => match contents.trim_start().as_bytes().first() {
+                Some(b'{') | Some(b'[') => Format::Json,
+                _ => Format::Text,
+            },
+        }
+    }
+
+    // Output format from a path's extension; text unless it is `.json`.
+    pub fn from_path(path: Option<&str>) -> Format {
+        match path.and_then(|p| Path::new(p).extension()) {
+            Some(extension) if extension == "json" => Format::Json,
+            _ => Format::Text,
+        }
+    }
+}
+
+impl Value {
+    pub fn parse(s: &str) -> Result<Value, JsonError> {
+        let mut parser = Parser {
+            input: s.as_bytes(),
+            offset: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.offset < parser.input.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Parse a number into any numeric type, from its original text.
+    pub fn as_number<T: FromStr>(&self) -> Option<T> {
+        match self {
+            Value::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn number(n: impl fmt::Display) -> Value {
+        Value::Number(n.to_string())
+    }
+
+    // Multi-line output: objects get a line per member, and arrays of objects
+    // or arrays a line per element. Everything else stays on one line.
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize) {
+        let pad = |n: usize| "  ".repeat(n);
+        match self {
+            Value::Object(members) if !members.is_empty() => {
+                out.push_str("{\n");
+                for (i, (key, value)) in members.iter().enumerate() {
+                    out.push_str(&format!(
+                        "{}{}: ",
+                        pad(indent + 1),
+                        Value::String(key.clone())
+                    ));
+                    value.write_pretty(out, indent + 1);
+                    out.push_str(if i + 1 < members.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&format!("{}}}", pad(indent)));
+            }
+            Value::Array(values)
+                if values
+                    .iter()
+                    .any(|v| matches!(v, Value::Object(_) | Value::Array(_))) =>
+            {
+                out.push_str("[\n");
+                for (i, value) in values.iter().enumerate() {
+                    out.push_str(&format!("{}{}", pad(indent + 1), value));
+                    out.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&format!("{}]", pad(indent)));
+            }
+            value => out.push_str(&value.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    // Compact, single-line JSON.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", Value::String(key.clone()), value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+// Deeper than any scenario or solution nests, shallow enough that the
+// recursive parser can't overflow the stack.
+pub const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    input: &'a [u8],
+    offset: usize,
+    depth: usize, // Objects and arrays open around the current value
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.get(self.offset) {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.whitespace();
+        self.input.get(self.offset).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.peek() == Some(byte) {
+            self.offset += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, JsonError> {
+        if self.input[self.offset..].starts_with(word.as_bytes()) {
+            self.offset += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        match self.peek() {
+            Some(open @ (b'{' | b'[')) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error("nested too deeply"));
+                }
+                self.depth += 1;
+                let value = match open {
+                    b'{' => self.object(),
+                    _ => self.array(),
+                };
+                self.depth -= 1;
+                value
+            }
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.offset += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b'}') => {
+                    self.offset += 1;
+                    return Ok(Value::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        if self.peek() == Some(b']') {
+            self.offset += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b']') => {
+                    self.offset += 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.offset;
+        let digits = |p: &mut Parser| {
+            let from = p.offset;
+            while let Some(b'0'..=b'9') = p.input.get(p.offset) {
+                p.offset += 1;
+            }
+            p.offset > from
+        };
+        if self.input.get(self.offset) == Some(&b'-') {
+            self.offset += 1;
+        }
+        if !digits(self) {
+            return Err(self.error("invalid number"));
+        }
+        if self.input.get(self.offset) == Some(&b'.') {
+            self.offset += 1;
+            if !digits(self) {
+                return Err(self.error("invalid number"));
+            }
+        }
+        if let Some(b'e' | b'E') = self.input.get(self.offset) {
+            self.offset += 1;
+            if let Some(b'+' | b'-') = self.input.get(self.offset) {
+                self.offset += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("invalid number"));
+            }
+        }
+        // Only ASCII was consumed, so this can't split a character.
+        let text = std::str::from_utf8(&self.input[start..self.offset]).unwrap();
+        Ok(Value::Number(text.to_string()))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = match self.input.get(self.offset) {
+                Some(byte) => *byte,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.offset += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = self.input.get(self.offset).copied();
+                    self.offset += 1;
+                    let c = match escape {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.unicode()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                byte if byte < 0x20 => return Err(self.error("control character in string")),
+                byte => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let hex = self
+            .input
+            .get(self.offset..self.offset + 4)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u32::from_str_radix(h, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.offset += 4;
+        Ok(hex)
+    }
+
+    // The four hex digits after `\u`, joining UTF-16 surrogate pairs.
+    fn unicode(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.input[self.offset..].starts_with(b"\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.offset += 2;
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid code point"))
+    }
+}
+
+fn structure(message: String) -> JsonError {
+    JsonError { offset: 0, message }
+}
+
+fn field<'v>(value: &'v Value, key: &str, context: &str) -> Result<&'v Value, JsonError> {
+    value
+        .get(key)
+        .ok_or_else(|| structure(format!("{} is missing \"{}\"", context, key)))
+}
+
+fn number<T: FromStr>(value: &Value, key: &str, context: &str) -> Result<T, JsonError> {
+    field(value, key, context)?
+        .as_number()
+        .ok_or_else(|| structure(format!("{} has an invalid \"{}\"", context, key)))
+}
+
+fn array<'v>(value: &'v Value, key: &str) -> Result<&'v [Value], JsonError> {
+    match value.get(key) {
+        None => Ok(&[]),
+        Some(v) => v
+            .as_array()
+            .ok_or_else(|| structure(format!("\"{}\" must be an array", key))),
+    }
+}
+
+// The "attributes" object of an entity or class, as `key=value` pairs.
+fn attributes(value: &Value, context: &str) -> Result<Attributes, JsonError> {
+    match value.get("attributes") {
+        None => Ok(Attributes::new()),
+        Some(Value::Object(members)) => members
+            .iter()
+            .map(|(key, value)| match value {
+                Value::String(s) => Ok((key.clone(), s.clone())),
+                Value::Number(n) => Ok((key.clone(), n.clone())),
+                Value::Bool(b) => Ok((key.clone(), b.to_string())),
+                _ => Err(structure(format!(
+                    "{} attribute \"{}\" must be a string, number or bool",
+                    context, key
+                ))),
+            })
+            .collect(),
+        Some(_) => Err(structure(format!(
+            "{} attributes must be an object",
+            context
+        ))),
+    }
+}
+
+fn attributes_value(attributes: &[(String, String)]) -> Value {
+    Value::Object(
+        attributes
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect(),
+    )
+}
+
+const SECTIONS: [(&str, EntityKind); 3] = [
+    ("users", EntityKind::User),
+    ("satellites", EntityKind::Satellite),
+    ("interferers", EntityKind::Interferer),
+];
+
+impl Scenario {
+    pub fn from_json(s: &str) -> Result<Scenario, JsonError> {
+        let root = Value::parse(s)?;
+        let mut scenario = Scenario::new();
+        for value in array(&root, "classes")? {
+            let name = field(value, "name", "class entry")?
+                .as_str()
+                .ok_or_else(|| structure(String::from("class name must be a string")))?;
+            let context = format!("class {}", name);
+            let class =
+                TerminalClass::new(name, attributes(value, &context)?).map_err(structure)?;
+            scenario.add_class(class);
+        }
+        for value in array(&root, "shells")? {
+            let name = field(value, "name", "shell entry")?
+                .as_str()
+                .ok_or_else(|| structure(String::from("shell name must be a string")))?;
+            let context = format!("shell {}", name);
+            let shell = ShellParams::new(name, attributes(value, &context)?).map_err(structure)?;
+            scenario.add_shell(shell);
+        }
+        for (section, kind) in SECTIONS.iter() {
+            for value in array(&root, section)? {
+                let context = format!("{} entry", kind);
+                let id = number(value, "id", &context)?;
+                let context = format!("{} {}", kind, id);
+                let orbit = match (kind, value.get("orbit")) {
+                    (EntityKind::User, _) | (_, None) => None,
+                    (_, Some(orbit)) => {
+                        let context = format!("{} orbit", context);
+                        Some(Orbit::new(
+                            number(orbit, "inclination", &context)?,
+                            number(orbit, "raan", &context)?,
+                            number(orbit, "argument_of_latitude", &context)?,
+                            number(orbit, "altitude", &context)?,
+                        ))
+                    }
+                };
+                let tle = match (kind, value.get("tle")) {
+                    (EntityKind::User, _) | (_, None) => None,
+                    (_, Some(tle)) => match tle.as_array() {
+                        Some([Value::String(line1), Value::String(line2)]) => Some(
+                            Tle::parse(line1, line2)
+                                .map_err(|e| structure(format!("{}: {}", context, e)))?,
+                        ),
+                        _ => {
+                            return Err(structure(format!(
+                                "{} tle must be [line1, line2]",
+                                context
+                            )))
+                        }
+                    },
+                };
+                let norad = match (kind, value.get("norad")) {
+                    (EntityKind::User, _) | (_, None) => None,
+                    (_, Some(_)) => Some(number::<u32>(value, "norad", &context)?),
+                };
+                if let Some(norad) = norad {
+                    scenario.norad.insert((*kind, id), norad);
+                }
+                let position = match (orbit, &tle) {
+                    (Some(orbit), _) => orbit.eci(),
+                    _ if tle.is_some() || norad.is_some() => {
+                        Position::new(f64::NAN, f64::NAN, f64::NAN)
+                    }
+                    _ => match field(value, "position", &context)?.as_array() {
+                        Some([x, y, z]) => match (x.as_number(), y.as_number(), z.as_number()) {
+                            (Some(x), Some(y), Some(z)) => Position::new(x, y, z),
+                            _ => {
+                                return Err(structure(format!(
+                                    "{} has a non-numeric position",
+                                    context
+                                )))
+                            }
+                        },
+                        _ => {
+                            return Err(structure(format!(
+                                "{} position must be [x, y, z]",
+                                context
+                            )))
+                        }
+                    },
+                };
+                let attributes = attributes(value, &context)?;
+                if let (EntityKind::User, Some(horizon)) = (kind, value.get("horizon")) {
+                    let invalid =
+                        || structure(format!("{} horizon must be [[az, el], ...]", context));
+                    let points = horizon
+                        .as_array()
+                        .ok_or_else(invalid)?
+                        .iter()
+                        .map(|point| match point.as_array() {
+                            Some([azimuth, elevation]) => {
+                                match (azimuth.as_number(), elevation.as_number()) {
+                                    (Some(azimuth), Some(elevation)) => Ok((azimuth, elevation)),
+                                    _ => Err(invalid()),
+                                }
+                            }
+                            _ => Err(invalid()),
+                        })
+                        .collect::<Result<Vec<(f64, f64)>, JsonError>>()?;
+                    let mask = HorizonMask::new(points)
+                        .map_err(|e| structure(format!("{}: {}", context, e)))?;
+                    scenario.horizons.insert(id, mask);
+                }
+                let entity = Entity::new(id, position);
+                scenario.set_attributes(*kind, id, attributes);
+                match (kind, orbit, tle) {
+                    (_, Some(orbit), _) => scenario.add_orbit(*kind, id, orbit),
+                    (_, _, Some(tle)) => scenario.add_tle(*kind, id, tle),
+                    (EntityKind::User, ..) => scenario.users.push(entity),
+                    (EntityKind::Satellite, ..) => scenario.satellites.push(Satellite::new(entity)),
+                    (EntityKind::Interferer, ..) => scenario.interferers.push(entity),
+                }
+            }
+        }
+        if let Some(datum) = root.get("datum") {
+            scenario.datum = datum
+                .as_str()
+                .and_then(|datum| datum.parse().ok())
+                .ok_or_else(|| {
+                    structure(String::from("\"datum\" must be \"spherical\" or \"wgs84\""))
+                })?;
+        }
+        if let Some(epoch) = root.get("epoch") {
+            let epoch = epoch
+                .as_str()
+                .and_then(|epoch| epoch.parse().ok())
+                .ok_or_else(|| structure(String::from("\"epoch\" must be a UTC time")))?;
+            scenario.epoch = Some(epoch);
+        }
+        for (kind, entity) in scenario.entities() {
+            scenario
+                .check_attributes(kind, entity.id())
+                .map_err(structure)?;
+        }
+        scenario.place_tles();
+        Ok(scenario)
+    }
+
+    pub fn to_json(&self) -> String {
+        let section = |kind: EntityKind| {
+            Value::Array(
+                self.entities()
+                    .filter(|(k, _)| *k == kind)
+                    .map(|(_, entity)| {
+                        let position = entity.position();
+                        let mut members = vec![(String::from("id"), Value::number(entity.id()))];
+                        match self.norad.get(&(kind, entity.id())) {
+                            Some(norad) => {
+                                members.push((String::from("norad"), Value::number(norad)))
+                            }
+                            None => members.push((
+                                String::from("position"),
+                                Value::Array(vec![
+                                    Value::number(position.x),
+                                    Value::number(position.y),
+                                    Value::number(position.z),
+                                ]),
+                            )),
+                        }
+                        if let Some(orbit) = self.orbits.get(&(kind, entity.id())) {
+                            members.push((
+                                String::from("orbit"),
+                                Value::Object(vec![
+                                    (
+                                        String::from("inclination"),
+                                        Value::number(orbit.inclination),
+                                    ),
+                                    (String::from("raan"), Value::number(orbit.raan)),
+                                    (
+                                        String::from("argument_of_latitude"),
+                                        Value::number(orbit.argument_of_latitude),
+                                    ),
+                                    (String::from("altitude"), Value::number(orbit.altitude)),
+                                ]),
+                            ));
+                        }
+                        if let Some(tle) = self.tle(kind, entity.id()) {
+                            let (line1, line2) = tle.lines();
+                            members.push((
+                                String::from("tle"),
+                                Value::Array(vec![
+                                    Value::String(line1.to_string()),
+                                    Value::String(line2.to_string()),
+                                ]),
+                            ));
+                        }
+                        if let (EntityKind::User, Some(mask)) = (kind, self.horizon(entity.id())) {
+                            members.push((
+                                String::from("horizon"),
+                                Value::Array(
+                                    mask.points()
+                                        .iter()
+                                        .map(|(azimuth, elevation)| {
+                                            Value::Array(vec![
+                                                Value::number(azimuth),
+                                                Value::number(elevation),
+                                            ])
+                                        })
+                                        .collect(),
+                                ),
+                            ));
+                        }
+                        let attributes = self.attributes(kind, entity.id());
+                        if !attributes.is_empty() {
+                            members
+                                .push((String::from("attributes"), attributes_value(attributes)));
+                        }
+                        Value::Object(members)
+                    })
+                    .collect(),
+            )
+        };
+        let datum = (self.datum != Datum::default())
+            .then(|| (String::from("datum"), Value::String(self.datum.to_string())));
+        let epoch = self
+            .epoch()
+            .map(|epoch| (String::from("epoch"), Value::String(epoch.to_string())));
+        // Named groups of settings, each with its attributes.
+        let groups = |key: &str, groups: Vec<(&str, &[(String, String)])>| match groups.is_empty() {
+            true => None,
+            false => Some((
+                key.to_string(),
+                Value::Array(
+                    groups
+                        .into_iter()
+                        .map(|(name, attributes)| {
+                            Value::Object(vec![
+                                (String::from("name"), Value::String(name.to_string())),
+                                (String::from("attributes"), attributes_value(attributes)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            )),
+        };
+        let classes = groups(
+            "classes",
+            self.classes()
+                .iter()
+                .map(|class| (class.name(), class.attributes()))
+                .collect(),
+        );
+        let shells = groups(
+            "shells",
+            self.shells()
+                .iter()
+                .map(|shell| (shell.name(), shell.attributes()))
+                .collect(),
+        );
+        Value::Object(
+            datum
+                .into_iter()
+                .chain(epoch)
+                .chain(classes)
+                .chain(shells)
+                .chain(
+                    SECTIONS
+                        .iter()
+                        .map(|(name, kind)| (name.to_string(), section(*kind))),
+                )
+                .collect(),
+        )
+        .pretty()
+    }
+}
+
+impl Solution {
+    pub fn from_json(s: &str) -> Result<Solution, JsonError> {
+        let root = Value::parse(s)?;
+        array(&root, "assignments")?
+            .iter()
+            .map(|value| {
+                let context = "assignment";
+                let band = field(value, "band", context)?
+                    .as_str()
+                    .and_then(|band| band.parse().ok())
+                    .ok_or_else(|| structure(String::from("assignment has an invalid band")))?;
+                let beam = number(value, "beam", context)?;
+                if beam == 0 {
+                    return Err(structure(String::from("assignment beams start at 1")));
+                }
+                Ok(Assignment {
+                    satellite: number(value, "satellite", context)?,
+                    beam,
+                    user: number(value, "user", context)?,
+                    band,
+                })
+            })
+            .collect::<Result<Vec<Assignment>, JsonError>>()
+            .map(Solution::new)
+    }
+
+    // The analysis, when given, is written alongside for downstream reporting;
+    // from_json ignores it.
+    pub fn to_json(&self, analysis: Option<&Analysis>) -> String {
+        let assignments = Value::Array(
+            self.iter()
+                .map(|a| {
+                    Value::Object(vec![
+                        (String::from("satellite"), Value::number(a.satellite)),
+                        (String::from("beam"), Value::number(a.beam)),
+                        (String::from("user"), Value::number(a.user)),
+                        (String::from("band"), Value::String(a.band.to_string())),
+                    ])
+                })
+                .collect(),
+        );
+        let mut members = vec![(String::from("assignments"), assignments)];
+        if let Some(analysis) = analysis {
+            members.push((String::from("analysis"), analysis.to_json()));
+        }
+        Value::Object(members).pretty()
+    }
+}
+
+impl Analysis {
+    pub fn to_json(&self) -> Value {
+        let number = |n: f64| {
+            if n.is_finite() {
+                Value::number(n)
+            } else {
+                Value::Null
+            }
+        };
+        let counts = |counts: &[usize]| Value::Array(counts.iter().map(Value::number).collect());
+        let members = vec![
+            ("users", Value::number(self.users)),
+            ("covered", Value::number(self.covered)),
+            ("utilization", number(self.utilization())),
+            ("success", number(self.success())),
+            ("saturated", Value::number(self.saturated)),
+            ("unassigned", Value::number(self.unassigned)),
+            ("max_possible", Value::number(self.max_possible)),
+            ("uncovered", Value::number(self.uncovered)),
+            ("masked", Value::number(self.masked)),
+            ("weight", number(self.weight)),
+            ("covered_weight", number(self.covered_weight)),
+            (
+                "occluded_rejections",
+                self.occluded_rejections.map_or(Value::Null, Value::number),
+            ),
+            ("average_elevation", number(self.average_elevation)),
+            (
+                "elevation_histogram",
+                Value::Object(vec![
+                    (String::from("min"), number(self.elevations.min)),
+                    (String::from("width"), number(self.elevations.width)),
+                    (String::from("counts"), counts(&self.elevations.counts)),
+                ]),
+            ),
+            (
+                "scan_histogram",
+                Value::Object(vec![
+                    (String::from("min"), number(self.scan_angles.min)),
+                    (String::from("width"), number(self.scan_angles.width)),
+                    (String::from("counts"), counts(&self.scan_angles.counts)),
+                ]),
+            ),
+            (
+                "slant_range",
+                Value::Object(
+                    [
+                        ("min", 0.0),
+                        ("p50", 50.0),
+                        ("p90", 90.0),
+                        ("p99", 99.0),
+                        ("max", 100.0),
+                    ]
+                    .iter()
+                    .map(|(name, p)| (name.to_string(), number(percentile(&self.slant_ranges, *p))))
+                    .collect(),
+                ),
+            ),
+            (
+                "slack",
+                Value::Object(
+                    [
+                        ("elevation", self.slack.elevation),
+                        ("beam_angle", self.slack.beam_angle),
+                        ("interference_angle", self.slack.interference_angle),
+                        ("geo_arc", self.slack.geo_arc),
+                        ("scan", self.slack.scan),
+                    ]
+                    .iter()
+                    .map(|(name, slack)| (name.to_string(), slack.map_or(Value::Null, number)))
+                    .collect(),
+                ),
+            ),
+            (
+                "beams_per_satellite",
+                counts(&self.beams_per_satellite.counts),
+            ),
+            (
+                "band_beams",
+                Value::Object(
+                    BANDS
+                        .iter()
+                        .zip(self.band_beams.iter())
+                        .map(|(band, count)| (band.to_string(), Value::number(count)))
+                        .collect(),
+                ),
+            ),
+            (
+                "band_saturated",
+                Value::Object(
+                    BANDS
+                        .iter()
+                        .zip(self.band_saturated.iter())
+                        .map(|(band, count)| (band.to_string(), Value::number(count)))
+                        .collect(),
+                ),
+            ),
+            (
+                "latitudes",
+                Value::Array(
+                    self.latitudes
+                        .iter()
+                        .filter(|band| band.users > 0)
+                        .map(|band| {
+                            Value::Object(vec![
+                                (String::from("south"), number(band.south)),
+                                (String::from("users"), Value::number(band.users)),
+                                (String::from("covered"), Value::number(band.covered)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "classes",
+                Value::Array(
+                    self.classes
+                        .iter()
+                        .map(|class| {
+                            Value::Object(vec![
+                                (String::from("name"), Value::String(class.name.clone())),
+                                (String::from("users"), Value::number(class.users)),
+                                (String::from("covered"), Value::number(class.covered)),
+                                (String::from("uncovered"), Value::number(class.uncovered)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "priorities",
+                Value::Array(
+                    self.priorities
+                        .iter()
+                        .map(|tier| {
+                            Value::Object(vec![
+                                (String::from("priority"), Value::number(tier.priority)),
+                                (String::from("users"), Value::number(tier.users)),
+                                (String::from("covered"), Value::number(tier.covered)),
+                                (String::from("weight"), number(tier.weight)),
+                                (String::from("covered_weight"), number(tier.covered_weight)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ];
+        Value::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.offset > 0 {
+            write!(f, "invalid JSON at byte {}: {}", self.offset, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        let value = Value::parse(
+            r#" {"a": [1, -2.5e3, true, false, null], "b": "x\"\\\n\u00e9\ud83d\ude00", "c": {}} "#,
+        )
+        .unwrap();
+        assert_eq!(
+            value.get("a"),
+            Some(&Value::Array(vec![
+                Value::number(1),
+                Value::Number(String::from("-2.5e3")),
+                Value::Bool(true),
+                Value::Bool(false),
+                Value::Null,
+            ]))
+        );
+        assert_eq!(value.get("b").and_then(Value::as_str), Some("x\"\\\né😀"));
+        assert_eq!(value.get("c"), Some(&Value::Object(vec![])));
+        assert_eq!(Value::parse(&value.to_string()), Ok(value.clone()));
+        assert_eq!(Value::parse(&value.pretty()), Ok(value));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for input in &[
+            "",
+            "{",
+            "[1,]",
+            "{\"a\" 1}",
+            "01x",
+            "\"\\q\"",
+            "tru",
+            "[1] 2",
+            "-",
+        ] {
+            assert!(Value::parse(input).is_err(), "{} should not parse", input);
+        }
+        assert_eq!(Value::parse("[1,]").unwrap_err().offset, 3);
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Value::parse(&nested(MAX_DEPTH)).is_ok());
+        let error = Value::parse(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(
+            (error.offset, error.message.as_str()),
+            (128, "nested too deeply")
+        );
+        assert!(Value::parse(&"[{\"a\": ".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(Format::detect(Some("a.json"), ""), Format::Json);
+        assert_eq!(
+            Format::detect(Some("a.txt"), "  {\"users\": []}"),
+            Format::Json
+        );
+        assert_eq!(Format::detect(None, "user 1 6371 0 0"), Format::Text);
+        assert_eq!(Format::from_path(Some("out.json")), Format::Json);
+        assert_eq!(Format::from_path(None), Format::Text);
+    }
+
+    #[test]
+    fn test_scenario_round_trip() {
+        let text = include_str!("../beam-planning/test_cases/07_eighteen_planes.txt");
+        let scenario = Scenario::from_str(text);
+        let json = scenario.to_json();
+        let from_json = Scenario::from_json(&json).unwrap();
+        assert_eq!(from_json.to_input(), scenario.to_input());
+        for ((_, a), (_, b)) in scenario.entities().zip(from_json.entities()) {
+            assert_eq!(a.id(), b.id());
+            assert_eq!(a.position(), b.position());
+        }
+
+        let with_attributes = Scenario::from_str(
+            "class maritime min_elev=35 bands=A,B
+user 1 6371 0 0 class=maritime weight=2
+horizon 1 0:50 180.5:20",
+        );
+        let json = with_attributes.to_json();
+        assert!(
+            json.contains("\"horizon\": [[0, 50], [180.5, 20]]"),
+            "{}",
+            json
+        );
+        let from_json = Scenario::from_json(&json).unwrap();
+        assert_eq!(
+            from_json.terminal_class(1).unwrap().min_elevation(),
+            Some(35.0)
+        );
+        assert_eq!(from_json.to_input(), with_attributes.to_input());
+
+        let with_datum = Scenario::from_str("datum wgs84\nuser_geo 1 47.6 -122.3 50");
+        let json = with_datum.to_json();
+        assert!(json.contains("\"datum\": \"wgs84\""), "{}", json);
+        let from_json = Scenario::from_json(&json).unwrap();
+        assert_eq!(from_json.datum(), Datum::Wgs84);
+        let user = from_json.users()[0].position();
+        assert!((from_json.geodetic(user).latitude - 47.6).abs() < 1e-9);
+        assert_eq!(from_json.to_input(), with_datum.to_input());
+
+        let with_orbit = Scenario::from_str(
+            "shell 1 max_scan=56.5
+sat_orbit 1 53 90 45 550 shell=1
+interferer_orbit 1 87.9 10 0 1200 protect=12",
+        );
+        let json = with_orbit.to_json();
+        assert!(json.contains("\"argument_of_latitude\": 45"));
+        let from_json = Scenario::from_json(&json).unwrap();
+        assert_eq!(from_json.orbit(1), with_orbit.orbit(1));
+        assert_eq!(
+            from_json.interferer_orbit(1),
+            with_orbit.interferer_orbit(1)
+        );
+        assert_eq!(from_json.protection_angle(1), Some(12.0));
+        let payload = from_json.payload(1, &Default::default());
+        assert_eq!(payload.max_scan, Some(56.5));
+        assert_eq!(from_json.to_input(), with_orbit.to_input());
+
+        let with_tles = Scenario::from_str(
+            "epoch 2019-12-10T00:00:00Z
+sat_tle 1
+1 25544U 98067A   19343.69339541  .00001764  00000-0  38792-4 0  9991
+2 25544  51.6439 211.2001 0007417  17.6667  85.6398 15.50103472202482
+interferer_norad 1 99999",
+        );
+        let json = with_tles.to_json();
+        assert!(json.contains("\"norad\": 99999"));
+        let from_json = Scenario::from_json(&json).unwrap();
+        assert_eq!(from_json.to_input(), with_tles.to_input());
+        assert_eq!(
+            from_json.satellites()[0].entity().position(),
+            with_tles.satellites()[0].entity().position()
+        );
+    }
+
+    #[test]
+    fn test_scenario_errors() {
+        assert!(Scenario::from_json(r#"{"users": [{"id": 1}]}"#).is_err());
+        assert!(Scenario::from_json(r#"{"users": [{"id": 1, "position": [1, 2]}]}"#).is_err());
+        assert!(Scenario::from_json(r#"{"users": {}}"#).is_err());
+        assert!(Scenario::from_json(r#"{"datum": "flat"}"#).is_err());
+        let undefined = r#"{"users": [{"id": 1, "position": [6371, 0, 0],
+            "attributes": {"class": "nope"}}]}"#;
+        assert_eq!(
+            Scenario::from_json(undefined).err().map(|e| e.to_string()),
+            Some(String::from("user 1: undefined class 'nope'"))
+        );
+        let empty = Scenario::from_json("{}").unwrap();
+        assert!(empty.users().is_empty());
+    }
+
+    #[test]
+    fn test_solution_round_trip() {
+        let scenario =
+            Scenario::from_str(include_str!("../beam-planning/test_cases/00_example.txt"));
+        let solution = scenario.optimize();
+        let analysis = scenario.analyze(&solution);
+        let json = solution.to_json(Some(&analysis));
+        assert_eq!(Solution::from_json(&json).unwrap(), solution);
+
+        let root = Value::parse(&json).unwrap();
+        let analysis = root.get("analysis").unwrap();
+        assert_eq!(analysis.get("covered"), Some(&Value::number(3)));
+        assert!(Solution::from_json(
+            r#"{"assignments": [{"satellite": 1, "beam": 1, "user": 1, "band": "E"}]}"#
+        )
+        .is_err());
+    }
+}
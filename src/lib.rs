@@ -0,0 +1,33 @@
+//! Beam planning for a constellation of satellites serving ground users.
+//!
+//! A `Scenario` is built from the line-based input format, from JSON or with a
+//! `ScenarioBuilder`, solved into a `Solution` with `Scenario::optimize`,
+//! checked with `Scenario::validate` and summarized with `Scenario::analyze`.
+//! Limits for all three come from `Constraints`. Satellites on circular orbits
+//! can be propagated with `Scenario::propagate` and planned at a series of
+//! epochs into a `Schedule`; satellites and interferers given by TLE are
+//! placed with SGP4. The `spacex_interview` binary wraps these in a
+//! command line interface.
+
+pub mod analysis;
+pub mod builder;
+pub mod constellation;
+pub mod constraints;
+pub mod geodesy;
+pub mod horizon;
+pub mod json;
+pub mod orbit;
+pub mod passes;
+pub mod payload;
+pub mod position;
+pub mod priority;
+pub mod scenario;
+pub mod schedule;
+pub mod sdp4;
+pub mod sgp4;
+pub mod solution;
+pub mod solver;
+pub mod terminal;
+pub mod time;
+pub mod tle;
+pub mod validate;
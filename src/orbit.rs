@@ -0,0 +1,343 @@
+// Circular orbits and the frames they are propagated in. A scenario's
+// positions are Earth-fixed (ECEF) at its epoch, t = 0 seconds. The inertial
+// (ECI) frame used for orbital elements coincides with ECEF at that instant;
+// afterwards the Earth turns eastward under it at EARTH_ROTATION.
+use std::{fmt, str::FromStr};
+
+use crate::{
+    geodesy::WGS84_A,
+    position::{Position, EARTH_RADIUS},
+    scenario::{Entity, EntityKind, Scenario},
+    sgp4::Sgp4,
+    time::Utc,
+};
+
+pub const MU: f64 = 398600.4418; // km^3/s^2
+pub const J2: f64 = 1.08262668e-3;
+pub const EARTH_ROTATION: f64 = 7.292115e-5; // rad/s, sidereal
+
+// Which perturbations propagation includes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OrbitModel {
+    // Two-body motion: the orbit plane stays fixed in inertial space.
+    #[default]
+    Keplerian,
+    // Adds the secular drift from the Earth's oblateness: the node regresses
+    // (or, past 90 degrees inclination, advances) and the satellite runs
+    // slightly ahead of or behind two-body motion.
+    J2,
+}
+
+// A circular orbit, given by its plane and the satellite's place in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orbit {
+    pub inclination: f64,          // Degrees
+    pub raan: f64,                 // Degrees; right ascension of the ascending node
+    pub argument_of_latitude: f64, // Degrees along the orbit from the node
+    pub altitude: f64,             // km above EARTH_RADIUS
+}
+
+impl Orbit {
+    pub fn new(inclination: f64, raan: f64, argument_of_latitude: f64, altitude: f64) -> Orbit {
+        Orbit {
+            inclination,
+            raan,
+            argument_of_latitude,
+            altitude,
+        }
+    }
+
+    pub fn radius(&self) -> f64 {
+        EARTH_RADIUS + self.altitude
+    }
+
+    // Radians per second.
+    pub fn mean_motion(&self) -> f64 {
+        (MU / self.radius().powi(3)).sqrt()
+    }
+
+    // Seconds per revolution.
+    pub fn period(&self) -> f64 {
+        2.0 * std::f64::consts::PI / self.mean_motion()
+    }
+
+    // Secular rates of the node and argument of latitude, in radians per
+    // second. For a circular orbit the J2 terms are dΩ/dt = -3/2 n J2 (Re/a)²
+    // cos i, and du/dt = dω/dt + dM/dt = n (1 + 3/4 J2 (Re/a)² (8 cos² i - 2)).
+    pub fn rates(&self, model: OrbitModel) -> (f64, f64) {
+        let n = self.mean_motion();
+        match model {
+            OrbitModel::Keplerian => (0.0, n),
+            OrbitModel::J2 => {
+                let k = J2 * (WGS84_A / self.radius()).powi(2);
+                let cos_i = self.inclination.to_radians().cos();
+                (
+                    -1.5 * n * k * cos_i,
+                    n * (1.0 + 0.75 * k * (8.0 * cos_i * cos_i - 2.0)),
+                )
+            }
+        }
+    }
+
+    // The elements `seconds` later, in the same inertial frame.
+    pub fn advance(&self, seconds: f64, model: OrbitModel) -> Orbit {
+        let (node, latitude) = self.rates(model);
+        Orbit {
+            raan: (self.raan + (node * seconds).to_degrees()).rem_euclid(360.0),
+            argument_of_latitude: (self.argument_of_latitude + (latitude * seconds).to_degrees())
+                .rem_euclid(360.0),
+            ..*self
+        }
+    }
+
+    // Position in the inertial frame.
+    pub fn eci(&self) -> Position {
+        let o = self.raan.to_radians();
+        let i = self.inclination.to_radians();
+        let u = self.argument_of_latitude.to_radians();
+        let (xp, yp) = (self.radius() * u.cos(), self.radius() * u.sin());
+        Position::new(
+            o.cos() * xp - o.sin() * i.cos() * yp,
+            o.sin() * xp + o.cos() * i.cos() * yp,
+            i.sin() * yp,
+        )
+    }
+
+    // Earth-fixed position `seconds` after the epoch.
+    pub fn ecef(&self, seconds: f64, model: OrbitModel) -> Position {
+        eci_to_ecef(&self.advance(seconds, model).eci(), seconds)
+    }
+}
+
+// The Earth has turned EARTH_ROTATION * seconds about z since the epoch, so
+// inertial coordinates rotate back by that angle to become Earth-fixed.
+pub fn eci_to_ecef(position: &Position, seconds: f64) -> Position {
+    rotate_z(position, -EARTH_ROTATION * seconds)
+}
+
+pub fn ecef_to_eci(position: &Position, seconds: f64) -> Position {
+    rotate_z(position, EARTH_ROTATION * seconds)
+}
+
+fn rotate_z(position: &Position, angle: f64) -> Position {
+    let (sin, cos) = angle.sin_cos();
+    Position::new(
+        cos * position.x - sin * position.y,
+        sin * position.x + cos * position.y,
+        position.z,
+    )
+}
+
+// Where an entity is at any time after the scenario epoch, for sampling one
+// entity many times without propagating the whole scenario.
+#[derive(Debug, Clone)]
+pub(crate) enum Track {
+    Fixed(Position),
+    Orbit(Orbit, OrbitModel),
+    Tle(Box<Sgp4>, Utc), // The scenario epoch
+}
+
+impl Track {
+    // None once SGP4 gives up on the element set, e.g. after decay.
+    pub(crate) fn position(&self, seconds: f64) -> Option<Position> {
+        match self {
+            Track::Fixed(position) => Some(*position),
+            Track::Orbit(orbit, model) => Some(orbit.ecef(seconds, *model)),
+            Track::Tle(sgp4, epoch) => sgp4.position_at(&epoch.add_seconds(seconds)).ok(),
+        }
+    }
+}
+
+impl Scenario {
+    pub(crate) fn track(&self, kind: EntityKind, entity: &Entity, model: OrbitModel) -> Track {
+        if let Some(orbit) = self.orbits.get(&(kind, entity.id())) {
+            return Track::Orbit(*orbit, model);
+        }
+        match (self.tle(kind, entity.id()), self.epoch()) {
+            (Some(tle), Some(epoch)) => match Sgp4::new(tle) {
+                Ok(sgp4) => Track::Tle(Box::new(sgp4), epoch),
+                Err(_) => Track::Fixed(*entity.position()),
+            },
+            _ => Track::Fixed(*entity.position()),
+        }
+    }
+
+    // The scenario `seconds` after its epoch, with that instant as the new
+    // epoch. Satellites and interferers with an orbit move along it and those
+    // with an element set are placed by SGP4; users, and anything given only
+    // by position, keep their Earth-fixed positions. Orbits are
+    // re-expressed in the new epoch's inertial frame, so propagating by a and
+    // then b matches propagating by a + b.
+    pub fn propagate(&self, seconds: f64, model: OrbitModel) -> Scenario {
+        let mut scenario = Scenario::new();
+        scenario.users = self.users.clone();
+        scenario.attributes = self.attributes.clone();
+        scenario.datum = self.datum;
+        scenario.epoch = self.epoch().map(|epoch| epoch.add_seconds(seconds));
+        scenario.tles = self.tles.clone();
+        scenario.norad = self.norad.clone();
+        scenario.tle_errors = self.tle_errors.clone();
+        scenario.horizons = self.horizons.clone();
+        scenario.classes = self.classes.clone();
+        scenario.shells = self.shells.clone();
+        let entities = self
+            .satellites
+            .iter()
+            .map(|s| (EntityKind::Satellite, s.entity()))
+            .chain(self.interferers.iter().map(|i| (EntityKind::Interferer, i)));
+        for (kind, entity) in entities {
+            match self.orbits.get(&(kind, entity.id())) {
+                Some(orbit) => {
+                    let mut orbit = orbit.advance(seconds, model);
+                    orbit.raan =
+                        (orbit.raan - (EARTH_ROTATION * seconds).to_degrees()).rem_euclid(360.0);
+                    scenario.add_orbit(kind, entity.id(), orbit);
+                }
+                None => scenario.push(kind, *entity),
+            }
+        }
+        scenario.place_tles();
+        scenario
+    }
+
+    // The scenario at `time`, if it has an epoch to propagate from.
+    pub fn at(&self, time: &Utc, model: OrbitModel) -> Option<Scenario> {
+        let epoch = self.epoch()?;
+        Some(self.propagate(time.seconds_since(&epoch), model))
+    }
+
+    // Leave satellites and interferers where they are and forget their
+    // orbits, element sets and epoch, so the scenario is written as plain
+    // `sat` and `interferer` lines that evaluate.py can read.
+    pub fn drop_orbits(&mut self) {
+        self.orbits.clear();
+        self.tles.clear();
+        self.epoch = None;
+    }
+}
+
+impl FromStr for OrbitModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keplerian" => Ok(OrbitModel::Keplerian),
+            "j2" => Ok(OrbitModel::J2),
+            _ => Err(format!("unknown orbit model '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for OrbitModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OrbitModel::Keplerian => "keplerian",
+                OrbitModel::J2 => "j2",
+            }
+        )
+    }
+}
+
+impl fmt::Display for Orbit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.inclination, self.raan, self.argument_of_latitude, self.altitude
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: &Position, b: &Position, tolerance: f64) {
+        assert!(a.sub(b).len() < tolerance, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_keplerian() {
+        let orbit = Orbit::new(53.0, 40.0, 10.0, 550.0);
+        // 550km circular orbits take about 95.6 minutes.
+        assert!((orbit.period() / 60.0 - 95.6).abs() < 0.1);
+        // After a full period the satellite is back where it started in
+        // inertial space, and the Earth has turned under it.
+        let start = orbit.eci();
+        let period = orbit.period();
+        assert_near(
+            &orbit.advance(period, OrbitModel::Keplerian).eci(),
+            &start,
+            1e-6,
+        );
+        let ecef = orbit.ecef(period, OrbitModel::Keplerian);
+        assert_near(&ecef_to_eci(&ecef, period), &start, 1e-6);
+        let turned = Position::angle(
+            &Position::new(ecef.x, ecef.y, 0.0),
+            &Position::new(start.x, start.y, 0.0),
+        );
+        assert!((turned - EARTH_ROTATION * period).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_j2_drift() {
+        let per_day = |rate: f64| (rate * 86400.0).to_degrees();
+        // A sun-synchronous orbit's node advances about 0.9856 degrees a day,
+        // keeping pace with the Sun.
+        let sun_synchronous = Orbit::new(97.64, 0.0, 0.0, 6938.137 - EARTH_RADIUS);
+        let (node, _) = sun_synchronous.rates(OrbitModel::J2);
+        assert!((per_day(node) - 0.9856).abs() < 0.01, "{}", per_day(node));
+        // A 53 degree shell at 550km regresses by roughly 4.5 degrees a day.
+        let shell = Orbit::new(53.0, 0.0, 0.0, 550.0);
+        let (node, _) = shell.rates(OrbitModel::J2);
+        assert!((per_day(node) + 4.5).abs() < 0.1, "{}", per_day(node));
+        assert_eq!(shell.rates(OrbitModel::Keplerian).0, 0.0);
+    }
+
+    #[test]
+    fn test_propagate() {
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+sat_orbit 1 53 0 0 550
+sat 2 6921 0 0
+interferer 1 -42164 0 0",
+        );
+        for model in [OrbitModel::Keplerian, OrbitModel::J2].iter() {
+            let later = scenario.propagate(60.0, *model);
+            let orbit = scenario.orbit(1).unwrap();
+            assert_near(
+                later.satellites()[0].entity().position(),
+                &orbit.ecef(60.0, *model),
+                1e-6,
+            );
+            // Users, interferers and satellites without orbits stay put.
+            assert_eq!(later.users()[0].position(), scenario.users()[0].position());
+            assert_eq!(
+                later.satellites()[1].entity().position(),
+                scenario.satellites()[1].entity().position()
+            );
+            assert_eq!(
+                later.interferers()[0].position(),
+                scenario.interferers()[0].position()
+            );
+            let twice = later.propagate(60.0, *model);
+            assert_near(
+                twice.satellites()[0].entity().position(),
+                scenario.propagate(120.0, *model).satellites()[0]
+                    .entity()
+                    .position(),
+                1e-6,
+            );
+        }
+
+        let mut snapshot = scenario.propagate(60.0, OrbitModel::Keplerian);
+        // The node has moved a quarter degree west under the turning Earth.
+        assert!(snapshot.to_input().contains("\nsat_orbit 1 53 359.749"));
+        snapshot.drop_orbits();
+        let input = snapshot.to_input();
+        assert!(input.contains("\nsat 1 ") && !input.contains("sat_orbit"));
+    }
+}
@@ -0,0 +1,300 @@
+// Visibility passes: when each satellite rises above a user's elevation mask,
+// when and how high it culminates, and when it sets. Elevations are sampled at
+// a series of epochs (seconds from the scenario epoch, as for a Schedule), and
+// each crossing of the mask between two samples is then bisected, so the times
+// are good to a millisecond. A pass that rises and sets between two samples is
+// missed, so the step should be well under the shortest pass of interest: a
+// 550km satellite stays above 45 degrees for at most about three minutes.
+use std::fmt;
+
+use crate::{
+    constraints::Constraints,
+    orbit::{OrbitModel, Track},
+    position::Position,
+    scenario::{Entity, EntityKind, Scenario},
+    terminal::Terminal,
+    time::Utc,
+};
+
+const TOLERANCE: f64 = 1e-3; // Seconds
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pass {
+    pub satellite: i32,
+    pub rise: f64,          // Seconds from the epoch; the first sample if already up
+    pub culmination: f64,   // When the satellite is highest
+    pub max_elevation: f64, // Degrees
+    pub set: f64,           // The last sample if still up
+}
+
+// The passes over one user, by rise time and then satellite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassTable {
+    pub user: i32,
+    pub epoch: Option<Utc>, // The scenario's, when it has one
+    pub passes: Vec<Pass>,
+}
+
+impl Scenario {
+    // Pass tables for `users` (all or some of the scenario's), in the order
+    // given, over `epochs`, which must be increasing. A satellite is up when
+    // the user can see it above its terminal's elevation limit and any horizon
+    // mask, as in the solver.
+    pub fn passes(
+        &self,
+        users: &[Entity],
+        epochs: &[f64],
+        model: OrbitModel,
+        constraints: &Constraints,
+    ) -> Vec<PassTable> {
+        let mut tables: Vec<PassTable> = users
+            .iter()
+            .map(|user| PassTable {
+                user: user.id(),
+                epoch: self.epoch(),
+                passes: Vec::new(),
+            })
+            .collect();
+        for satellite in self.satellites() {
+            let entity = satellite.entity();
+            let track = self.track(EntityKind::Satellite, entity, model);
+            let samples: Vec<Option<Position>> =
+                epochs.iter().map(|&t| track.position(t)).collect();
+            for (user, table) in users.iter().zip(tables.iter_mut()) {
+                let pass = Visibility {
+                    user,
+                    terminal: self.terminal(user.id()),
+                    track: &track,
+                    constraints,
+                };
+                table
+                    .passes
+                    .extend(pass.find(entity.id(), epochs, &samples));
+            }
+        }
+        for table in &mut tables {
+            table.passes.sort_by(|a, b| {
+                a.rise
+                    .partial_cmp(&b.rise)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(a.satellite.cmp(&b.satellite))
+            });
+        }
+        tables
+    }
+}
+
+// One user's view of one satellite.
+struct Visibility<'a> {
+    user: &'a Entity,
+    terminal: Terminal<'a>,
+    track: &'a Track,
+    constraints: &'a Constraints,
+}
+
+impl Visibility<'_> {
+    // Degrees above the elevation limit or mask; negative when not visible,
+    // and when the satellite can't be placed at all.
+    fn clearance(&self, position: Option<&Position>) -> f64 {
+        match position {
+            Some(position) => {
+                self.terminal
+                    .clearance(self.user.position(), position, self.constraints)
+            }
+            None => -180.0,
+        }
+    }
+
+    fn clearance_at(&self, seconds: f64) -> f64 {
+        self.clearance(self.track.position(seconds).as_ref())
+    }
+
+    // A mask can put the highest clearance somewhere other than the highest
+    // elevation, so culminations are found by elevation.
+    fn elevation(&self, position: Option<&Position>) -> f64 {
+        match position {
+            Some(position) => self
+                .user
+                .position()
+                .elevation(position, self.constraints.elevation_model),
+            None => -90.0,
+        }
+    }
+
+    fn elevation_at(&self, seconds: f64) -> f64 {
+        self.elevation(self.track.position(seconds).as_ref())
+    }
+
+    fn find(&self, satellite: i32, epochs: &[f64], samples: &[Option<Position>]) -> Vec<Pass> {
+        let clearances: Vec<f64> = samples.iter().map(|p| self.clearance(p.as_ref())).collect();
+        let elevations: Vec<f64> = samples.iter().map(|p| self.elevation(p.as_ref())).collect();
+        let mut passes = Vec::new();
+        // The rise time and the highest sample so far of the pass in progress.
+        let mut current: Option<(f64, usize)> = None;
+        for (i, &clearance) in clearances.iter().enumerate() {
+            let up = clearance > 0.0;
+            current = match current {
+                None if up => {
+                    let rise = match i {
+                        0 => epochs[0],
+                        _ => self.crossing(epochs[i - 1], epochs[i]),
+                    };
+                    Some((rise, i))
+                }
+                Some((rise, highest)) if up && elevations[i] > elevations[highest] => {
+                    Some((rise, i))
+                }
+                Some(pass) if up => Some(pass),
+                Some((rise, highest)) => {
+                    let set = self.crossing(epochs[i], epochs[i - 1]);
+                    passes.push(self.pass(satellite, rise, set, highest, epochs));
+                    None
+                }
+                None => None,
+            };
+        }
+        if let (Some((rise, highest)), Some(&last)) = (current, epochs.last()) {
+            passes.push(self.pass(satellite, rise, last, highest, epochs));
+        }
+        passes
+    }
+
+    // Bisect between a time the satellite is down and one it is up.
+    fn crossing(&self, mut down: f64, mut up: f64) -> f64 {
+        while (up - down).abs() > TOLERANCE {
+            let middle = (down + up) / 2.0;
+            if self.clearance_at(middle) > 0.0 {
+                up = middle;
+            } else {
+                down = middle;
+            }
+        }
+        up
+    }
+
+    // Golden-section search for the culmination around the highest sample.
+    fn pass(&self, satellite: i32, rise: f64, set: f64, highest: usize, epochs: &[f64]) -> Pass {
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let mut low = epochs[highest.saturating_sub(1)].max(rise);
+        let mut high = epochs[(highest + 1).min(epochs.len() - 1)].min(set);
+        while high - low > TOLERANCE {
+            let a = high - ratio * (high - low);
+            let b = low + ratio * (high - low);
+            if self.elevation_at(a) < self.elevation_at(b) {
+                low = a;
+            } else {
+                high = b;
+            }
+        }
+        let culmination = (low + high) / 2.0;
+        Pass {
+            satellite,
+            rise,
+            culmination,
+            max_elevation: self.elevation_at(culmination),
+            set,
+        }
+    }
+}
+
+impl fmt::Display for PassTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.epoch {
+            Some(epoch) => writeln!(f, "user {} (seconds from {})", self.user, epoch)?,
+            None => writeln!(f, "user {}", self.user)?,
+        }
+        if self.passes.is_empty() {
+            writeln!(f, "\tno passes")?;
+        }
+        for pass in &self.passes {
+            writeln!(f, "\t{}", pass)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sat {} rise {:.3} culmination {:.3} set {:.3} max elevation {:.2}",
+            self.satellite, self.rise, self.culmination, self.set, self.max_elevation
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{orbit::EARTH_ROTATION, schedule::parse_epochs};
+
+    #[test]
+    fn test_overhead_pass() {
+        // An equatorial satellite 30 degrees west of a user on the equator
+        // catches up with the turning Earth and passes straight overhead.
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+sat_orbit 1 0 0 -30 550",
+        );
+        let constraints = Constraints::default();
+        let epochs = parse_epochs("0:1200:30").unwrap();
+        let tables = scenario.passes(
+            scenario.users(),
+            &epochs,
+            OrbitModel::Keplerian,
+            &constraints,
+        );
+        assert_eq!(tables.len(), 1);
+        let passes = &tables[0].passes;
+        assert_eq!(passes.len(), 1);
+        let pass = passes[0];
+
+        let orbit = scenario.orbit(1).unwrap();
+        let overhead = 30f64.to_radians() / (orbit.mean_motion() - EARTH_ROTATION);
+        assert!((pass.culmination - overhead).abs() < 0.01, "{:?}", pass);
+        assert!(pass.max_elevation > 89.99);
+        assert!(((pass.culmination - pass.rise) - (pass.set - pass.culmination)).abs() < 0.01);
+        // The rise and set times bracket visibility as the solver sees it.
+        let user = scenario.users()[0].position();
+        let visible = |t: f64| {
+            user.can_see_above_with(
+                &orbit.ecef(t, OrbitModel::Keplerian),
+                constraints.elevation_limit(),
+                constraints.elevation_model,
+            )
+        };
+        assert!(!visible(pass.rise - 0.01) && visible(pass.rise + 0.01));
+        assert!(visible(pass.set - 0.01) && !visible(pass.set + 0.01));
+    }
+
+    #[test]
+    fn test_pass_table() {
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+user 2 0 0 6371
+sat 1 6921 0 0
+sat_orbit 2 0 0 -30 550",
+        );
+        let epochs = parse_epochs("0:600:60").unwrap();
+        let tables = scenario.passes(
+            scenario.users(),
+            &epochs,
+            OrbitModel::J2,
+            &Constraints::default(),
+        );
+        // The fixed satellite is up for the whole window, and the orbiting one
+        // rises part way through.
+        let passes = &tables[0].passes;
+        assert_eq!(passes.len(), 2);
+        assert_eq!(
+            (passes[0].satellite, passes[0].rise, passes[0].set),
+            (1, 0.0, 600.0)
+        );
+        assert!(passes[0].max_elevation > 89.99);
+        assert!(passes[1].rise > 0.0 && passes[1].satellite == 2);
+        assert_eq!(tables[1].to_string(), "user 2\n\tno passes\n");
+        assert!(tables[0]
+            .to_string()
+            .starts_with("user 1\n\tsat 1 rise 0.000 culmination "));
+    }
+}
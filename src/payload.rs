@@ -0,0 +1,401 @@
+// What each satellite's payload can do. A fleet mixes generations, so
+// satellites of a shell (or any other group) can share settings through a
+// scenario line,
+//   shell v2 beams=48 bands=A,B,C max_scan=56.5
+// and a `shell=v2` attribute on their `sat` lines; the same keys on a
+// satellite's own line take precedence, and the constraints fill in the rest.
+// A value that doesn't parse, on either line, or a `shell=` naming a shell the
+// scenario doesn't define, is an input error.
+//
+// beams is how many beams the satellite has, in place of the constraints'
+// beams, and bands the only bands it can use. band_beams caps the beams on
+// each band, either all alike (`band_beams=8`) or band by band
+// (`band_beams=A:8,B:8,C:4`, leaving the others as they were).
+//
+// max_scan is the furthest off nadir, in degrees, the satellite's phased
+// arrays can steer a beam: the angle at the satellite between the Earth's
+// centre and the user, more than 0 and at most 90. It limits beams
+// independently of the user's elevation mask, and there is no limit by
+// default, as in evaluate.py.
+use std::fmt;
+
+use crate::{
+    constraints::Constraints,
+    scenario::{Attributes, Band, EntityKind, Scenario},
+};
+
+// Settings shared by the satellites of one `shell` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellParams {
+    name: String,
+    attributes: Attributes,
+}
+
+// One satellite's resolved limits.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Payload {
+    pub beams: Option<usize>,           // None for the constraints' beams
+    pub bands: Option<Vec<Band>>,       // None for all of BANDS
+    pub band_beams: [Option<usize>; 4], // In BANDS order; None for no cap
+    pub max_scan: Option<f64>,          // Degrees off nadir, margin included
+}
+
+impl ShellParams {
+    // Known keys must parse; others are kept but ignored.
+    pub fn new(name: &str, attributes: Attributes) -> Result<ShellParams, String> {
+        let shell = ShellParams {
+            name: name.to_string(),
+            attributes,
+        };
+        parse_payload(&shell.attributes, &mut Payload::default())
+            .map_err(|e| format!("shell {}: {}", name, e))?;
+        Ok(shell)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+}
+
+impl Payload {
+    pub fn beams(&self, constraints: &Constraints) -> usize {
+        self.beams.unwrap_or(constraints.beams)
+    }
+
+    pub fn band_beams(&self, band: Band) -> Option<usize> {
+        self.band_beams[band as usize]
+    }
+
+    pub fn carries(&self, band: Band) -> bool {
+        match &self.bands {
+            Some(bands) => bands.contains(&band),
+            None => true,
+        }
+    }
+}
+
+// Apply the payload keys among `attributes` over `payload`.
+fn parse_payload(attributes: &[(String, String)], payload: &mut Payload) -> Result<(), String> {
+    for (key, value) in attributes {
+        match key.as_str() {
+            "beams" => {
+                let beams = value
+                    .parse()
+                    .map_err(|_| format!("invalid beams '{}'", value))?;
+                payload.beams = Some(beams);
+            }
+            "bands" => {
+                let bands = value
+                    .split(',')
+                    .map(|band| band.parse())
+                    .collect::<Result<Vec<Band>, String>>()
+                    .map_err(|_| format!("invalid bands '{}'", value))?;
+                payload.bands = Some(bands);
+            }
+            "band_beams" => {
+                let invalid = || format!("invalid band_beams '{}'", value);
+                match value.parse::<usize>() {
+                    Ok(beams) => payload.band_beams = [Some(beams); 4],
+                    Err(_) => {
+                        for cap in value.split(',') {
+                            let (band, beams) = cap.split_once(':').ok_or_else(invalid)?;
+                            let band: Band = band.parse().map_err(|_| invalid())?;
+                            payload.band_beams[band as usize] =
+                                Some(beams.parse().map_err(|_| invalid())?);
+                        }
+                    }
+                }
+            }
+            "max_scan" => {
+                let angle = value
+                    .parse()
+                    .ok()
+                    .filter(|angle: &f64| *angle > 0.0 && *angle <= 90.0)
+                    .ok_or_else(|| format!("invalid max_scan '{}'", value))?;
+                payload.max_scan = Some(angle);
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+impl Scenario {
+    pub fn shells(&self) -> &[ShellParams] {
+        &self.shells
+    }
+
+    pub fn shell(&self, name: &str) -> Option<&ShellParams> {
+        self.shells.iter().find(|shell| shell.name == name)
+    }
+
+    // The satellite's limits: its own attributes, then its shell's, then the
+    // constraints. The scan margin keeps beams that much inside max_scan.
+    // Reading a scenario rejects attributes that don't parse (see
+    // check_payload), so only one built in code can have any, and they are
+    // skipped.
+    pub fn payload(&self, satellite: i32, constraints: &Constraints) -> Payload {
+        let mut payload = Payload {
+            band_beams: [constraints.band_beams; 4],
+            max_scan: constraints.max_scan,
+            ..Payload::default()
+        };
+        let shell = self
+            .attribute(EntityKind::Satellite, satellite, "shell")
+            .and_then(|name| self.shell(name));
+        for attributes in shell
+            .map(ShellParams::attributes)
+            .into_iter()
+            .chain(Some(self.attributes(EntityKind::Satellite, satellite)))
+        {
+            let mut resolved = payload.clone();
+            if parse_payload(attributes, &mut resolved).is_ok() {
+                payload = resolved;
+            }
+        }
+        payload.max_scan = payload
+            .max_scan
+            .map(|angle| angle - constraints.margins.scan);
+        payload
+    }
+
+    // The satellite's own payload attributes must parse, and its shell must
+    // be defined.
+    pub(crate) fn check_payload(&self, satellite: i32) -> Result<(), String> {
+        let context = |message| format!("sat {}: {}", satellite, message);
+        if let Some(name) = self.attribute(EntityKind::Satellite, satellite, "shell") {
+            if self.shell(name).is_none() {
+                return Err(context(format!("undefined shell '{}'", name)));
+            }
+        }
+        let attributes = self.attributes(EntityKind::Satellite, satellite);
+        parse_payload(attributes, &mut Payload::default()).map_err(context)
+    }
+
+    // A later definition of a shell replaces an earlier one.
+    pub(crate) fn add_shell(&mut self, shell: ShellParams) {
+        match self.shells.iter_mut().find(|s| s.name == shell.name) {
+            Some(existing) => *existing = shell,
+            None => self.shells.push(shell),
+        }
+    }
+}
+
+impl fmt::Display for ShellParams {
+    // As a `shell` line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "shell {}", self.name)?;
+        for (key, value) in &self.attributes {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{solution::Solution, validate::Violation};
+
+    #[test]
+    fn test_payload() {
+        let scenario = Scenario::from_str(
+            "shell v1 max_scan=50
+sat 1 6921 0 0 shell=v1
+sat 2 6921 0 0 shell=v1 max_scan=40
+sat 3 6921 0 0",
+        );
+        assert_eq!(scenario.shells()[0].to_string(), "shell v1 max_scan=50");
+        let mut constraints = Constraints::default();
+        let max_scan = |id| scenario.payload(id, &constraints).max_scan;
+        assert_eq!(max_scan(1), Some(50.0));
+        assert_eq!(max_scan(2), Some(40.0));
+        assert_eq!(max_scan(3), None);
+
+        constraints.max_scan = Some(60.0);
+        constraints.margins.scan = 1.0;
+        let max_scan = |id| scenario.payload(id, &constraints).max_scan;
+        assert_eq!(max_scan(1), Some(49.0));
+        assert_eq!(max_scan(3), Some(59.0));
+        assert!(ShellParams::new("bad", vec![("max_scan".into(), "wide".into())]).is_err());
+        for angle in ["NaN", "inf", "0", "-10", "90.5"].iter() {
+            let bad = vec![("max_scan".into(), angle.to_string())];
+            assert_eq!(
+                ShellParams::new("bad", bad),
+                Err(format!("shell bad: invalid max_scan '{}'", angle))
+            );
+        }
+
+        // Payload keys that don't parse, and undefined shells, are errors.
+        let error = |input: &str| Scenario::parse(input).err().map(|e| e.to_string());
+        assert_eq!(
+            error("sat 1 6921 0 0\nsat 2 6921 0 0 beams=abc bands=Q"),
+            Some(String::from("line 2: sat 2: invalid beams 'abc'"))
+        );
+        assert_eq!(
+            error("sat 1 6921 0 0 bands=A,Q"),
+            Some(String::from("line 1: sat 1: invalid bands 'A,Q'"))
+        );
+        assert_eq!(
+            error("sat 1 6921 0 0 max_scan=NaN"),
+            Some(String::from("line 1: sat 1: invalid max_scan 'NaN'"))
+        );
+        assert_eq!(
+            error("sat 1 6921 0 0 shell=v9"),
+            Some(String::from("line 1: sat 1: undefined shell 'v9'"))
+        );
+        assert_eq!(
+            error("shell v1 band_beams=A:many"),
+            Some(String::from(
+                "line 1: shell v1: invalid band_beams 'A:many'"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_max_scan() {
+        // One user straight below a satellite 550km up and one 4 degrees of
+        // arc away, where the beam is 38 degrees off nadir and the satellite
+        // 48 degrees up.
+        let (s, c) = 4f64.to_radians().sin_cos();
+        let input = format!(
+            "user 1 6371 0 0
+user 2 {} 0 {}
+sat 1 6921 0 0 max_scan=30",
+            6371.0 * c,
+            6371.0 * s
+        );
+        let scenario = Scenario::from_str(&input);
+        let solution = scenario.optimize();
+        assert_eq!(solution.covered(), 1);
+        assert!(solution.by_user(1).is_some());
+        assert_eq!(scenario.validate(&solution), vec![]);
+
+        let forced: Solution = "sat 1 beam 1 user 2 color A".parse().unwrap();
+        match scenario.validate(&forced)[..] {
+            [Violation::ScanAngle { user: 2, angle, .. }] => assert!(angle > 38.0),
+            ref violations => panic!("{:?}", violations),
+        }
+        let unlimited = Scenario::from_str(&input.replace(" max_scan=30", ""));
+        assert_eq!(unlimited.optimize().covered(), 2);
+
+        let analysis = scenario.analyze(&solution);
+        assert_eq!(analysis.scan_angles.total(), 1);
+        assert!(analysis.slack.scan.unwrap() > 29.99);
+    }
+
+    // Users below (6921, 0, 0) and a degree of arc around it, far enough
+    // apart to share a band.
+    fn four_users() -> String {
+        let (s, c) = 1f64.to_radians().sin_cos();
+        let (near, off) = (6371.0 * c, 6371.0 * s);
+        format!(
+            "user 1 6371 0 0
+user 2 {near} {off} 0
+user 3 {near} {minus} 0
+user 4 {near} 0 {off}",
+            near = near,
+            off = off,
+            minus = -off
+        )
+    }
+
+    #[test]
+    fn test_heterogeneous_satellites() {
+        // Four users around the point below two satellites, each beam well
+        // clear of the others: an older satellite with two beams on band A,
+        // and a newer one with a single beam on band D.
+        let input = format!(
+            "shell old beams=2 bands=A
+{}
+sat 1 6921 0 0 shell=old
+sat 2 6921 0 0 beams=1 bands=D",
+            four_users()
+        );
+        let scenario = Scenario::from_str(&input);
+        let payload = scenario.payload(1, &Constraints::default());
+        assert_eq!(
+            (payload.beams, payload.bands),
+            (Some(2), Some(vec![Band::A]))
+        );
+        let solution = scenario.optimize();
+        assert_eq!(solution.covered(), 3);
+        assert_eq!(solution.by_satellite(1).len(), 2);
+        assert!(solution.by_satellite(2).iter().all(|a| a.band == Band::D));
+        assert_eq!(scenario.validate(&solution), vec![]);
+        let analysis = scenario.analyze(&solution);
+        assert_eq!((analysis.saturated, analysis.max_possible), (2, 3));
+
+        let forced: Solution = "sat 1 beam 3 user 1 color B".parse().unwrap();
+        assert_eq!(
+            scenario.validate(&forced),
+            vec![
+                Violation::InvalidBeam {
+                    satellite: 1,
+                    beam: 3,
+                    max: 2
+                },
+                Violation::SatelliteBand {
+                    satellite: 1,
+                    beam: 3,
+                    band: Band::B
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_band_caps() {
+        let mut payload = Payload::default();
+        parse_payload(&[("band_beams".into(), "3".into())], &mut payload).unwrap();
+        parse_payload(&[("band_beams".into(), "A:1,C:0".into())], &mut payload).unwrap();
+        assert_eq!(payload.band_beams, [Some(1), Some(3), Some(0), Some(3)]);
+        assert!(parse_payload(&[("band_beams".into(), "A1".into())], &mut payload).is_err());
+
+        // Uncapped, every user would go on band A. With one beam a band
+        // they spread over the satellite's two bands, and the rest go
+        // unserved.
+        let input = format!("{}\nsat 1 6921 0 0 bands=A,B", four_users());
+        let uncapped = Scenario::from_str(&input);
+        let solution = uncapped.optimize();
+        assert_eq!(solution.covered(), 4);
+        assert!(solution.iter().all(|a| a.band == Band::A));
+
+        let capped = Scenario::from_str(&format!("{} band_beams=1", input));
+        let solution = capped.optimize();
+        assert_eq!(solution.covered(), 2);
+        assert_eq!(
+            solution.iter().map(|a| a.band).collect::<Vec<_>>(),
+            [Band::A, Band::B]
+        );
+        assert_eq!(capped.validate(&solution), vec![]);
+        assert_eq!(capped.analyze(&solution).band_saturated, [1, 1, 0, 0]);
+        let constraints = Constraints {
+            band_beams: Some(2),
+            ..Constraints::default()
+        };
+        assert_eq!(
+            uncapped
+                .optimize_with(Default::default(), &constraints)
+                .covered(),
+            4
+        );
+
+        let forced: Solution = "sat 1 beam 1 user 1 color A\nsat 1 beam 2 user 2 color A"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            capped.validate(&forced),
+            vec![Violation::BandBeams {
+                satellite: 1,
+                band: Band::A,
+                count: 2,
+                max: 1
+            }]
+        );
+    }
+}
@@ -1,11 +1,45 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use crate::geodesy::WGS84_F;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Position {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+// Mean Earth radius, in km. The test cases put users at this distance.
+pub const EARTH_RADIUS: f64 = 6371.0;
+
+// Squared eccentricity of the WGS84 ellipsoid.
+const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+
+// Which way is "up" when measuring a user's elevation. evaluate.py uses the
+// geocentric radius vector; a real terminal levels itself to the local normal
+// of the WGS84 ellipsoid, which tilts toward the pole by up to ~0.19 degrees at
+// mid latitudes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ElevationModel {
+    #[default]
+    Geocentric,
+    Geodetic,
 }
 
-#[allow(dead_code)]
+// Where a target appears from a point on the ground, in the local east, north,
+// up frame: azimuth in degrees clockwise from north, elevation in degrees above
+// the horizon, and straight-line distance in km.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LookAngles {
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub range_km: f64,
+}
+
+// Radius of the geostationary arc, in km; the test cases' GEO interferers sit
+// on it.
+pub const GEO_RADIUS: f64 = 42164.0;
+
 pub const ORIGIN: Position = Position {
     x: 0.0,
     y: 0.0,
@@ -13,15 +47,15 @@ pub const ORIGIN: Position = Position {
 };
 
 impl Position {
-    pub fn new(x: f32, y: f32, z: f32) -> Position {
+    pub fn new(x: f64, y: f64, z: f64) -> Position {
         Position { x, y, z }
     }
 
-    pub fn len(&self) -> f32 {
+    pub fn len(&self) -> f64 {
         let x = self.x;
         let y = self.y;
         let z = self.z;
-        f32::sqrt(x * x + y * y + z * z)
+        f64::sqrt(x * x + y * y + z * z)
     }
 
     pub fn sub(&self, a: &Position) -> Position {
@@ -32,33 +66,84 @@ impl Position {
         }
     }
 
-    pub fn dot(a: &Position, b: &Position) -> f32 {
+    pub fn dot(a: &Position, b: &Position) -> f64 {
         a.x * b.x + a.y * b.y + a.z * b.z
     }
 
-    pub fn angle_origin(a: &Position, b: &Position, o: &Position) -> f32 {
+    pub fn angle_origin(a: &Position, b: &Position, o: &Position) -> f64 {
         Position::angle(&a.sub(o), &b.sub(o))
     }
 
-    pub fn angle(a: &Position, b: &Position) -> f32 {
-        let r = Position::dot(&a.norm(), &b.norm());
-        // When close to 0, catch possible NaN
-        if (r - 1.0).abs() < 0.01 {
-            return 0.0;
+    pub fn cross(a: &Position, b: &Position) -> Position {
+        Position {
+            x: a.y * b.z - a.z * b.y,
+            y: a.z * b.x - a.x * b.z,
+            z: a.x * b.y - a.y * b.x,
         }
-        let t = f32::acos(r);
-        t
     }
 
-    pub fn separation(&self, a: &Position, b: &Position) -> f32 {
+    // Angle between two vectors, in radians. atan2 of the cross and dot
+    // products stays accurate near 0 and 180 degrees, where acos of the dot
+    // product loses precision, and needs no normalizing or clamping.
+    pub fn angle(a: &Position, b: &Position) -> f64 {
+        Position::cross(a, b).len().atan2(Position::dot(a, b))
+    }
+
+    pub fn separation(&self, a: &Position, b: &Position) -> f64 {
         Position::angle(&a.sub(self), &b.sub(self))
     }
 
+    // The angle, in radians, between straight down from here and the line of
+    // sight to `target`: how far off nadir a satellite here points to reach it.
+    pub fn off_nadir(&self, target: &Position) -> f64 {
+        self.separation(&Position::new(0.0, 0.0, 0.0), target)
+    }
+
+    // Whether the Earth hides `target` from here: the line of sight leaves
+    // below the horizontal plane, into the ground, as it does for anything
+    // on the far side of the planet.
+    pub fn is_occluded(&self, target: &Position) -> bool {
+        Position::dot(&target.sub(self), self) < 0.0
+    }
+
+    // The smallest angle, in radians, between the line of sight from here to
+    // `target` and any point of the geostationary arc: the whole circle of
+    // GEO_RADIUS in the equatorial plane, not a list of slots along it. The
+    // angle to the arc point at longitude λ is smooth in λ, so a scan every
+    // 5 degrees (arc points no more than about 6 degrees apart as seen from
+    // the ground) finds the closest stretch, and a golden-section search
+    // over the scan steps either side of it pins down the minimum.
+    pub fn geo_arc_separation(&self, target: &Position) -> f64 {
+        const SCAN: f64 = 5.0;
+        let angle = |longitude: f64| {
+            let (sin, cos) = longitude.to_radians().sin_cos();
+            let arc = Position::new(GEO_RADIUS * cos, GEO_RADIUS * sin, 0.0);
+            self.separation(target, &arc)
+        };
+        let closest = (0..(360.0 / SCAN) as usize)
+            .map(|i| i as f64 * SCAN)
+            .min_by(|a, b| angle(*a).partial_cmp(&angle(*b)).unwrap_or(Ordering::Equal))
+            .unwrap_or(0.0);
+
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let (mut low, mut high) = (closest - SCAN, closest + SCAN);
+        while high - low > 1e-7 {
+            let a = high - ratio * (high - low);
+            let b = low + ratio * (high - low);
+            if angle(a) < angle(b) {
+                high = b;
+            } else {
+                low = a;
+            }
+        }
+        angle((low + high) / 2.0).min(angle(closest))
+    }
+
     pub fn norm(&self) -> Position {
         self.scale(1.0 / self.len())
     }
 
-    pub fn scale(&self, n: f32) -> Position {
+    pub fn scale(&self, n: f64) -> Position {
         Position::new(self.x * n, self.y * n, self.z * n)
     }
 
@@ -72,7 +157,6 @@ impl Position {
     // radius of the cone of `angle` degrees at that point. However, since
     // angle is always 45 for this project, it simplifies to the radius being
     // equal to the height on the cone.
-    #[allow(dead_code)]
     pub fn can_see_cone(&self, target: &Position) -> bool {
         let n = self.norm();
         let l = self.len();
@@ -100,22 +184,148 @@ impl Position {
     pub fn can_see(&self, target: &Position) -> bool {
         self.can_see_sat(target)
     }
+
+    // The same test as can_see_sat, for an arbitrary elevation mask in degrees.
+    pub fn can_see_above(&self, target: &Position, elevation: f64) -> bool {
+        Position::angle_origin(&ORIGIN, target, self).to_degrees() > 90.0 + elevation
+    }
+
+    pub fn can_see_above_with(
+        &self,
+        target: &Position,
+        elevation: f64,
+        model: ElevationModel,
+    ) -> bool {
+        self.elevation(target, model) > elevation
+    }
+
+    // Degrees of `target` above the horizon at this position. Like
+    // can_see_sat, this is the angle between "down" and the line of sight,
+    // less 90.
+    pub fn elevation(&self, target: &Position, model: ElevationModel) -> f64 {
+        let angle = match model {
+            ElevationModel::Geocentric => Position::angle_origin(&ORIGIN, target, self),
+            ElevationModel::Geodetic => {
+                Position::angle(&self.up(model).scale(-1.0), &target.sub(self))
+            }
+        };
+        angle.to_degrees() - 90.0
+    }
+
+    // Unit vector pointing up from this position. The geodetic normal is the
+    // gradient of the ellipsoid through the point, which is exact on the
+    // WGS84 surface and within 0.001 degrees for anything within 15 km of it.
+    pub fn up(&self, model: ElevationModel) -> Position {
+        match model {
+            ElevationModel::Geocentric => self.norm(),
+            ElevationModel::Geodetic => {
+                Position::new(self.x, self.y, self.z / (1.0 - WGS84_E2)).norm()
+            }
+        }
+    }
+
+    pub fn look_angles(&self, target: &Position, model: ElevationModel) -> LookAngles {
+        let up = self.up(model);
+        // East is undefined at the poles; pick the +y axis there, which makes
+        // north point along -x.
+        let east = Position::cross(&Position::new(0.0, 0.0, 1.0), &up);
+        let east = if east.len() < 1e-12 {
+            Position::new(0.0, 1.0, 0.0)
+        } else {
+            east.norm()
+        };
+        let north = Position::cross(&up, &east);
+
+        let line = target.sub(self);
+        let (e, n, u) = (
+            Position::dot(&line, &east),
+            Position::dot(&line, &north),
+            Position::dot(&line, &up),
+        );
+        LookAngles {
+            azimuth: e.atan2(n).to_degrees().rem_euclid(360.0),
+            elevation: u.atan2(e.hypot(n)).to_degrees(),
+            range_km: line.len(),
+        }
+    }
+}
+
+impl FromStr for ElevationModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "geocentric" => Ok(ElevationModel::Geocentric),
+            "geodetic" => Ok(ElevationModel::Geodetic),
+            _ => Err(format!("unknown elevation model '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for ElevationModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ElevationModel::Geocentric => "geocentric",
+                ElevationModel::Geodetic => "geodetic",
+            }
+        )
+    }
+}
+
+impl fmt::Display for LookAngles {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "az {:.2} el {:.2} range {:.1}",
+            self.azimuth, self.elevation, self.range_km
+        )
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.x, self.y, self.z)
+    }
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    // `<x> <y> <z>` in km, as Display writes it.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let coordinates = parts
+            .iter()
+            .map(|part| {
+                part.parse::<f64>()
+                    .map_err(|_| format!("invalid coordinate '{}'", part))
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
+        match coordinates[..] {
+            [x, y, z] => Ok(Position { x, y, z }),
+            _ => Err(format!("expected x y z, got '{}'", s)),
+        }
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::excessive_precision)]
 mod test {
-    use std::f32::consts::PI;
+    use std::f64::consts::PI;
 
     use super::*;
 
     #[test]
     fn test_build_position() {
-        let position = String::from("6371 0 0");
-        let parts = position.split_whitespace();
-        let position: Position = parts.collect();
+        let position: Position = "6371 0 0".parse().unwrap();
         assert_eq!(position.x, 6371.0);
         assert_eq!(position.y, 0.0);
         assert_eq!(position.z, 0.0);
+        assert!("6371 0".parse::<Position>().is_err());
+        assert!("6371 0 zero".parse::<Position>().is_err());
     }
 
     #[test]
@@ -123,7 +333,7 @@ mod test {
         let p1 = Position::new(2.0, 3.0, 4.0);
         let p2 = Position::new(3.0, 4.0, 6.0);
 
-        assert_eq!(p2.len(), 7.81025);
+        assert_eq!(p2.len(), 61f64.sqrt());
 
         assert_eq!(p2.sub(&p1), Position::new(1.0, 1.0, 2.0));
 
@@ -156,6 +366,10 @@ mod test {
         assert_eq!(x.can_see(&p2), false);
         assert_eq!(x.can_see(&p3), false);
         assert_eq!(x.can_see(&p4), false);
+
+        assert_eq!(x.can_see_above(&p1, 10.0), true);
+        assert_eq!(x.can_see_above(&p1, 75.0), false);
+        assert_eq!(x.can_see_above(&p2, 0.0), false);
     }
 
     #[test]
@@ -179,15 +393,238 @@ mod test {
         assert_eq!(g4.can_see(&s4), false);
     }
 
+    // evaluate.py's `angle`, in degrees: acos of the clamped dot product of
+    // the normalized vectors from `o`.
+    fn evaluate_angle(o: &Position, a: &Position, b: &Position) -> f64 {
+        let (j, k) = (a.sub(o).norm(), b.sub(o).norm());
+        Position::dot(&j, &k).clamp(-1.0, 1.0).acos().to_degrees()
+    }
+
     #[test]
     fn regression_angle() {
         // This specific pair of ground stations triggerd an f32 precision error
-        // in f32::cos, so there's a special case in angle for close to 0*
+        // in f32::cos, which used to be papered over by treating anything
+        // within 0.01 of a dot product of 1 (about 8 degrees) as 0.
         let s = Position::new(-5111.007144121957, -1334.7360828140702, 4471.7252332817225);
         let p1 = Position::new(-4462.399898375494, -1507.4791341925356, 4286.176851267787);
         let p2 = Position::new(-4462.341423785467, -1507.5185635095902, 4286.223546883291);
 
-        let separation = s.separation(&p1, &p2);
-        assert_eq!(separation, 0.0);
+        let separation = s.separation(&p1, &p2).to_degrees();
+        assert!((separation - 0.0055010).abs() < 1e-6, "{}", separation);
+        assert!((separation - evaluate_angle(&s, &p1, &p2)).abs() < 1e-9);
+    }
+
+    // Every angle the evaluator computes on the test cases: user elevations
+    // against every satellite, same-satellite user pairs and user to
+    // interferer separations for every visible satellite. acos is itself
+    // imprecise within a hair of 0 and 180 degrees, so those are only
+    // compared loosely.
+    #[test]
+    fn test_angle_matches_evaluator() {
+        use crate::scenario::Scenario;
+
+        let cases = [
+            include_str!("../beam-planning/test_cases/00_example.txt"),
+            include_str!("../beam-planning/test_cases/01_simplest_possible.txt"),
+            include_str!("../beam-planning/test_cases/02_two_users.txt"),
+            include_str!("../beam-planning/test_cases/03_five_users.txt"),
+            include_str!("../beam-planning/test_cases/04_one_interferer.txt"),
+            include_str!("../beam-planning/test_cases/05_equatorial_plane.txt"),
+            include_str!("../beam-planning/test_cases/06_partially_fullfillable.txt"),
+            include_str!("../beam-planning/test_cases/07_eighteen_planes.txt"),
+            include_str!("../beam-planning/test_cases/08_eighteen_planes_northern.txt"),
+        ];
+        let mut compared = 0;
+        let mut check = |o: &Position, a: &Position, b: &Position| {
+            let angle = Position::angle_origin(a, b, o).to_degrees();
+            let expected = evaluate_angle(o, a, b);
+            let tolerance = if !(0.01..=179.99).contains(&expected) {
+                1e-5
+            } else {
+                1e-9
+            };
+            assert!(
+                (angle - expected).abs() < tolerance,
+                "{} != {} at {} {} {}",
+                angle,
+                expected,
+                o,
+                a,
+                b
+            );
+            compared += 1;
+        };
+        for text in cases.iter() {
+            let scenario = Scenario::from_str(text);
+            for satellite in scenario.satellites().iter().map(|s| s.entity().position()) {
+                let visible: Vec<&Position> = scenario
+                    .users()
+                    .iter()
+                    .map(|u| u.position())
+                    .filter(|u| {
+                        check(u, &ORIGIN, satellite);
+                        u.can_see_sat(satellite)
+                    })
+                    .collect();
+                for (i, user) in visible.iter().enumerate() {
+                    for other in &visible[i + 1..] {
+                        check(satellite, user, other);
+                    }
+                    for interferer in scenario.interferers() {
+                        check(user, satellite, interferer.position());
+                    }
+                }
+            }
+        }
+        assert!(compared > 1_000_000, "{}", compared);
+    }
+
+    #[test]
+    fn test_geodetic_elevation() {
+        // On the equator and at the poles the two normals agree.
+        let user = Position::new(6378.137, 0.0, 0.0);
+        let overhead = Position::new(6928.137, 0.0, 0.0);
+        assert_eq!(user.elevation(&overhead, ElevationModel::Geodetic), 90.0);
+
+        // At 45 degrees the geodetic normal tilts ~0.19 degrees toward the
+        // pole, so a satellite due north at 45 degrees geocentric elevation is
+        // higher when measured from it.
+        let user = Position::new(4517.59, 0.0, 4487.35);
+        let up = user.norm();
+        let north = Position::new(-up.z, 0.0, up.x);
+        let satellite = Position::new(
+            user.x + 1000.0 * (up.x + north.x),
+            0.0,
+            user.z + 1000.0 * (up.z + north.z),
+        );
+        let geocentric = user.elevation(&satellite, ElevationModel::Geocentric);
+        let geodetic = user.elevation(&satellite, ElevationModel::Geodetic);
+        assert!((geocentric - 45.0).abs() < 1e-3);
+        assert!((geodetic - geocentric - 0.19).abs() < 0.01);
+        assert_eq!(
+            user.can_see_above_with(&satellite, 44.9, ElevationModel::Geocentric),
+            user.can_see_above(&satellite, 44.9)
+        );
+    }
+
+    #[test]
+    fn test_elevation_models_on_eighteen_planes() {
+        use crate::{constraints::Constraints, scenario::Scenario};
+
+        // (test case, user/satellite pairs on opposite sides of the 45 degree
+        // mask, NotVisible violations of the default solution when validated
+        // geodetically)
+        let cases = [
+            (
+                include_str!("../beam-planning/test_cases/07_eighteen_planes.txt"),
+                17,
+                3,
+            ),
+            (
+                include_str!("../beam-planning/test_cases/08_eighteen_planes_northern.txt"),
+                28,
+                6,
+            ),
+        ];
+        let geodetic = Constraints {
+            elevation_model: ElevationModel::Geodetic,
+            ..Constraints::default()
+        };
+        for (text, disagree, violations) in cases.iter() {
+            let scenario = Scenario::from_str(text);
+            let mut largest: f64 = 0.0;
+            let mut count = 0;
+            for user in scenario.users().iter().map(|u| u.position()) {
+                for satellite in scenario.satellites().iter().map(|s| s.entity().position()) {
+                    let a = user.elevation(satellite, ElevationModel::Geocentric);
+                    let b = user.elevation(satellite, ElevationModel::Geodetic);
+                    if a > 0.0 {
+                        largest = largest.max((a - b).abs());
+                    }
+                    if (a > 45.0) != (b > 45.0) {
+                        count += 1;
+                    }
+                }
+            }
+            assert!(largest > 0.18 && largest < 0.2, "{}", largest);
+            assert_eq!(count, *disagree);
+
+            let solution = scenario.optimize();
+            assert_eq!(
+                scenario.validate_with(&solution, &geodetic).len(),
+                *violations
+            );
+            let solution = scenario.optimize_with(Default::default(), &geodetic);
+            assert!(scenario.validate_with(&solution, &geodetic).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_geo_arc_separation() {
+        // From the equator, straight up points at the arc.
+        let user = Position::new(6371.0, 0.0, 0.0);
+        let zenith = Position::new(6921.0, 0.0, 0.0);
+        assert!(user.geo_arc_separation(&zenith).to_degrees() < 1e-6);
+
+        // Elsewhere it matches a brute-force search along the arc.
+        let brute = |user: &Position, target: &Position| {
+            (0..360_000)
+                .map(|i| {
+                    let longitude = (i as f64 / 1000.0).to_radians();
+                    let arc = Position::new(
+                        GEO_RADIUS * longitude.cos(),
+                        GEO_RADIUS * longitude.sin(),
+                        0.0,
+                    );
+                    user.separation(target, &arc)
+                })
+                .fold(f64::INFINITY, f64::min)
+        };
+        let user = Position::new(3000.0, 4000.0, 3600.0).norm().scale(6371.0);
+        for target in [
+            Position::new(3500.0, 4200.0, 3800.0),
+            Position::new(2000.0, 5500.0, 3500.0),
+            Position::new(4500.0, 3000.0, 3000.0),
+        ]
+        .iter()
+        {
+            let angle = user.geo_arc_separation(target).to_degrees();
+            assert!((angle - brute(&user, target).to_degrees()).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_look_angles() {
+        let user = Position::new(6371.0, 0.0, 0.0);
+        let look = |target: Position| user.look_angles(&target, ElevationModel::Geocentric);
+
+        let overhead = look(Position::new(6921.0, 0.0, 0.0));
+        assert_eq!(overhead.elevation, 90.0);
+        assert_eq!(overhead.range_km, 550.0);
+
+        // On the equator at 0 longitude, east is +y and north is +z.
+        let north = look(Position::new(6371.0 + 500.0, 0.0, 500.0));
+        assert!((north.azimuth - 0.0).abs() < 1e-9);
+        assert!((north.elevation - 45.0).abs() < 1e-9);
+        let east = look(Position::new(6371.0 + 500.0, 500.0, 0.0));
+        assert!((east.azimuth - 90.0).abs() < 1e-9);
+        let south_west = look(Position::new(6371.0 + 100.0, -100.0, -100.0));
+        assert!((south_west.azimuth - 225.0).abs() < 1e-9);
+        assert!((south_west.range_km - 300f64.sqrt() * 10.0).abs() < 1e-9);
+
+        // Elevation agrees with the evaluator-style angle, in either model.
+        let user = Position::new(4517.59, 1000.0, 4487.35);
+        let satellite = Position::new(5000.0, 1500.0, 5000.0);
+        for model in [ElevationModel::Geocentric, ElevationModel::Geodetic].iter() {
+            let look = user.look_angles(&satellite, *model);
+            assert!((look.elevation - user.elevation(&satellite, *model)).abs() < 1e-9);
+        }
+
+        let pole = Position::new(0.0, 0.0, 6371.0);
+        let look = pole.look_angles(
+            &Position::new(-500.0, 0.0, 6871.0),
+            ElevationModel::Geocentric,
+        );
+        assert!((look.azimuth - 0.0).abs() < 1e-9);
     }
 }
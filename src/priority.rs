@@ -0,0 +1,171 @@
+// Weighted users and priority tiers. Users aren't all worth the same: a
+// `user` line may carry
+//   user 7 ... priority=2 weight=5
+// where priority is a strict tier, higher first, and weight what serving the
+// user is worth within its tier. The solver packs tiers one at a time, from
+// the highest, so a user is never left unserved for one of a lower tier, and
+// within a tier takes heavier users first. Both default to what evaluate.py
+// assumes, priority 0 and weight 1, so a scenario without them is solved and
+// analyzed exactly as before. A priority that isn't an integer, or a weight
+// that isn't a non-negative number, is an input error.
+use std::collections::BTreeMap;
+
+use crate::{
+    scenario::{Entity, EntityKind, Scenario},
+    solution::Solution,
+};
+
+pub const DEFAULT_PRIORITY: i32 = 0;
+pub const DEFAULT_WEIGHT: f64 = 1.0;
+
+impl Scenario {
+    // The user's `priority` attribute. Reading or building a scenario
+    // rejects invalid values (see check_priority), so the default only
+    // stands in for a missing one.
+    pub fn priority(&self, user: i32) -> i32 {
+        self.attribute(EntityKind::User, user, "priority")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_PRIORITY)
+    }
+
+    // The user's `weight` attribute, likewise.
+    pub fn weight(&self, user: i32) -> f64 {
+        self.attribute(EntityKind::User, user, "weight")
+            .and_then(parse_weight)
+            .unwrap_or(DEFAULT_WEIGHT)
+    }
+
+    pub(crate) fn check_priority(&self, user: i32) -> Result<(), String> {
+        let attribute = |key| self.attribute(EntityKind::User, user, key);
+        if let Some(value) = attribute("priority") {
+            if value.parse::<i32>().is_err() {
+                return Err(format!("user {}: invalid priority '{}'", user, value));
+            }
+        }
+        match attribute("weight") {
+            Some(value) if parse_weight(value).is_none() => {
+                Err(format!("user {}: invalid weight '{}'", user, value))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Whether any user sets a priority or weight.
+    pub fn is_weighted(&self) -> bool {
+        self.users().iter().any(|user| {
+            let attribute = |key| self.attribute(EntityKind::User, user.id(), key);
+            attribute("priority").is_some() || attribute("weight").is_some()
+        })
+    }
+
+    // Users grouped by priority, highest first, each tier in input order.
+    pub fn tiers(&self) -> Vec<(i32, Vec<Entity>)> {
+        let mut tiers: BTreeMap<i32, Vec<Entity>> = BTreeMap::new();
+        for user in self.users() {
+            tiers
+                .entry(self.priority(user.id()))
+                .or_default()
+                .push(*user);
+        }
+        tiers.into_iter().rev().collect()
+    }
+
+    // The summed weight of the users the solution serves.
+    pub fn covered_weight(&self, solution: &Solution) -> f64 {
+        self.users()
+            .iter()
+            .filter(|user| solution.by_user(user.id()).is_some())
+            .map(|user| self.weight(user.id()))
+            .sum()
+    }
+}
+
+// A finite, non-negative weight.
+fn parse_weight(value: &str) -> Option<f64> {
+    value
+        .parse()
+        .ok()
+        .filter(|weight: &f64| weight.is_finite() && *weight >= 0.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_attributes() {
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0 priority=2 weight=5
+user 2 6371 0 0 weight=0.5
+user 3 6371 0 0
+user 4 6371 0 0 priority=2",
+        );
+        assert!(scenario.is_weighted());
+        assert!(
+            !Scenario::from_str("class maritime\nuser 1 6371 0 0 class=maritime").is_weighted()
+        );
+        assert_eq!(
+            (1..=4).map(|id| scenario.priority(id)).collect::<Vec<_>>(),
+            [2, 0, 0, 2]
+        );
+        assert_eq!(
+            (1..=4).map(|id| scenario.weight(id)).collect::<Vec<_>>(),
+            [5.0, 0.5, 1.0, 1.0]
+        );
+        let tiers: Vec<(i32, Vec<i32>)> = scenario
+            .tiers()
+            .into_iter()
+            .map(|(priority, users)| (priority, users.iter().map(Entity::id).collect()))
+            .collect();
+        assert_eq!(tiers, [(2, vec![1, 4]), (0, vec![2, 3])]);
+        let error = |input: &str| Scenario::parse(input).err().map(|e| e.to_string());
+        assert_eq!(
+            error("user 1 6371 0 0\nuser 2 6371 0 0 priority=high"),
+            Some(String::from("line 2: user 2: invalid priority 'high'"))
+        );
+        for weight in ["abc", "-1", "inf", "NaN"].iter() {
+            assert_eq!(
+                error(&format!("user 1 6371 0 0 weight={}", weight)),
+                Some(format!("line 1: user 1: invalid weight '{}'", weight))
+            );
+        }
+    }
+
+    #[test]
+    fn test_strict_tiers() {
+        // One single-beam satellite and three users under it. Unweighted the
+        // solver serves the last; a weight picks the heavier user, and a
+        // priority wins over any weight.
+        let users = "user 1 6371 0 0
+user 2 6371 0 0
+user 3 6371 0 0";
+        let served = |input: &str| {
+            let scenario = Scenario::from_str(&format!("{}\nsat 1 6921 0 0 beams=1", input));
+            let solution = scenario.optimize();
+            assert_eq!(solution.covered(), 1);
+            assert_eq!(scenario.validate(&solution), vec![]);
+            let user = solution.iter().next().unwrap().user;
+            (user, scenario.covered_weight(&solution))
+        };
+        assert_eq!(served(users), (3, 1.0));
+        let weighted = users.replace("user 1 6371 0 0", "user 1 6371 0 0 weight=3");
+        assert_eq!(served(&weighted), (1, 3.0));
+        let tiered = weighted.replace("user 2 6371 0 0", "user 2 6371 0 0 priority=1");
+        assert_eq!(served(&tiered), (2, 1.0));
+
+        // Nor does keeping an earlier assignment hold back a higher tier.
+        let scenario = Scenario::from_str(&format!("{}\nsat 1 6921 0 0 beams=1", tiered));
+        let previous: Solution = "sat 1 beam 1 user 1 color A".parse().unwrap();
+        let solution = scenario.optimize_from(&previous, Default::default(), &Default::default());
+        assert!(solution.by_user(2).is_some());
+
+        let analysis = scenario.analyze(&solution);
+        let tiers: Vec<_> = analysis
+            .priorities
+            .iter()
+            .map(|t| (t.priority, t.users, t.covered, t.weight, t.covered_weight))
+            .collect();
+        assert_eq!(tiers, [(1, 1, 1, 1.0, 1.0), (0, 2, 0, 4.0, 0.0)]);
+        assert_eq!((analysis.weight, analysis.covered_weight), (5.0, 1.0));
+    }
+}
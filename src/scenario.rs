@@ -1,13 +1,45 @@
-use super::position::Position;
-use std::{fmt, iter::FromIterator};
-
+use super::{
+    geodesy::{Datum, Geodetic},
+    horizon::HorizonMask,
+    orbit::Orbit,
+    payload::ShellParams,
+    position::Position,
+    solution::ParseError,
+    terminal::TerminalClass,
+    time::Utc,
+    tle::{Tle, TleError},
+};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+// The problem to solve. Once built, a scenario is not changed by solving it;
+// the solver's output is a separate `Solution`.
 pub struct Scenario {
-    users: Vec<Entity>,
-    satellites: Vec<Satellite>,
-    pub interferers: Vec<Entity>,
-    pub assigned: usize,
+    pub(crate) users: Vec<Entity>,
+    pub(crate) satellites: Vec<Satellite>,
+    pub(crate) interferers: Vec<Entity>,
+    pub(crate) attributes: HashMap<(EntityKind, i32), Attributes>,
+    pub(crate) datum: Datum, // For `_geo` lines and reporting lat/lon
+    pub(crate) orbits: HashMap<(EntityKind, i32), Orbit>, // For propagation
+    pub(crate) epoch: Option<Utc>, // From an `epoch` line
+    pub(crate) tles: HashMap<(EntityKind, i32), Tle>,
+    pub(crate) norad: HashMap<(EntityKind, i32), u32>, // Awaiting `load_tles`
+    pub(crate) tle_errors: Vec<(EntityKind, i32, TleError)>,
+    pub(crate) horizons: HashMap<i32, HorizonMask>, // By user
+    pub(crate) classes: Vec<TerminalClass>,
+    pub(crate) shells: Vec<ShellParams>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntityKind {
+    User,
+    Satellite,
+    Interferer,
 }
 
+// Extra `key=value` tokens after an entity's position, kept in input order.
+// The solver ignores keys it doesn't know, so they pass through conversions.
+pub type Attributes = Vec<(String, String)>;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Entity {
     id: i32,
@@ -16,12 +48,6 @@ pub struct Entity {
 
 pub struct Satellite {
     entity: Entity,
-    beams: Vec<Beam>,
-}
-
-pub struct Beam {
-    user: Entity,
-    band: Band,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -35,22 +61,69 @@ pub enum Band {
 pub const BANDS: [Band; 4] = [Band::A, Band::B, Band::C, Band::D];
 
 impl Scenario {
-    fn new() -> Scenario {
+    pub(crate) fn new() -> Scenario {
         Scenario {
             // capacities from eighteen planes scenario
             users: Vec::with_capacity(2500),
             satellites: Vec::with_capacity(360),
             interferers: Vec::with_capacity(50),
-            assigned: 0,
+            attributes: HashMap::new(),
+            datum: Datum::default(),
+            orbits: HashMap::new(),
+            epoch: None,
+            tles: HashMap::new(),
+            norad: HashMap::new(),
+            tle_errors: Vec::new(),
+            horizons: HashMap::new(),
+            classes: Vec::new(),
+            shells: Vec::new(),
         }
     }
 
-    pub fn users(&self) -> &Vec<Entity> {
-        &self.users
+    // Parse a scenario from the line-based input format. Unknown lines and
+    // comments are skipped; the first line that is malformed is an error.
+    pub fn parse(s: &str) -> Result<Scenario, ParseError> {
+        let mut reader = Reader::new();
+        // The line each entity is added on, to check its attributes against
+        // the classes and shells read before or after it.
+        let mut added = Vec::new();
+        let mut tle_line = 0; // Where the element set being read began
+        for (i, line) in s.lines().enumerate() {
+            let counts = reader.scenario.counts();
+            if reader.tle.is_none() {
+                tle_line = i + 1;
+            }
+            reader.add_line(line).map_err(|message| ParseError {
+                line: i + 1,
+                message,
+            })?;
+            for (kind, id) in reader.scenario.added_since(counts) {
+                // An element set's entity is added on its last line.
+                let line = match reader.scenario.tle(kind, id) {
+                    Some(_) => tle_line,
+                    None => i + 1,
+                };
+                added.push((line, (kind, id)));
+            }
+        }
+        let scenario = reader.finish();
+        for (line, (kind, id)) in added {
+            scenario
+                .check_attributes(kind, id)
+                .map_err(|message| ParseError { line, message })?;
+        }
+        Ok(scenario)
+    }
+
+    // For tests, whose scenarios are known to be well formed.
+    #[cfg(test)]
+    #[allow(clippy::should_implement_trait)]
+    pub(crate) fn from_str(s: &str) -> Scenario {
+        Scenario::parse(s).unwrap()
     }
 
-    pub fn satellites_mut(&mut self) -> &mut Vec<Satellite> {
-        &mut self.satellites
+    pub fn users(&self) -> &Vec<Entity> {
+        &self.users
     }
 
     pub fn satellites(&self) -> &Vec<Satellite> {
@@ -61,129 +134,435 @@ impl Scenario {
         &self.interferers
     }
 
-    fn add_line(&mut self, line: &str) {
-        let mut parts = line.split_whitespace();
-        match parts.next() {
-            Some("user") => self.users.push(parts.collect()),
-            Some("sat") => self.satellites.push(parts.collect()),
-            Some("interferer") => self.interferers.push(parts.collect()),
-            Some(_) => (),
-            None => (),
+    pub fn users_by_id(&self) -> HashMap<i32, &Entity> {
+        self.users.iter().map(|u| (u.id, u)).collect()
+    }
+
+    pub fn satellites_by_id(&self) -> HashMap<i32, &Satellite> {
+        self.satellites.iter().map(|s| (s.entity.id, s)).collect()
+    }
+
+    pub fn attributes(&self, kind: EntityKind, id: i32) -> &[(String, String)] {
+        self.attributes
+            .get(&(kind, id))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    pub fn attribute(&self, kind: EntityKind, id: i32, key: &str) -> Option<&str> {
+        self.attributes(kind, id)
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub(crate) fn set_attributes(&mut self, kind: EntityKind, id: i32, attributes: Attributes) {
+        if !attributes.is_empty() {
+            self.attributes.insert((kind, id), attributes);
         }
-        ()
     }
-}
 
-impl Entity {
-    pub fn id(&self) -> i32 {
-        self.id
+    pub fn datum(&self) -> Datum {
+        self.datum
     }
 
-    pub fn position(&self) -> &Position {
-        &self.position
+    // The satellite's orbit, if it was given as one.
+    pub fn orbit(&self, satellite: i32) -> Option<&Orbit> {
+        self.orbits.get(&(EntityKind::Satellite, satellite))
     }
-}
 
-impl Satellite {
-    pub fn entity(&self) -> &Entity {
-        &self.entity
+    pub fn interferer_orbit(&self, interferer: i32) -> Option<&Orbit> {
+        self.orbits.get(&(EntityKind::Interferer, interferer))
     }
 
-    pub fn beams(&self) -> &Vec<Beam> {
-        &self.beams
+    // A satellite or interferer placed on its orbit at the epoch.
+    pub(crate) fn add_orbit(&mut self, kind: EntityKind, id: i32, orbit: Orbit) {
+        self.push(kind, Entity::new(id, orbit.eci()));
+        self.orbits.insert((kind, id), orbit);
     }
 
-    pub fn beams_mut(&mut self) -> &mut Vec<Beam> {
-        &mut self.beams
+    pub(crate) fn entity_mut(&mut self, kind: EntityKind, id: i32) -> Option<&mut Entity> {
+        match kind {
+            EntityKind::User => self.users.iter_mut().find(|u| u.id == id),
+            EntityKind::Satellite => self
+                .satellites
+                .iter_mut()
+                .map(|s| &mut s.entity)
+                .find(|s| s.id == id),
+            EntityKind::Interferer => self.interferers.iter_mut().find(|i| i.id == id),
+        }
     }
-}
 
-impl Beam {
-    pub fn new(user: Entity, band: Band) -> Beam {
-        Beam { user, band }
+    pub(crate) fn remove_entity(&mut self, kind: EntityKind, id: i32) {
+        match kind {
+            EntityKind::User => self.users.retain(|u| u.id != id),
+            EntityKind::Satellite => self.satellites.retain(|s| s.entity.id != id),
+            EntityKind::Interferer => self.interferers.retain(|i| i.id != id),
+        }
+        self.attributes.remove(&(kind, id));
+        if kind == EntityKind::User {
+            self.horizons.remove(&id);
+        }
     }
 
-    pub fn user(&self) -> &Entity {
-        &self.user
+    // Every entity with its kind, in output order: users, satellites, then
+    // interferers.
+    pub fn entities(&self) -> impl Iterator<Item = (EntityKind, &Entity)> {
+        self.users
+            .iter()
+            .map(|u| (EntityKind::User, u))
+            .chain(
+                self.satellites
+                    .iter()
+                    .map(|s| (EntityKind::Satellite, &s.entity)),
+            )
+            .chain(self.interferers.iter().map(|i| (EntityKind::Interferer, i)))
     }
 
-    pub fn band(&self) -> Band {
-        self.band
+    // Write the scenario back out in the line-based input format.
+    pub fn to_input(&self) -> String {
+        // Positions are written as x/y/z, so the datum is only kept for
+        // reporting lat/lon.
+        let datum = (self.datum != Datum::default()).then(|| format!("datum {}", self.datum));
+        let epoch = self.epoch().map(|epoch| format!("epoch {}", epoch));
+        let classes = self.classes.iter().map(TerminalClass::to_string);
+        let shells = self.shells.iter().map(ShellParams::to_string);
+        let entities = self.entities().map(|(kind, entity)| {
+            let id = entity.id;
+            let mut line = match self.orbits.get(&(kind, id)) {
+                Some(orbit) => format!("{}_orbit {} {}", kind, id, orbit),
+                _ if self.tle(kind, id).is_some() => format!("{}_tle {}", kind, id),
+                _ => match self.norad.get(&(kind, id)) {
+                    Some(norad) => format!("{}_norad {} {}", kind, id, norad),
+                    None => format!("{} {} {}", kind, id, entity.position),
+                },
+            };
+            for (key, value) in self.attributes(kind, id) {
+                line.push_str(&format!(" {}={}", key, value));
+            }
+            if let Some(tle) = self.tle(kind, id) {
+                line.push_str(&format!("\n{}", tle));
+            }
+            if let Some(horizon) = self.horizon_line(kind, id) {
+                line.push_str(&format!("\n{}", horizon));
+            }
+            line
+        });
+        datum
+            .into_iter()
+            .chain(epoch)
+            .chain(classes)
+            .chain(shells)
+            .chain(entities)
+            .collect::<Vec<String>>()
+            .join("\n")
     }
-}
 
-impl FromIterator<String> for Scenario {
-    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
-        let mut scenario = Scenario::new();
-        for line in iter {
-            scenario.add_line(line.as_str())
+    // Besides `<kind> <id> <x> <y> <z>`, users and satellites can be given as
+    // `user_geo <id> <lat> <lon> [alt_m]` and `sat_geo <id> <lat> <lon>
+    // <alt_km>`, converted with the datum set by the last `datum` line.
+    // Satellites and interferers can also be given by a circular orbit,
+    // `sat_orbit <id> <inc> <raan> <arg_lat> <alt_km>` (or `interferer_orbit`),
+    // and are then moved along it by `propagate`.
+    // Element sets (`_tle` and `_norad` lines) are described in tle.rs,
+    // `horizon` lines in horizon.rs, `class` lines in terminal.rs and `shell`
+    // lines in payload.rs.
+    fn add_line(&mut self, line: &str) -> Result<(), String> {
+        let mut parts = line.split_whitespace();
+        let (kind, geo) = match parts.next() {
+            Some("user") => (EntityKind::User, false),
+            Some("sat") => (EntityKind::Satellite, false),
+            Some("interferer") => (EntityKind::Interferer, false),
+            Some("user_geo") => (EntityKind::User, true),
+            Some("sat_geo") => (EntityKind::Satellite, true),
+            Some("sat_orbit") | Some("interferer_orbit") => {
+                let kind = match line.starts_with("sat") {
+                    true => EntityKind::Satellite,
+                    false => EntityKind::Interferer,
+                };
+                let parts: Vec<&str> = parts.collect();
+                let id = field(&parts, 0, "id")?;
+                let orbit = Orbit::new(
+                    field(&parts, 1, "inclination")?,
+                    field(&parts, 2, "RAAN")?,
+                    field(&parts, 3, "argument of latitude")?,
+                    field(&parts, 4, "altitude")?,
+                );
+                let attributes = parse_attributes(parts.get(5..).unwrap_or(&[]))?;
+                self.add_orbit(kind, id, orbit);
+                self.set_attributes(kind, id, attributes);
+                return Ok(());
+            }
+            Some("sat_norad") | Some("interferer_norad") => {
+                let kind = match line.starts_with("sat") {
+                    true => EntityKind::Satellite,
+                    false => EntityKind::Interferer,
+                };
+                let parts: Vec<&str> = parts.collect();
+                let id = field(&parts, 0, "id")?;
+                let norad = field(&parts, 1, "NORAD number")?;
+                let attributes = parse_attributes(parts.get(2..).unwrap_or(&[]))?;
+                let entity = Entity::new(id, Position::new(f64::NAN, f64::NAN, f64::NAN));
+                self.norad.insert((kind, id), norad);
+                self.set_attributes(kind, id, attributes);
+                self.push(kind, entity);
+                return Ok(());
+            }
+            Some("epoch") => {
+                let epoch = parts.collect::<Vec<&str>>().join(" ");
+                self.epoch = Some(epoch.parse()?);
+                return Ok(());
+            }
+            Some("horizon") => return self.add_horizon_line(line),
+            Some("class") => {
+                let parts: Vec<&str> = parts.collect();
+                let name: String = field(&parts, 0, "class name")?;
+                let attributes = parse_attributes(&parts[1..])?;
+                self.add_class(TerminalClass::new(&name, attributes)?);
+                return Ok(());
+            }
+            Some("shell") => {
+                let parts: Vec<&str> = parts.collect();
+                let name: String = field(&parts, 0, "shell name")?;
+                let attributes = parse_attributes(&parts[1..])?;
+                self.add_shell(ShellParams::new(&name, attributes)?);
+                return Ok(());
+            }
+            Some("datum") => {
+                let parts: Vec<&str> = parts.collect();
+                self.datum = field(&parts, 0, "datum")?;
+                return Ok(());
+            }
+            Some(_) => return Ok(()),
+            None => return Ok(()),
+        };
+        let parts: Vec<&str> = parts.collect();
+        let (entity, rest) = if geo {
+            let id = field(&parts, 0, "id")?;
+            let latitude = field(&parts, 1, "latitude")?;
+            let longitude = field(&parts, 2, "longitude")?;
+            // A user's altitude, in meters, is optional.
+            let altitude = match parts.get(3) {
+                Some(part) if kind == EntityKind::User && !part.contains('=') => {
+                    Some(field::<f64>(&parts, 3, "altitude")? / 1000.0)
+                }
+                _ if kind == EntityKind::User => None,
+                _ => Some(field(&parts, 3, "altitude")?),
+            };
+            let geodetic = Geodetic::new(latitude, longitude, altitude.unwrap_or(0.0));
+            let rest = if altitude.is_some() { 4 } else { 3 };
+            (Entity::new(id, geodetic.to_position(self.datum)), rest)
+        } else {
+            (Entity::parse(&parts)?, 4)
+        };
+        let attributes = parse_attributes(parts.get(rest..).unwrap_or(&[]))?;
+        self.set_attributes(kind, entity.id, attributes);
+        self.push(kind, entity);
+        Ok(())
+    }
+
+    // Attributes that name a class or shell the scenario doesn't define, or
+    // otherwise can't be used, as an error naming the entity and key.
+    pub(crate) fn check_attributes(&self, kind: EntityKind, id: i32) -> Result<(), String> {
+        match kind {
+            EntityKind::User => self.check_class(id).and(self.check_priority(id)),
+            EntityKind::Satellite => self.check_payload(id),
+            EntityKind::Interferer => self.check_protection(id),
+        }
+    }
+
+    fn counts(&self) -> [usize; 3] {
+        [
+            self.users.len(),
+            self.satellites.len(),
+            self.interferers.len(),
+        ]
+    }
+
+    // The entities added since `counts` was taken.
+    fn added_since(&self, counts: [usize; 3]) -> impl Iterator<Item = (EntityKind, i32)> + '_ {
+        let users = self.users[counts[0]..]
+            .iter()
+            .map(|u| (EntityKind::User, u.id));
+        let satellites = self.satellites[counts[1]..]
+            .iter()
+            .map(|s| (EntityKind::Satellite, s.entity.id));
+        let interferers = self.interferers[counts[2]..]
+            .iter()
+            .map(|i| (EntityKind::Interferer, i.id));
+        users.chain(satellites).chain(interferers)
+    }
+
+    pub(crate) fn push(&mut self, kind: EntityKind, entity: Entity) {
+        match kind {
+            EntityKind::User => self.users.push(entity),
+            EntityKind::Satellite => self.satellites.push(Satellite::new(entity)),
+            EntityKind::Interferer => self.interferers.push(entity),
         }
-        scenario
+    }
+
+    // An entity placed from an element set; `place_tles` finds its position.
+    pub(crate) fn add_tle(&mut self, kind: EntityKind, id: i32, tle: Tle) {
+        self.push(
+            kind,
+            Entity::new(id, Position::new(f64::NAN, f64::NAN, f64::NAN)),
+        );
+        self.tles.insert((kind, id), tle);
     }
 }
 
-impl<'a> FromIterator<&'a str> for Scenario {
-    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
-        let mut scenario = Scenario::new();
-        for line in iter {
-            scenario.add_line(line)
+// Reads the line format. Most entries take one line, but `sat_tle` and
+// `interferer_tle` take the two element lines after them too.
+struct Reader {
+    scenario: Scenario,
+    tle: Option<(EntityKind, i32, Attributes, Vec<String>)>,
+}
+
+impl Reader {
+    fn new() -> Reader {
+        Reader {
+            scenario: Scenario::new(),
+            tle: None,
         }
-        scenario
+    }
+
+    fn add_line(&mut self, line: &str) -> Result<(), String> {
+        if let Some((kind, id, attributes, mut lines)) = self.tle.take() {
+            let expected = lines.len() + 1;
+            if line.starts_with(&format!("{} ", expected)) {
+                lines.push(line.to_string());
+                match lines.as_slice() {
+                    [line1, line2] => self.add_tle(kind, id, attributes, line1, line2),
+                    _ => self.tle = Some((kind, id, attributes, lines)),
+                }
+                return Ok(());
+            }
+            let line = expected as u8;
+            self.scenario
+                .tle_errors
+                .push((kind, id, TleError::MissingLine { line }));
+        }
+        let mut parts = line.split_whitespace();
+        let kind = match parts.next() {
+            Some("sat_tle") => EntityKind::Satellite,
+            Some("interferer_tle") => EntityKind::Interferer,
+            _ => return self.scenario.add_line(line),
+        };
+        let parts: Vec<&str> = parts.collect();
+        let id = field(&parts, 0, "id")?;
+        let attributes = parse_attributes(parts.get(1..).unwrap_or(&[]))?;
+        self.tle = Some((kind, id, attributes, Vec::new()));
+        Ok(())
+    }
+
+    fn add_tle(&mut self, kind: EntityKind, id: i32, attributes: Attributes, l1: &str, l2: &str) {
+        match Tle::parse(l1, l2) {
+            Ok(tle) => {
+                self.scenario.set_attributes(kind, id, attributes);
+                self.scenario.add_tle(kind, id, tle);
+            }
+            Err(error) => self.scenario.tle_errors.push((kind, id, error)),
+        }
+    }
+
+    fn finish(mut self) -> Scenario {
+        if let Some((kind, id, _, lines)) = self.tle.take() {
+            let line = lines.len() as u8 + 1;
+            self.scenario
+                .tle_errors
+                .push((kind, id, TleError::MissingLine { line }));
+        }
+        self.scenario.place_tles();
+        self.scenario
     }
 }
 
-impl<'a> FromIterator<&'a str> for Entity {
-    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
-        let mut iter = iter.into_iter();
-        let id = iter.next().unwrap().parse::<i32>().unwrap();
-        let position: Position = iter.collect();
+// The `key=value` tokens at the end of a line; anything else there is an
+// error.
+fn parse_attributes(parts: &[&str]) -> Result<Attributes, String> {
+    parts
+        .iter()
+        .map(|part| match part.split_once('=') {
+            Some((key, value)) => Ok((key.to_string(), value.to_string())),
+            None => Err(format!("unexpected '{}'; expected key=value", part)),
+        })
+        .collect()
+}
+
+// The `i`th field of a line, named in the error if it is missing or doesn't
+// parse.
+fn field<T: FromStr>(parts: &[&str], i: usize, name: &str) -> Result<T, String> {
+    match parts.get(i) {
+        Some(part) if !part.is_empty() => part
+            .parse()
+            .map_err(|_| format!("invalid {} '{}'", name, part)),
+        _ => Err(format!("missing {}", name)),
+    }
+}
+
+impl Entity {
+    pub fn new(id: i32, position: Position) -> Entity {
         Entity { id, position }
     }
+
+    // `<id> <x> <y> <z>`, ignoring anything after.
+    fn parse(parts: &[&str]) -> Result<Entity, String> {
+        let id = field(parts, 0, "id")?;
+        let position = Position::new(
+            field(parts, 1, "x")?,
+            field(parts, 2, "y")?,
+            field(parts, 3, "z")?,
+        );
+        Ok(Entity::new(id, position))
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub(crate) fn set_position(&mut self, position: Position) {
+        self.position = position;
+    }
 }
 
-impl<'a> FromIterator<&'a str> for Position {
-    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
-        let mut iter = iter.into_iter();
-        let x = iter.next().unwrap().parse::<f32>().unwrap();
-        let y = iter.next().unwrap().parse::<f32>().unwrap();
-        let z = iter.next().unwrap().parse::<f32>().unwrap();
-        Position { x, y, z }
+impl Satellite {
+    pub fn new(entity: Entity) -> Satellite {
+        Satellite { entity }
+    }
+
+    pub fn entity(&self) -> &Entity {
+        &self.entity
     }
 }
 
-impl<'a> FromIterator<&'a str> for Satellite {
-    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
-        let iter = iter.into_iter();
-        let entity: Entity = iter.collect();
-        Satellite {
-            entity,
-            beams: Vec::with_capacity(32),
+impl FromStr for Entity {
+    type Err = String;
+
+    // `<id> <x> <y> <z>`, as on a user, sat or interferer line.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        if parts.len() > 4 {
+            return Err(format!("unexpected '{}'", parts[4]));
         }
+        Entity::parse(&parts)
     }
 }
 
-impl fmt::Display for Scenario {
+impl fmt::Display for EntityKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut lines: Vec<String> = self
-            .satellites
-            .iter()
-            .flat_map(|satellite| {
-                let mut id = 1;
-                satellite.beams().iter().map(move |beam| {
-                    let f = format!(
-                        "sat {} beam {} user {} color {}",
-                        satellite.entity().id(),
-                        id,
-                        beam.user(),
-                        beam.band(),
-                    );
-                    id += 1;
-                    f
-                })
-            })
-            .collect();
-        lines.sort_unstable();
-        write!(f, "{}", lines.join("\n"))
+        write!(
+            f,
+            "{}",
+            match self {
+                EntityKind::User => "user",
+                EntityKind::Satellite => "sat",
+                EntityKind::Interferer => "interferer",
+            }
+        )
     }
 }
 
@@ -214,26 +593,41 @@ impl fmt::Display for Band {
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+impl FromStr for Band {
+    type Err = String;
 
-    impl Scenario {
-        pub fn from_str(s: &str) -> Scenario {
-            s.lines().collect()
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "A" => Ok(Band::A),
+            "B" => Ok(Band::B),
+            "C" => Ok(Band::C),
+            "D" => Ok(Band::D),
+            _ => Err(format!("unknown color '{}'", s)),
         }
     }
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
+mod test {
+    use super::*;
 
     #[test]
     fn test_build_entity() {
-        let entity = String::from("1 6371.1897 22.65 -123");
-        let parts = entity.split_whitespace();
-        let entity: Entity = parts.collect();
+        let entity: Entity = "1 6371.1897 22.65 -123".parse().unwrap();
         let position = entity.position;
         assert_eq!(entity.id, 1);
         assert_eq!(position.x, 6371.1897);
         assert_eq!(position.y, 22.65);
         assert_eq!(position.z, -123.0);
+        assert_eq!(
+            "1 6371 0".parse::<Entity>().unwrap_err(),
+            "missing z".to_string()
+        );
+        assert_eq!(
+            "x 6371 0 0".parse::<Entity>().unwrap_err(),
+            "invalid id 'x'".to_string()
+        );
     }
 
     const EXAMPLE_SCENARIO: &str =
@@ -257,4 +651,108 @@ interferer 1 -42164 0 0
         assert_eq!(scenario.satellites.len(), 2);
         assert_eq!(scenario.interferers.len(), 1);
     }
+
+    #[test]
+    fn test_to_input() {
+        let scenario = Scenario::from_str(EXAMPLE_SCENARIO);
+        let input = scenario.to_input();
+        assert_eq!(
+            input,
+            "user 1 6371 0 0
+user 2 0 0 6371
+user 3 111.189281412 0 6370.02966584
+sat 1 6921 0 0
+sat 2 0 0 6921
+interferer 1 -42164 0 0"
+        );
+        let round_trip = Scenario::from_str(&input);
+        assert_eq!(round_trip.users[2].position, scenario.users[2].position);
+        assert_eq!(round_trip.to_input(), input);
+    }
+
+    #[test]
+    fn test_attributes() {
+        let scenario = Scenario::from_str(
+            "class maritime
+user 1 6371 0 0 class=maritime weight=3
+sat 1 6921 0 0 beams=16",
+        );
+        assert_eq!(
+            scenario.attribute(EntityKind::User, 1, "class"),
+            Some("maritime")
+        );
+        assert_eq!(scenario.attribute(EntityKind::User, 1, "weight"), Some("3"));
+        assert_eq!(scenario.attribute(EntityKind::Satellite, 1, "class"), None);
+        assert_eq!(scenario.attributes(EntityKind::Satellite, 1).len(), 1);
+        assert_eq!(
+            scenario.to_input(),
+            "class maritime
+user 1 6371 0 0 class=maritime weight=3
+sat 1 6921 0 0 beams=16"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| Scenario::parse(input).err().map(|e| e.to_string());
+        assert_eq!(
+            error("user 1 6371 0 0\nuser 2 abc 0 0"),
+            Some(String::from("line 2: invalid x 'abc'"))
+        );
+        assert_eq!(
+            error("user 1 6371 0"),
+            Some(String::from("line 1: missing z"))
+        );
+        assert_eq!(
+            error("user x"),
+            Some(String::from("line 1: invalid id 'x'"))
+        );
+        assert_eq!(
+            error("user 1 6371 0 0 maritime"),
+            Some(String::from(
+                "line 1: unexpected 'maritime'; expected key=value"
+            ))
+        );
+        assert_eq!(
+            error("user_geo 1 north 0"),
+            Some(String::from("line 1: invalid latitude 'north'"))
+        );
+        assert_eq!(
+            error("sat_geo 1 0 0"),
+            Some(String::from("line 1: missing altitude"))
+        );
+        assert_eq!(
+            error("sat_orbit 1 53 0 0"),
+            Some(String::from("line 1: missing altitude"))
+        );
+        assert_eq!(
+            error("interferer_orbit 1 0 0 zero 35786"),
+            Some(String::from("line 1: invalid argument of latitude 'zero'"))
+        );
+        assert_eq!(
+            error("class foo min_elev=abc"),
+            Some(String::from("line 1: class foo: invalid min_elev 'abc'"))
+        );
+        assert_eq!(
+            error("class foo min_elev=NaN"),
+            Some(String::from("line 1: class foo: invalid min_elev 'NaN'"))
+        );
+        // A user's class may be defined after it, but must be defined.
+        assert_eq!(
+            error("user 1 6371 0 0\nuser 2 6371 0 0 class=nope\nclass maritime"),
+            Some(String::from("line 2: user 2: undefined class 'nope'"))
+        );
+        assert!(Scenario::parse("user 1 6371 0 0 class=maritime\nclass maritime").is_ok());
+        assert!(error("datum flat").is_some());
+        assert!(error("epoch yesterday").is_some());
+        assert!(error("horizon 1 0-50").is_some());
+
+        // Comments, blank and unknown lines are still skipped.
+        let scenario = Scenario::parse("# users\n\nfoo 1 2 3\nuser_geo 1 0 0 site=roof").unwrap();
+        assert_eq!(scenario.users().len(), 1);
+        assert_eq!(
+            scenario.attribute(EntityKind::User, 1, "site"),
+            Some("roof")
+        );
+    }
 }
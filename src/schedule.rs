@@ -0,0 +1,441 @@
+// Solutions for a scenario at a series of epochs, in seconds from the
+// scenario's epoch. As text, each solution follows an `epoch <seconds>` line,
+// so the block after it can be checked with evaluate.py against the scenario
+// propagated to that epoch.
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::{
+    constraints::Constraints,
+    orbit::OrbitModel,
+    scenario::Scenario,
+    solution::{Assignment, ParseError, Solution},
+    solver::Strategy,
+    validate::Violation,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schedule {
+    epochs: Vec<(f64, Solution)>,
+}
+
+// A user served at two consecutive epochs by a different satellite or band.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Handover {
+    pub epoch: f64, // The later of the two epochs
+    pub from: Assignment,
+    pub to: Assignment,
+}
+
+impl Schedule {
+    pub fn new(epochs: Vec<(f64, Solution)>) -> Schedule {
+        Schedule { epochs }
+    }
+
+    pub fn epochs(&self) -> &[(f64, Solution)] {
+        &self.epochs
+    }
+
+    pub fn len(&self) -> usize {
+        self.epochs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.epochs.is_empty()
+    }
+
+    // Every handover, in epoch then user order. Users who lose or gain
+    // service between epochs aren't handed over.
+    pub fn handovers(&self) -> Vec<Handover> {
+        let mut handovers = Vec::new();
+        for pair in self.epochs.windows(2) {
+            let ((_, before), (epoch, after)) = (&pair[0], &pair[1]);
+            let mut changed: Vec<Handover> = after
+                .iter()
+                .filter_map(|to| {
+                    let from = before.by_user(to.user)?;
+                    if after.by_user(to.user) != Some(to)
+                        || (from.satellite, from.band) == (to.satellite, to.band)
+                    {
+                        return None;
+                    }
+                    Some(Handover {
+                        epoch: *epoch,
+                        from: *from,
+                        to: *to,
+                    })
+                })
+                .collect();
+            changed.sort_by_key(|h| h.to.user);
+            handovers.append(&mut changed);
+        }
+        handovers
+    }
+
+    // Handovers at each epoch after the first.
+    pub fn handovers_per_epoch(&self) -> Vec<(f64, usize)> {
+        let handovers = self.handovers();
+        self.epochs
+            .iter()
+            .skip(1)
+            .map(|(epoch, _)| {
+                (
+                    *epoch,
+                    handovers.iter().filter(|h| h.epoch == *epoch).count(),
+                )
+            })
+            .collect()
+    }
+
+    // Handovers of each user handed over at least once.
+    pub fn handovers_per_user(&self) -> BTreeMap<i32, usize> {
+        let mut counts = BTreeMap::new();
+        for handover in self.handovers() {
+            *counts.entry(handover.to.user).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+impl Scenario {
+    // Propagate to each epoch and plan it independently.
+    pub fn optimize_over(
+        &self,
+        epochs: &[f64],
+        model: OrbitModel,
+        strategy: Strategy,
+        constraints: &Constraints,
+    ) -> Schedule {
+        Schedule::new(
+            epochs
+                .iter()
+                .map(|&seconds| {
+                    let scenario = self.propagate(seconds, model);
+                    (seconds, scenario.optimize_with(strategy, constraints))
+                })
+                .collect(),
+        )
+    }
+
+    // Plan each epoch starting from the previous epoch's plan, with
+    // optimize_from, so users stay on their satellite and band for as long
+    // as they can instead of being reshuffled at every epoch.
+    pub fn optimize_over_warm(
+        &self,
+        epochs: &[f64],
+        model: OrbitModel,
+        strategy: Strategy,
+        constraints: &Constraints,
+    ) -> Schedule {
+        let mut planned: Vec<(f64, Solution)> = Vec::with_capacity(epochs.len());
+        for &seconds in epochs {
+            let scenario = self.propagate(seconds, model);
+            let solution = match planned.last() {
+                Some((_, previous)) => scenario.optimize_from(previous, strategy, constraints),
+                None => scenario.optimize_with(strategy, constraints),
+            };
+            planned.push((seconds, solution));
+        }
+        Schedule::new(planned)
+    }
+
+    // Every violation, with the epoch it occurs at.
+    pub fn validate_schedule(
+        &self,
+        schedule: &Schedule,
+        model: OrbitModel,
+        constraints: &Constraints,
+    ) -> Vec<(f64, Violation)> {
+        schedule
+            .epochs()
+            .iter()
+            .flat_map(|(seconds, solution)| {
+                self.propagate(*seconds, model)
+                    .validate_with(solution, constraints)
+                    .into_iter()
+                    .map(move |violation| (*seconds, violation))
+            })
+            .collect()
+    }
+}
+
+// More epochs than anyone plans in one run; a day at one-second steps fits.
+pub const MAX_EPOCHS: usize = 100_000;
+
+// Epochs as `START:END:STEP` (END included when the steps land on it) or a
+// comma-separated list of seconds, each finite and at most MAX_EPOCHS of them.
+pub fn parse_epochs(s: &str) -> Result<Vec<f64>, String> {
+    let invalid = || format!("invalid epochs '{}'; expected START:END:STEP or T,T,...", s);
+    let number = |n: &str| match n.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => Err(invalid()),
+    };
+    let too_many = || format!("too many epochs in '{}' (at most {})", s, MAX_EPOCHS);
+    match s.split(':').collect::<Vec<&str>>()[..] {
+        [start, end, step] => {
+            let (start, end, step) = (number(start)?, number(end)?, number(step)?);
+            if step <= 0.0 || end < start {
+                return Err(invalid());
+            }
+            // Checked as a float, before the count can overflow or allocate.
+            let steps = ((end - start) / step + 1e-9).floor();
+            if steps >= MAX_EPOCHS as f64 {
+                return Err(too_many());
+            }
+            Ok((0..=steps as usize)
+                .map(|i| start + step * i as f64)
+                .collect())
+        }
+        [list] if list.split(',').count() > MAX_EPOCHS => Err(too_many()),
+        [list] => list.split(',').map(number).collect(),
+        _ => Err(invalid()),
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ParseError;
+
+    // Comments and blank lines are skipped as in a single solution.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut epochs: Vec<(f64, Vec<Assignment>)> = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            if line.contains('#') || line.trim().is_empty() {
+                continue;
+            }
+            let error = |message: String| ParseError {
+                line: i + 1,
+                message,
+            };
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                ["epoch", seconds] => {
+                    let seconds = seconds
+                        .parse()
+                        .map_err(|_| error(format!("invalid epoch '{}'", seconds)))?;
+                    epochs.push((seconds, Vec::new()));
+                }
+                _ => match epochs.last_mut() {
+                    Some((_, assignments)) => assignments.push(line.parse().map_err(error)?),
+                    None => return Err(error(String::from("expected an epoch line first"))),
+                },
+            }
+        }
+        Ok(Schedule::new(
+            epochs
+                .into_iter()
+                .map(|(seconds, assignments)| (seconds, Solution::new(assignments)))
+                .collect(),
+        ))
+    }
+}
+
+impl fmt::Display for Handover {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "epoch {}: user {} sat {} color {} -> sat {} color {}",
+            self.epoch,
+            self.to.user,
+            self.from.satellite,
+            self.from.band,
+            self.to.satellite,
+            self.to.band
+        )
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (seconds, solution)) in self.epochs.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "epoch {}", seconds)?;
+            if !solution.is_empty() {
+                write!(f, "\n{}", solution)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{builder::ScenarioBuilder, constellation::Shell, position::Position};
+
+    #[test]
+    fn test_parse_epochs() {
+        assert_eq!(parse_epochs("0:120:60"), Ok(vec![0.0, 60.0, 120.0]));
+        assert_eq!(parse_epochs("0:100:60"), Ok(vec![0.0, 60.0]));
+        assert_eq!(parse_epochs("30,90.5"), Ok(vec![30.0, 90.5]));
+        assert!(parse_epochs("0:60:0").is_err());
+        assert!(parse_epochs("60:0:10").is_err());
+        assert!(parse_epochs("0:60").is_err());
+        assert!(parse_epochs("NaN:10:5").is_err());
+        assert!(parse_epochs("0:inf:5").is_err());
+        assert!(parse_epochs("inf").is_err());
+        assert!(parse_epochs("0,NaN").is_err());
+        assert_eq!(
+            parse_epochs("0:1e12:1e-3"),
+            Err(String::from(
+                "too many epochs in '0:1e12:1e-3' (at most 100000)"
+            ))
+        );
+        assert_eq!(parse_epochs("0:99999:1").map(|e| e.len()), Ok(100_000));
+        assert!(parse_epochs("0:100000:1").is_err());
+    }
+
+    #[test]
+    fn test_optimize_over() {
+        // Users along a line of latitude under a Walker shell, planned every
+        // two minutes for ten minutes. As the shell moves past, users are
+        // handed to other satellites, and each epoch's plan stays valid.
+        let mut builder = ScenarioBuilder::new();
+        let shell: Shell = "53:360/18/0:550".parse().unwrap();
+        builder.add_shell(&shell, 1).unwrap();
+        for i in 0..12 {
+            let longitude = (i as f64 * 30.0).to_radians();
+            let (x, y) = (
+                6371.0 * 0.8 * longitude.cos(),
+                6371.0 * 0.8 * longitude.sin(),
+            );
+            builder
+                .add_user(i + 1, Position::new(x, y, 6371.0 * 0.6))
+                .unwrap();
+        }
+        let scenario = builder.build();
+
+        let epochs = parse_epochs("0:600:120").unwrap();
+        let constraints = Constraints::default();
+        for model in [OrbitModel::Keplerian, OrbitModel::J2].iter() {
+            let schedule =
+                scenario.optimize_over(&epochs, *model, Strategy::default(), &constraints);
+            assert_eq!(schedule.len(), 6);
+            assert!(schedule.epochs().iter().all(|(_, s)| s.covered() > 0));
+            let pairs = |solution: &Solution| -> Vec<(i32, i32)> {
+                solution.iter().map(|a| (a.user, a.satellite)).collect()
+            };
+            assert!(schedule
+                .epochs()
+                .windows(2)
+                .all(|w| pairs(&w[0].1) != pairs(&w[1].1)));
+            assert_eq!(
+                scenario.validate_schedule(&schedule, *model, &constraints),
+                vec![]
+            );
+
+            // Round trip through text.
+            let text = schedule.to_string();
+            assert!(text.starts_with("epoch 0\nsat "));
+            assert_eq!(text.parse::<Schedule>(), Ok(schedule));
+        }
+    }
+
+    #[test]
+    fn test_optimize_over_warm() {
+        let mut builder = ScenarioBuilder::new();
+        let shell: Shell = "53:360/18/0:550".parse().unwrap();
+        builder.add_shell(&shell, 1).unwrap();
+        for i in 0..60 {
+            let longitude = (i as f64 * 6.0).to_radians();
+            let (x, y) = (
+                6371.0 * 0.8 * longitude.cos(),
+                6371.0 * 0.8 * longitude.sin(),
+            );
+            builder
+                .add_user(i + 1, Position::new(x, y, 6371.0 * 0.6))
+                .unwrap();
+        }
+        let scenario = builder.build();
+
+        // Starting each epoch from the last plan hands over fewer users than
+        // planning from scratch, without serving fewer of them.
+        let epochs = parse_epochs("0:300:30").unwrap();
+        let (model, constraints) = (OrbitModel::J2, Constraints::default());
+        let cold = scenario.optimize_over(&epochs, model, Strategy::default(), &constraints);
+        let warm = scenario.optimize_over_warm(&epochs, model, Strategy::default(), &constraints);
+        assert_eq!(
+            scenario.validate_schedule(&warm, model, &constraints),
+            vec![]
+        );
+        assert_eq!(warm.epochs()[0], cold.epochs()[0]);
+        for (w, c) in warm.epochs().iter().zip(cold.epochs()) {
+            assert!(w.1.covered() >= c.1.covered());
+        }
+        let (warm_handovers, cold_handovers) = (warm.handovers().len(), cold.handovers().len());
+        assert!(
+            warm_handovers < cold_handovers,
+            "{} {}",
+            warm_handovers,
+            cold_handovers
+        );
+
+        let per_epoch = warm.handovers_per_epoch();
+        assert_eq!(per_epoch.len(), 10);
+        assert_eq!(
+            per_epoch.iter().map(|(_, n)| n).sum::<usize>(),
+            warm_handovers
+        );
+        assert_eq!(
+            warm.handovers_per_user().values().sum::<usize>(),
+            warm_handovers
+        );
+    }
+
+    #[test]
+    fn test_handovers() {
+        let schedule: Schedule = "epoch 0
+sat 1 beam 1 user 1 color A
+sat 1 beam 2 user 2 color A
+sat 1 beam 3 user 3 color A
+epoch 60
+sat 1 beam 1 user 1 color A
+sat 1 beam 2 user 2 color B
+sat 2 beam 1 user 4 color A
+epoch 120
+sat 2 beam 1 user 1 color A
+sat 2 beam 2 user 2 color B"
+            .parse()
+            .unwrap();
+        let handovers: Vec<String> = schedule.handovers().iter().map(|h| h.to_string()).collect();
+        assert_eq!(
+            handovers,
+            vec![
+                "epoch 60: user 2 sat 1 color A -> sat 1 color B",
+                "epoch 120: user 1 sat 1 color A -> sat 2 color A",
+                "epoch 120: user 2 sat 1 color B -> sat 2 color B",
+            ]
+        );
+        assert_eq!(schedule.handovers_per_epoch(), vec![(60.0, 1), (120.0, 2)]);
+        assert_eq!(
+            schedule
+                .handovers_per_user()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![(1, 1), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let schedule: Schedule = "# comment
+epoch 0
+sat 1 beam 1 user 1 color A
+epoch 60
+
+epoch 120
+sat 2 beam 1 user 1 color B"
+            .parse()
+            .unwrap();
+        assert_eq!(schedule.len(), 3);
+        assert!(schedule.epochs()[1].1.is_empty());
+        assert_eq!(schedule.epochs()[2].0, 120.0);
+        assert_eq!(
+            "sat 1 beam 1 user 1 color A".parse::<Schedule>(),
+            Err(ParseError {
+                line: 1,
+                message: String::from("expected an epoch line first")
+            })
+        );
+        assert_eq!("epoch soon".parse::<Schedule>().unwrap_err().line, 1);
+    }
+}
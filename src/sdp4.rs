@@ -0,0 +1,663 @@
+// SDP4's deep-space terms, which SGP4 adds for element sets with periods of
+// 225 minutes or more: lunar and solar gravity, both secular and long-period,
+// and the Earth's tesseral harmonics for orbits in resonance with its
+// rotation, geosynchronous (one revolution a day) and Molniya-like (two, with
+// a high eccentricity). Like sgp4.rs this follows Vallado et al. (AIAA
+// 2006-6753); the routines keep the report's names, dscom, dsinit, dspace and
+// dpper, and the resonance integrator restarts from the epoch on every call,
+// which gives the same result as Vallado's cached one.
+use std::f64::consts::PI;
+
+use crate::sgp4::gmst;
+use crate::time::Utc;
+
+const TWO_PI: f64 = 2.0 * PI;
+const ZES: f64 = 0.01675; // Solar eccentricity
+const ZEL: f64 = 0.05490; // Lunar eccentricity
+const ZNS: f64 = 1.19459e-5; // Solar mean motion, radians per minute
+const ZNL: f64 = 1.5835218e-4; // Lunar mean motion
+const RPTIM: f64 = 4.375_269_088_011_3e-3; // Earth rotation, radians per minute
+const STEP: f64 = 720.0; // Resonance integrator step, minutes
+
+// The mean elements at the epoch, and their secular rates from SGP4's own
+// gravity terms.
+pub struct MeanElements {
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub raan: f64,
+    pub argument_of_perigee: f64,
+    pub mean_anomaly: f64,
+    pub mean_motion: f64, // Un-Kozai'd, radians per minute
+    pub mdot: f64,
+    pub argpdot: f64,
+    pub nodedot: f64,
+}
+
+// Mean elements being propagated, as SGP4 passes them through the deep-space
+// terms.
+#[derive(Debug, Clone, Copy)]
+pub struct Elements {
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub raan: f64,
+    pub argument_of_perigee: f64,
+    pub mean_anomaly: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeepSpace {
+    periodics: Periodics,
+    // Secular lunar-solar rates.
+    dedt: f64,
+    didt: f64,
+    dmdt: f64,
+    dnodt: f64,
+    domdt: f64,
+    resonance: Option<Resonance>,
+}
+
+// Coefficients of the long-period lunar-solar terms, solar then lunar.
+#[derive(Debug, Clone)]
+struct Periodics {
+    se2: f64,
+    se3: f64,
+    si2: f64,
+    si3: f64,
+    sl2: f64,
+    sl3: f64,
+    sl4: f64,
+    sgh2: f64,
+    sgh3: f64,
+    sgh4: f64,
+    sh2: f64,
+    sh3: f64,
+    ee2: f64,
+    e3: f64,
+    xi2: f64,
+    xi3: f64,
+    xl2: f64,
+    xl3: f64,
+    xl4: f64,
+    xgh2: f64,
+    xgh3: f64,
+    xgh4: f64,
+    xh2: f64,
+    xh3: f64,
+    zmos: f64, // Solar and lunar mean anomalies at the epoch
+    zmol: f64,
+}
+
+#[derive(Debug, Clone)]
+enum Harmonics {
+    // One revolution a day.
+    Synchronous { del1: f64, del2: f64, del3: f64 },
+    // Two a day, eccentricity 0.5 or more.
+    HalfDay { d: [f64; 10] }, // d2201 d2211 d3210 d3222 d4410 d4422 d5220 d5232 d5421 d5433
+}
+
+#[derive(Debug, Clone)]
+struct Resonance {
+    harmonics: Harmonics,
+    xfact: f64,
+    xlamo: f64,
+    gsto: f64,
+    mean_motion: f64,
+    argument_of_perigee: f64,
+    argpdot: f64,
+}
+
+// dscom's solar or lunar intermediate terms.
+#[derive(Debug, Clone, Copy)]
+struct Body {
+    s1: f64,
+    s2: f64,
+    s3: f64,
+    s4: f64,
+    s5: f64,
+    s6: f64,
+    s7: f64,
+    z1: f64,
+    z2: f64,
+    z3: f64,
+    z11: f64,
+    z12: f64,
+    z13: f64,
+    z21: f64,
+    z22: f64,
+    z23: f64,
+    z31: f64,
+    z32: f64,
+    z33: f64,
+}
+
+impl DeepSpace {
+    // dscom and dsinit, at the epoch.
+    pub fn new(epoch: &Utc, elements: &MeanElements, xke: f64) -> DeepSpace {
+        let em = elements.eccentricity;
+        let inclm = elements.inclination;
+        let (snodm, cnodm) = elements.raan.sin_cos();
+        let (sinomm, cosomm) = elements.argument_of_perigee.sin_cos();
+        let (sinim, cosim) = inclm.sin_cos();
+        let emsq = em * em;
+        let betasq = 1.0 - emsq;
+        let rtemsq = betasq.sqrt();
+
+        // Days from 1950 January 0.0, then the lunar node and perigee.
+        let day = epoch.julian_date() - 2_433_281.5 + 18261.5;
+        let xnodce = (4.523_602_0 - 9.242_202_9e-4 * day) % TWO_PI;
+        let (stem, ctem) = xnodce.sin_cos();
+        let zcosil = 0.913_751_64 - 0.035_680_96 * ctem;
+        let zsinil = (1.0 - zcosil * zcosil).sqrt();
+        let zsinhl = 0.089_683_511 * stem / zsinil;
+        let zcoshl = (1.0 - zsinhl * zsinhl).sqrt();
+        let gam = 5.835_151_4 + 0.001_944_368_0 * day;
+        let zx = 0.397_854_16 * stem / zsinil;
+        let zy = zcoshl * ctem + 0.917_448_67 * zsinhl * stem;
+        let zx = gam + zx.atan2(zy) - xnodce;
+        let (zsingl, zcosgl) = zx.sin_cos();
+
+        let xnoi = 1.0 / elements.mean_motion;
+        let body = |zcosg: f64, zsing: f64, zcosi: f64, zsini: f64, zcosh: f64, zsinh: f64, cc| {
+            let a1 = zcosg * zcosh + zsing * zcosi * zsinh;
+            let a3 = -zsing * zcosh + zcosg * zcosi * zsinh;
+            let a7 = -zcosg * zsinh + zsing * zcosi * zcosh;
+            let a8 = zsing * zsini;
+            let a9 = zsing * zsinh + zcosg * zcosi * zcosh;
+            let a10 = zcosg * zsini;
+            let a2 = cosim * a7 + sinim * a8;
+            let a4 = cosim * a9 + sinim * a10;
+            let a5 = -sinim * a7 + cosim * a8;
+            let a6 = -sinim * a9 + cosim * a10;
+
+            let x1 = a1 * cosomm + a2 * sinomm;
+            let x2 = a3 * cosomm + a4 * sinomm;
+            let x3 = -a1 * sinomm + a2 * cosomm;
+            let x4 = -a3 * sinomm + a4 * cosomm;
+            let x5 = a5 * sinomm;
+            let x6 = a6 * sinomm;
+            let x7 = a5 * cosomm;
+            let x8 = a6 * cosomm;
+
+            let z31 = 12.0 * x1 * x1 - 3.0 * x3 * x3;
+            let z32 = 24.0 * x1 * x2 - 6.0 * x3 * x4;
+            let z33 = 12.0 * x2 * x2 - 3.0 * x4 * x4;
+            let z1 = 3.0 * (a1 * a1 + a2 * a2) + z31 * emsq;
+            let z2 = 6.0 * (a1 * a3 + a2 * a4) + z32 * emsq;
+            let z3 = 3.0 * (a3 * a3 + a4 * a4) + z33 * emsq;
+            let s3 = cc * xnoi;
+            let s4 = s3 * rtemsq;
+            Body {
+                s1: -15.0 * em * s4,
+                s2: -0.5 * s3 / rtemsq,
+                s3,
+                s4,
+                s5: x1 * x3 + x2 * x4,
+                s6: x2 * x3 + x1 * x4,
+                s7: x2 * x4 - x1 * x3,
+                z1: z1 + z1 + betasq * z31,
+                z2: z2 + z2 + betasq * z32,
+                z3: z3 + z3 + betasq * z33,
+                z11: -6.0 * a1 * a5 + emsq * (-24.0 * x1 * x7 - 6.0 * x3 * x5),
+                z12: -6.0 * (a1 * a6 + a3 * a5)
+                    + emsq * (-24.0 * (x2 * x7 + x1 * x8) - 6.0 * (x3 * x6 + x4 * x5)),
+                z13: -6.0 * a3 * a6 + emsq * (-24.0 * x2 * x8 - 6.0 * x4 * x6),
+                z21: 6.0 * a2 * a5 + emsq * (24.0 * x1 * x5 - 6.0 * x3 * x7),
+                z22: 6.0 * (a4 * a5 + a2 * a6)
+                    + emsq * (24.0 * (x2 * x5 + x1 * x6) - 6.0 * (x4 * x7 + x3 * x8)),
+                z23: 6.0 * a4 * a6 + emsq * (24.0 * x2 * x6 - 6.0 * x4 * x8),
+                z31,
+                z32,
+                z33,
+            }
+        };
+        let sun = body(
+            0.194_590_5,
+            -0.980_884_58,
+            0.917_448_67,
+            0.397_854_16,
+            cnodm,
+            snodm,
+            2.986_479_7e-6,
+        );
+        let moon = body(
+            zcosgl,
+            zsingl,
+            zcosil,
+            zsinil,
+            zcoshl * cnodm + zsinhl * snodm,
+            snodm * zcoshl - cnodm * zsinhl,
+            4.796_806_5e-7,
+        );
+
+        let periodics = Periodics {
+            se2: 2.0 * sun.s1 * sun.s6,
+            se3: 2.0 * sun.s1 * sun.s7,
+            si2: 2.0 * sun.s2 * sun.z12,
+            si3: 2.0 * sun.s2 * (sun.z13 - sun.z11),
+            sl2: -2.0 * sun.s3 * sun.z2,
+            sl3: -2.0 * sun.s3 * (sun.z3 - sun.z1),
+            sl4: -2.0 * sun.s3 * (-21.0 - 9.0 * emsq) * ZES,
+            sgh2: 2.0 * sun.s4 * sun.z32,
+            sgh3: 2.0 * sun.s4 * (sun.z33 - sun.z31),
+            sgh4: -18.0 * sun.s4 * ZES,
+            sh2: -2.0 * sun.s2 * sun.z22,
+            sh3: -2.0 * sun.s2 * (sun.z23 - sun.z21),
+            ee2: 2.0 * moon.s1 * moon.s6,
+            e3: 2.0 * moon.s1 * moon.s7,
+            xi2: 2.0 * moon.s2 * moon.z12,
+            xi3: 2.0 * moon.s2 * (moon.z13 - moon.z11),
+            xl2: -2.0 * moon.s3 * moon.z2,
+            xl3: -2.0 * moon.s3 * (moon.z3 - moon.z1),
+            xl4: -2.0 * moon.s3 * (-21.0 - 9.0 * emsq) * ZEL,
+            xgh2: 2.0 * moon.s4 * moon.z32,
+            xgh3: 2.0 * moon.s4 * (moon.z33 - moon.z31),
+            xgh4: -18.0 * moon.s4 * ZEL,
+            xh2: -2.0 * moon.s2 * moon.z22,
+            xh3: -2.0 * moon.s2 * (moon.z23 - moon.z21),
+            zmol: (4.719_967_2 + 0.229_971_50 * day - gam) % TWO_PI,
+            zmos: (6.256_583_7 + 0.017_201_977 * day) % TWO_PI,
+        };
+
+        // dsinit: secular rates, dropping the node terms near 0 and 180
+        // degrees of inclination.
+        let equatorial = !(5.235_987_7e-2..=PI - 5.235_987_7e-2).contains(&inclm);
+        let ses = sun.s1 * ZNS * sun.s5;
+        let sis = sun.s2 * ZNS * (sun.z11 + sun.z13);
+        let sls = -ZNS * sun.s3 * (sun.z1 + sun.z3 - 14.0 - 6.0 * emsq);
+        let sghs = sun.s4 * ZNS * (sun.z31 + sun.z33 - 6.0);
+        let mut shs = if equatorial {
+            0.0
+        } else {
+            -ZNS * sun.s2 * (sun.z21 + sun.z23)
+        };
+        if sinim != 0.0 {
+            shs /= sinim;
+        }
+        let sgs = sghs - cosim * shs;
+        let dedt = ses + moon.s1 * ZNL * moon.s5;
+        let didt = sis + moon.s2 * ZNL * (moon.z11 + moon.z13);
+        let dmdt = sls - ZNL * moon.s3 * (moon.z1 + moon.z3 - 14.0 - 6.0 * emsq);
+        let sghl = moon.s4 * ZNL * (moon.z31 + moon.z33 - 6.0);
+        let shll = if equatorial {
+            0.0
+        } else {
+            -ZNL * moon.s2 * (moon.z21 + moon.z23)
+        };
+        let mut domdt = sgs + sghl;
+        let mut dnodt = shs;
+        if sinim != 0.0 {
+            domdt -= cosim / sinim * shll;
+            dnodt += shll / sinim;
+        }
+
+        let gsto = gmst(epoch);
+        let nm = elements.mean_motion;
+        let resonance = if nm > 0.003_490_658_5 && nm < 0.005_235_987_7 {
+            Some(synchronous(elements, sinim, cosim, emsq, xke))
+        } else if (8.26e-3..=9.24e-3).contains(&nm) && em >= 0.5 {
+            Some(half_day(elements, sinim, cosim, xke))
+        } else {
+            None
+        }
+        .map(|(harmonics, xlamo_base, xfact_base)| {
+            let (xlamo, xfact) = match harmonics {
+                Harmonics::Synchronous { .. } => (
+                    (xlamo_base - gsto) % TWO_PI,
+                    xfact_base + dmdt + domdt + dnodt,
+                ),
+                Harmonics::HalfDay { .. } => (
+                    (xlamo_base - gsto - gsto) % TWO_PI,
+                    xfact_base + dmdt + 2.0 * dnodt,
+                ),
+            };
+            Resonance {
+                harmonics,
+                xfact,
+                xlamo,
+                gsto,
+                mean_motion: nm,
+                argument_of_perigee: elements.argument_of_perigee,
+                argpdot: elements.argpdot,
+            }
+        });
+
+        DeepSpace {
+            periodics,
+            dedt,
+            didt,
+            dmdt,
+            dnodt,
+            domdt,
+            resonance,
+        }
+    }
+
+    // dspace: the secular lunar-solar drift `t` minutes after the epoch and,
+    // for a resonant orbit, the mean anomaly and mean motion from the
+    // integrated resonance. Returns the elements and mean motion.
+    pub fn secular(&self, t: f64, mut elements: Elements, mean_motion: f64) -> (Elements, f64) {
+        elements.eccentricity += self.dedt * t;
+        elements.inclination += self.didt * t;
+        elements.argument_of_perigee += self.domdt * t;
+        elements.raan += self.dnodt * t;
+        elements.mean_anomaly += self.dmdt * t;
+        let resonance = match &self.resonance {
+            Some(resonance) => resonance,
+            None => return (elements, mean_motion),
+        };
+
+        // Euler-Maclaurin integration of the resonance, in STEP-minute
+        // steps toward t and a Taylor step for the rest.
+        let theta = (resonance.gsto + t * RPTIM) % TWO_PI;
+        let step = if t > 0.0 { STEP } else { -STEP };
+        let (mut atime, mut xni, mut xli) = (0.0, resonance.mean_motion, resonance.xlamo);
+        let (xndt, xldot, xnddt) = loop {
+            let rates = resonance.rates(atime, xli, xni);
+            if (t - atime).abs() < STEP {
+                break rates;
+            }
+            let (xndt, xldot, xnddt) = rates;
+            xli += xldot * step + xndt * STEP * STEP / 2.0;
+            xni += xndt * step + xnddt * STEP * STEP / 2.0;
+            atime += step;
+        };
+        let ft = t - atime;
+        let nm = xni + xndt * ft + xnddt * ft * ft * 0.5;
+        let xl = xli + xldot * ft + xndt * ft * ft * 0.5;
+        elements.mean_anomaly = match resonance.harmonics {
+            Harmonics::Synchronous { .. } => {
+                xl - elements.raan - elements.argument_of_perigee + theta
+            }
+            Harmonics::HalfDay { .. } => xl - 2.0 * elements.raan + 2.0 * theta,
+        };
+        (elements, nm)
+    }
+
+    // dpper: the long-period lunar-solar periodics `t` minutes after the
+    // epoch, with Lyddane's modification below 0.2 radians of inclination.
+    pub fn periodics(&self, t: f64, mut elements: Elements) -> Elements {
+        let p = &self.periodics;
+        let terms = |zm: f64, eccentricity: f64| {
+            let zf = zm + 2.0 * eccentricity * zm.sin();
+            let sinzf = zf.sin();
+            (0.5 * sinzf * sinzf - 0.25, -0.5 * sinzf * zf.cos(), sinzf)
+        };
+        let (f2, f3, sinzf) = terms(p.zmos + ZNS * t, ZES);
+        let ses = p.se2 * f2 + p.se3 * f3;
+        let sis = p.si2 * f2 + p.si3 * f3;
+        let sls = p.sl2 * f2 + p.sl3 * f3 + p.sl4 * sinzf;
+        let sghs = p.sgh2 * f2 + p.sgh3 * f3 + p.sgh4 * sinzf;
+        let shs = p.sh2 * f2 + p.sh3 * f3;
+        let (f2, f3, sinzf) = terms(p.zmol + ZNL * t, ZEL);
+        let sel = p.ee2 * f2 + p.e3 * f3;
+        let sil = p.xi2 * f2 + p.xi3 * f3;
+        let sll = p.xl2 * f2 + p.xl3 * f3 + p.xl4 * sinzf;
+        let sghl = p.xgh2 * f2 + p.xgh3 * f3 + p.xgh4 * sinzf;
+        let shll = p.xh2 * f2 + p.xh3 * f3;
+        let pe = ses + sel;
+        let pinc = sis + sil;
+        let pl = sls + sll;
+        let pgh = sghs + sghl;
+        let ph = shs + shll;
+
+        elements.inclination += pinc;
+        elements.eccentricity += pe;
+        let (sinip, cosip) = elements.inclination.sin_cos();
+        if elements.inclination >= 0.2 {
+            let ph = ph / sinip;
+            elements.argument_of_perigee += pgh - cosip * ph;
+            elements.raan += ph;
+            elements.mean_anomaly += pl;
+        } else {
+            let (sinop, cosop) = elements.raan.sin_cos();
+            let alfdp = sinip * sinop + ph * cosop + pinc * cosip * sinop;
+            let betdp = sinip * cosop - ph * sinop + pinc * cosip * cosop;
+            let nodep = elements.raan % TWO_PI;
+            let xls =
+                elements.mean_anomaly + elements.argument_of_perigee + cosip * nodep + pl + pgh
+                    - pinc * nodep * sinip;
+            let mut node = alfdp.atan2(betdp);
+            if (nodep - node).abs() > PI {
+                node += if node < nodep { TWO_PI } else { -TWO_PI };
+            }
+            elements.raan = node;
+            elements.mean_anomaly += pl;
+            elements.argument_of_perigee = xls - elements.mean_anomaly - cosip * node;
+        }
+        elements
+    }
+}
+
+impl Resonance {
+    // The resonance's dn/dt, dλ/dt and d²n/dt² at `atime` minutes.
+    fn rates(&self, atime: f64, xli: f64, xni: f64) -> (f64, f64, f64) {
+        let xldot = xni + self.xfact;
+        let (xndt, xnddt) = match &self.harmonics {
+            Harmonics::Synchronous { del1, del2, del3 } => {
+                const FASX2: f64 = 0.131_309_08;
+                const FASX4: f64 = 2.884_319_8;
+                const FASX6: f64 = 0.374_480_87;
+                (
+                    del1 * (xli - FASX2).sin()
+                        + del2 * (2.0 * (xli - FASX4)).sin()
+                        + del3 * (3.0 * (xli - FASX6)).sin(),
+                    del1 * (xli - FASX2).cos()
+                        + 2.0 * del2 * (2.0 * (xli - FASX4)).cos()
+                        + 3.0 * del3 * (3.0 * (xli - FASX6)).cos(),
+                )
+            }
+            Harmonics::HalfDay { d } => {
+                const G22: f64 = 5.768_639_6;
+                const G32: f64 = 0.952_408_98;
+                const G44: f64 = 1.801_499_8;
+                const G52: f64 = 1.050_833_0;
+                const G54: f64 = 4.410_889_8;
+                let xomi = self.argument_of_perigee + self.argpdot * atime;
+                let x2omi = xomi + xomi;
+                let x2li = xli + xli;
+                let angles = [
+                    x2omi + xli - G22,
+                    xli - G22,
+                    xomi + xli - G32,
+                    -xomi + xli - G32,
+                    x2omi + x2li - G44,
+                    x2li - G44,
+                    xomi + xli - G52,
+                    -xomi + xli - G52,
+                    xomi + x2li - G54,
+                    -xomi + x2li - G54,
+                ];
+                // The terms in 2λ change twice as fast.
+                let multiples = [1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 1.0, 1.0, 2.0, 2.0];
+                let mut xndt = 0.0;
+                let mut xnddt = 0.0;
+                for i in 0..10 {
+                    xndt += d[i] * angles[i].sin();
+                    xnddt += multiples[i] * d[i] * angles[i].cos();
+                }
+                (xndt, xnddt)
+            }
+        };
+        (xndt, xldot, xnddt * xldot)
+    }
+}
+
+// Geosynchronous resonance coefficients, with xlamo and xfact before the
+// sidereal time and lunar-solar rates are folded in.
+fn synchronous(
+    elements: &MeanElements,
+    sinim: f64,
+    cosim: f64,
+    emsq: f64,
+    xke: f64,
+) -> (Harmonics, f64, f64) {
+    const Q22: f64 = 1.789_167_9e-6;
+    const Q31: f64 = 2.146_074_8e-6;
+    const Q33: f64 = 2.212_301_5e-7;
+    let nm = elements.mean_motion;
+    let aonv = (nm / xke).powf(2.0 / 3.0);
+    let g200 = 1.0 + emsq * (-2.5 + 0.8125 * emsq);
+    let g310 = 1.0 + 2.0 * emsq;
+    let g300 = 1.0 + emsq * (-6.0 + 6.60937 * emsq);
+    let f220 = 0.75 * (1.0 + cosim) * (1.0 + cosim);
+    let f311 = 0.9375 * sinim * sinim * (1.0 + 3.0 * cosim) - 0.75 * (1.0 + cosim);
+    let f330 = 1.875 * (1.0 + cosim).powi(3);
+    let del1 = 3.0 * nm * nm * aonv * aonv;
+    let harmonics = Harmonics::Synchronous {
+        del1: del1 * f311 * g310 * Q31 * aonv,
+        del2: 2.0 * del1 * f220 * g200 * Q22,
+        del3: 3.0 * del1 * f330 * g300 * Q33 * aonv,
+    };
+    let xlamo = elements.mean_anomaly + elements.raan + elements.argument_of_perigee;
+    let xfact = elements.mdot + elements.argpdot + elements.nodedot - RPTIM - nm;
+    (harmonics, xlamo, xfact)
+}
+
+// Half-day resonance coefficients, likewise.
+fn half_day(elements: &MeanElements, sinim: f64, cosim: f64, xke: f64) -> (Harmonics, f64, f64) {
+    const ROOT22: f64 = 1.789_167_9e-6;
+    const ROOT32: f64 = 3.739_379_2e-7;
+    const ROOT44: f64 = 7.363_695_3e-9;
+    const ROOT52: f64 = 1.142_863_9e-7;
+    const ROOT54: f64 = 2.176_580_3e-9;
+    let nm = elements.mean_motion;
+    let aonv = (nm / xke).powf(2.0 / 3.0);
+    let em = elements.eccentricity;
+    let emsq = em * em;
+    let eoc = em * emsq;
+    let cosisq = cosim * cosim;
+    let g201 = -0.306 - (em - 0.64) * 0.440;
+    let (g211, g310, g322, g410, g422, g520);
+    if em <= 0.65 {
+        g211 = 3.616 - 13.2470 * em + 16.2900 * emsq;
+        g310 = -19.302 + 117.3900 * em - 228.4190 * emsq + 156.5910 * eoc;
+        g322 = -18.9068 + 109.7927 * em - 214.6334 * emsq + 146.5816 * eoc;
+        g410 = -41.122 + 242.6940 * em - 471.0940 * emsq + 313.9530 * eoc;
+        g422 = -146.407 + 841.8800 * em - 1629.014 * emsq + 1083.4350 * eoc;
+        g520 = -532.114 + 3017.977 * em - 5740.032 * emsq + 3708.2760 * eoc;
+    } else {
+        g211 = -72.099 + 331.819 * em - 508.738 * emsq + 266.724 * eoc;
+        g310 = -346.844 + 1582.851 * em - 2415.925 * emsq + 1246.113 * eoc;
+        g322 = -342.585 + 1554.908 * em - 2366.899 * emsq + 1215.972 * eoc;
+        g410 = -1052.797 + 4758.686 * em - 7193.992 * emsq + 3651.957 * eoc;
+        g422 = -3581.690 + 16178.110 * em - 24462.770 * emsq + 12422.520 * eoc;
+        g520 = if em > 0.715 {
+            -5149.66 + 29936.92 * em - 54087.36 * emsq + 31324.56 * eoc
+        } else {
+            1464.74 - 4664.75 * em + 3763.64 * emsq
+        };
+    }
+    let (g533, g521, g532) = if em < 0.7 {
+        (
+            -919.22770 + 4988.6100 * em - 9064.7700 * emsq + 5542.21 * eoc,
+            -822.71072 + 4568.6173 * em - 8491.4146 * emsq + 5337.524 * eoc,
+            -853.66600 + 4690.2500 * em - 8624.7700 * emsq + 5341.4 * eoc,
+        )
+    } else {
+        (
+            -37995.780 + 161616.52 * em - 229838.20 * emsq + 109377.94 * eoc,
+            -51752.104 + 218913.95 * em - 309468.16 * emsq + 146349.42 * eoc,
+            -40023.880 + 170470.89 * em - 242699.48 * emsq + 115605.82 * eoc,
+        )
+    };
+
+    let sini2 = sinim * sinim;
+    let f220 = 0.75 * (1.0 + 2.0 * cosim + cosisq);
+    let f221 = 1.5 * sini2;
+    let f321 = 1.875 * sinim * (1.0 - 2.0 * cosim - 3.0 * cosisq);
+    let f322 = -1.875 * sinim * (1.0 + 2.0 * cosim - 3.0 * cosisq);
+    let f441 = 35.0 * sini2 * f220;
+    let f442 = 39.3750 * sini2 * sini2;
+    let f522 = 9.84375
+        * sinim
+        * (sini2 * (1.0 - 2.0 * cosim - 5.0 * cosisq)
+            + 0.33333333 * (-2.0 + 4.0 * cosim + 6.0 * cosisq));
+    let f523 = sinim
+        * (4.92187512 * sini2 * (-2.0 - 4.0 * cosim + 10.0 * cosisq)
+            + 6.56250012 * (1.0 + 2.0 * cosim - 3.0 * cosisq));
+    let f542 =
+        29.53125 * sinim * (2.0 - 8.0 * cosim + cosisq * (-12.0 + 8.0 * cosim + 10.0 * cosisq));
+    let f543 =
+        29.53125 * sinim * (-2.0 - 8.0 * cosim + cosisq * (12.0 + 8.0 * cosim - 10.0 * cosisq));
+
+    let xno2 = nm * nm;
+    let ainv2 = aonv * aonv;
+    let temp1 = 3.0 * xno2 * ainv2;
+    let temp = temp1 * ROOT22;
+    let d2201 = temp * f220 * g201;
+    let d2211 = temp * f221 * g211;
+    let temp1 = temp1 * aonv;
+    let temp = temp1 * ROOT32;
+    let d3210 = temp * f321 * g310;
+    let d3222 = temp * f322 * g322;
+    let temp1 = temp1 * aonv;
+    let temp = 2.0 * temp1 * ROOT44;
+    let d4410 = temp * f441 * g410;
+    let d4422 = temp * f442 * g422;
+    let temp1 = temp1 * aonv;
+    let temp = temp1 * ROOT52;
+    let d5220 = temp * f522 * g520;
+    let d5232 = temp * f523 * g532;
+    let temp = 2.0 * temp1 * ROOT54;
+    let d5421 = temp * f542 * g521;
+    let d5433 = temp * f543 * g533;
+    let harmonics = Harmonics::HalfDay {
+        d: [
+            d2201, d2211, d3210, d3222, d4410, d4422, d5220, d5232, d5421, d5433,
+        ],
+    };
+    let xlamo = elements.mean_anomaly + elements.raan + elements.raan;
+    let xfact = elements.mdot + 2.0 * (elements.nodedot - RPTIM) - nm;
+    (harmonics, xlamo, xfact)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{sgp4::Sgp4, tle::Tle};
+
+    fn sgp4(lines: &str) -> Sgp4 {
+        let sgp4 = Sgp4::new(&lines.parse::<Tle>().unwrap()).unwrap();
+        assert!(sgp4.is_deep_space());
+        sgp4
+    }
+
+    #[test]
+    fn test_resonance() {
+        // A geostationary satellite at 30 degrees east is pulled toward the
+        // stable point near 75 east, drifting ever faster: about a degree in
+        // its first month and three and a half in two. Lunar-solar terms
+        // alone move it less than half a degree.
+        let geo = sgp4(
+            "1 99999U 19999A   19343.00000000  .00000000  00000-0  00000-0 0  9992
+2 99999   0.0100 100.0000 0001000 180.0000 187.4519  1.00273791    04",
+        );
+        let longitude = |days: f64| {
+            let p = geo
+                .position_at(&geo.epoch().add_seconds(days * 86400.0))
+                .unwrap();
+            assert!((p.len() - 42164.0).abs() < 50.0, "{}", p.len());
+            p.y.atan2(p.x).to_degrees()
+        };
+        assert!((longitude(0.0) - 30.0).abs() < 0.1);
+        assert!((30.5..31.5).contains(&longitude(30.0)));
+        assert!((33.0..34.5).contains(&longitude(60.0)));
+
+        // A Molniya orbit, in the half-day resonance, keeps its perigee and
+        // apogee to within tens of kilometres over a month.
+        let molniya = sgp4(
+            "1 99998U 19999B   19343.00000000  .00000000  00000-0  00000-0 0  9991
+2 99998  63.4000 100.0000 7200000 270.0000  10.0000  2.00600000    07",
+        );
+        let extremes = |days: f64| {
+            (0..720).fold((f64::MAX, 0.0_f64), |(min, max), minute| {
+                let (r, _) = molniya.propagate(days * 1440.0 + minute as f64).unwrap();
+                (min.min(r.len()), max.max(r.len()))
+            })
+        };
+        let (perigee, apogee) = extremes(0.0);
+        assert!((perigee - 7466.0).abs() < 1.0 && (apogee - 45648.0).abs() < 1.0);
+        let (later_perigee, later_apogee) = extremes(30.0);
+        assert!((later_perigee - perigee).abs() < 50.0);
+        assert!((later_apogee - apogee).abs() < 50.0);
+    }
+}
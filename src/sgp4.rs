@@ -0,0 +1,597 @@
+// SGP4, the propagator TLEs are fitted with, following Vallado, Crawford,
+// Hujsak and Kelso, "Revisiting Spacetrack Report #3" (AIAA 2006-6753), with
+// the WGS72 constants the element sets assume. It works in the TEME frame;
+// `teme_to_ecef` turns its output Earth-fixed.
+//
+// Deep-space element sets, with periods of 225 minutes or more such as GEO,
+// add SDP4's lunar-solar and resonance terms from sdp4.rs.
+use std::{f64::consts::PI, fmt};
+
+use crate::{
+    position::Position,
+    sdp4::{DeepSpace, Elements, MeanElements},
+    time::Utc,
+    tle::Tle,
+};
+
+const RADIUS: f64 = 6378.135; // km, WGS72
+const XKE: f64 = 0.074_366_916_133_173_4; // sqrt(GM) in Earth radii^1.5 per minute
+const J2: f64 = 0.001_082_616;
+const J3: f64 = -0.000_002_538_81;
+const J4: f64 = -0.000_001_655_97;
+const J3OJ2: f64 = J3 / J2;
+const TWO_PI: f64 = 2.0 * PI;
+const X2O3: f64 = 2.0 / 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sgp4Error {
+    Eccentricity(f64), // Mean eccentricity outside [0, 1)
+    MeanMotion,        // Mean motion fell to zero or below
+    SemiLatusRectum,   // Osculating orbit is no longer an ellipse
+    Decayed,           // The satellite is below the Earth's surface
+}
+
+// A TLE's elements initialized for propagation. Angles are in radians and
+// rates per minute; distances in Earth radii unless noted.
+#[derive(Debug, Clone)]
+pub struct Sgp4 {
+    epoch: Utc,
+    deep_space: Option<DeepSpace>,
+    simple: bool, // Perigee below 220km: drop the higher-order drag terms
+    bstar: f64,
+    inclination: f64,
+    raan: f64,
+    eccentricity: f64,
+    argument_of_perigee: f64,
+    mean_anomaly: f64,
+    mean_motion: f64, // Un-Kozai'd, radians per minute
+    con41: f64,
+    x1mth2: f64,
+    x7thm1: f64,
+    eta: f64,
+    cc1: f64,
+    cc4: f64,
+    cc5: f64,
+    d2: f64,
+    d3: f64,
+    d4: f64,
+    delmo: f64,
+    sinmao: f64,
+    mdot: f64,
+    argpdot: f64,
+    nodedot: f64,
+    omgcof: f64,
+    xmcof: f64,
+    nodecf: f64,
+    t2cof: f64,
+    t3cof: f64,
+    t4cof: f64,
+    t5cof: f64,
+    xlcof: f64,
+    aycof: f64,
+}
+
+impl Sgp4 {
+    pub fn new(tle: &Tle) -> Result<Sgp4, Sgp4Error> {
+        let e = tle.eccentricity;
+        if !(0.0..1.0).contains(&e) {
+            return Err(Sgp4Error::Eccentricity(e));
+        }
+        let inclination = tle.inclination.to_radians();
+        let argument_of_perigee = tle.argument_of_perigee.to_radians();
+        let mean_anomaly = tle.mean_anomaly.to_radians();
+        let no_kozai = tle.mean_motion * TWO_PI / 1440.0;
+        if no_kozai <= 0.0 {
+            return Err(Sgp4Error::MeanMotion);
+        }
+
+        // Recover the original mean motion and semi-major axis from the
+        // Kozai mean motion in the element set.
+        let omeosq = 1.0 - e * e;
+        let rteosq = omeosq.sqrt();
+        let cos_i = inclination.cos();
+        let sin_i = inclination.sin();
+        let cosio2 = cos_i * cos_i;
+        let ak = (XKE / no_kozai).powf(X2O3);
+        let d1 = 0.75 * J2 * (3.0 * cosio2 - 1.0) / (rteosq * omeosq);
+        let del = d1 / (ak * ak);
+        let adel = ak * (1.0 - del * del - del * (1.0 / 3.0 + 134.0 * del * del / 81.0));
+        let del = d1 / (adel * adel);
+        let no = no_kozai / (1.0 + del);
+        let ao = (XKE / no).powf(X2O3);
+        let po = ao * omeosq;
+        let con42 = 1.0 - 5.0 * cosio2;
+        let con41 = -con42 - cosio2 - cosio2;
+        let posq = po * po;
+        let rp = ao * (1.0 - e);
+        let deep_space = TWO_PI / no >= 225.0;
+        let simple = rp < 220.0 / RADIUS + 1.0 || deep_space;
+
+        // Atmospheric density parameters, lowered for low perigees.
+        let mut sfour = 78.0 / RADIUS + 1.0;
+        let mut qzms24 = ((120.0 - 78.0) / RADIUS).powi(4);
+        let perigee = (rp - 1.0) * RADIUS;
+        if perigee < 156.0 {
+            sfour = if perigee < 98.0 { 20.0 } else { perigee - 78.0 };
+            qzms24 = ((120.0 - sfour) / RADIUS).powi(4);
+            sfour = sfour / RADIUS + 1.0;
+        }
+        let pinvsq = 1.0 / posq;
+        let tsi = 1.0 / (ao - sfour);
+        let eta = ao * e * tsi;
+        let etasq = eta * eta;
+        let eeta = e * eta;
+        let psisq = (1.0 - etasq).abs();
+        let coef = qzms24 * tsi.powi(4);
+        let coef1 = coef / psisq.powf(3.5);
+        let cc2 = coef1
+            * no
+            * (ao * (1.0 + 1.5 * etasq + eeta * (4.0 + etasq))
+                + 0.375 * J2 * tsi / psisq * con41 * (8.0 + 3.0 * etasq * (8.0 + etasq)));
+        let cc1 = tle.bstar * cc2;
+        let cc3 = if e > 1.0e-4 {
+            -2.0 * coef * tsi * J3OJ2 * no * sin_i / e
+        } else {
+            0.0
+        };
+        let x1mth2 = 1.0 - cosio2;
+        let cc4 = 2.0
+            * no
+            * coef1
+            * ao
+            * omeosq
+            * (eta * (2.0 + 0.5 * etasq) + e * (0.5 + 2.0 * etasq)
+                - J2 * tsi / (ao * psisq)
+                    * (-3.0 * con41 * (1.0 - 2.0 * eeta + etasq * (1.5 - 0.5 * eeta))
+                        + 0.75
+                            * x1mth2
+                            * (2.0 * etasq - eeta * (1.0 + etasq))
+                            * (2.0 * argument_of_perigee).cos()));
+        let cc5 = 2.0 * coef1 * ao * omeosq * (1.0 + 2.75 * (etasq + eeta) + eeta * etasq);
+
+        // Secular rates from J2 and J4.
+        let cosio4 = cosio2 * cosio2;
+        let temp1 = 1.5 * J2 * pinvsq * no;
+        let temp2 = 0.5 * temp1 * J2 * pinvsq;
+        let temp3 = -0.46875 * J4 * pinvsq * pinvsq * no;
+        let mdot = no
+            + 0.5 * temp1 * rteosq * con41
+            + 0.0625 * temp2 * rteosq * (13.0 - 78.0 * cosio2 + 137.0 * cosio4);
+        let argpdot = -0.5 * temp1 * con42
+            + 0.0625 * temp2 * (7.0 - 114.0 * cosio2 + 395.0 * cosio4)
+            + temp3 * (3.0 - 36.0 * cosio2 + 49.0 * cosio4);
+        let xhdot1 = -temp1 * cos_i;
+        let nodedot = xhdot1
+            + (0.5 * temp2 * (4.0 - 19.0 * cosio2) + 2.0 * temp3 * (3.0 - 7.0 * cosio2)) * cos_i;
+        let omgcof = tle.bstar * cc3 * argument_of_perigee.cos();
+        let xmcof = if e > 1.0e-4 {
+            -X2O3 * coef * tle.bstar / eeta
+        } else {
+            0.0
+        };
+        let nodecf = 3.5 * omeosq * xhdot1 * cc1;
+        let t2cof = 1.5 * cc1;
+        let xlcof = -0.25 * J3OJ2 * sin_i * (3.0 + 5.0 * cos_i) / xlcof_divisor(cos_i);
+        let aycof = -0.5 * J3OJ2 * sin_i;
+        let delmo = (1.0 + eta * mean_anomaly.cos()).powi(3);
+
+        let (mut d2, mut d3, mut d4) = (0.0, 0.0, 0.0);
+        let (mut t3cof, mut t4cof, mut t5cof) = (0.0, 0.0, 0.0);
+        if !simple {
+            let cc1sq = cc1 * cc1;
+            d2 = 4.0 * ao * tsi * cc1sq;
+            let temp = d2 * tsi * cc1 / 3.0;
+            d3 = (17.0 * ao + sfour) * temp;
+            d4 = 0.5 * temp * ao * tsi * (221.0 * ao + 31.0 * sfour) * cc1;
+            t3cof = d2 + 2.0 * cc1sq;
+            t4cof = 0.25 * (3.0 * d3 + cc1 * (12.0 * d2 + 10.0 * cc1sq));
+            t5cof = 0.2
+                * (3.0 * d4 + 12.0 * cc1 * d3 + 6.0 * d2 * d2 + 15.0 * cc1sq * (2.0 * d2 + cc1sq));
+        }
+
+        let deep_space = if deep_space {
+            let elements = MeanElements {
+                eccentricity: e,
+                inclination,
+                raan: tle.raan.to_radians(),
+                argument_of_perigee,
+                mean_anomaly,
+                mean_motion: no,
+                mdot,
+                argpdot,
+                nodedot,
+            };
+            Some(DeepSpace::new(&tle.epoch, &elements, XKE))
+        } else {
+            None
+        };
+
+        Ok(Sgp4 {
+            epoch: tle.epoch,
+            deep_space,
+            simple,
+            bstar: tle.bstar,
+            inclination,
+            raan: tle.raan.to_radians(),
+            eccentricity: e,
+            argument_of_perigee,
+            mean_anomaly,
+            mean_motion: no,
+            con41,
+            x1mth2,
+            x7thm1: 7.0 * cosio2 - 1.0,
+            eta,
+            cc1,
+            cc4,
+            cc5,
+            d2,
+            d3,
+            d4,
+            delmo,
+            sinmao: mean_anomaly.sin(),
+            mdot,
+            argpdot,
+            nodedot,
+            omgcof,
+            xmcof,
+            nodecf,
+            t2cof,
+            t3cof,
+            t4cof,
+            t5cof,
+            xlcof,
+            aycof,
+        })
+    }
+
+    pub fn epoch(&self) -> Utc {
+        self.epoch
+    }
+
+    // Whether the SDP4 terms apply.
+    pub fn is_deep_space(&self) -> bool {
+        self.deep_space.is_some()
+    }
+
+    // TEME position (km) and velocity (km/s) `minutes` after the epoch.
+    pub fn propagate(&self, minutes: f64) -> Result<(Position, Position), Sgp4Error> {
+        let t = minutes;
+        let t2 = t * t;
+
+        // Secular gravity and drag.
+        let xmdf = self.mean_anomaly + self.mdot * t;
+        let argpdf = self.argument_of_perigee + self.argpdot * t;
+        let nodedf = self.raan + self.nodedot * t;
+        let mut argpm = argpdf;
+        let mut mm = xmdf;
+        let mut nodem = nodedf + self.nodecf * t2;
+        let mut tempa = 1.0 - self.cc1 * t;
+        let mut tempe = self.bstar * self.cc4 * t;
+        let mut templ = self.t2cof * t2;
+        if !self.simple {
+            let delomg = self.omgcof * t;
+            let delm = self.xmcof * ((1.0 + self.eta * xmdf.cos()).powi(3) - self.delmo);
+            let temp = delomg + delm;
+            mm = xmdf + temp;
+            argpm = argpdf - temp;
+            let t3 = t2 * t;
+            let t4 = t3 * t;
+            tempa -= self.d2 * t2 + self.d3 * t3 + self.d4 * t4;
+            tempe += self.bstar * self.cc5 * (mm.sin() - self.sinmao);
+            templ += self.t3cof * t3 + t4 * (self.t4cof + t * self.t5cof);
+        }
+
+        let mut nm = self.mean_motion;
+        let mut em = self.eccentricity;
+        let mut inclm = self.inclination;
+        if let Some(deep_space) = &self.deep_space {
+            let elements = Elements {
+                eccentricity: em,
+                inclination: inclm,
+                raan: nodem,
+                argument_of_perigee: argpm,
+                mean_anomaly: mm,
+            };
+            let (elements, n) = deep_space.secular(t, elements, nm);
+            em = elements.eccentricity;
+            inclm = elements.inclination;
+            nodem = elements.raan;
+            argpm = elements.argument_of_perigee;
+            mm = elements.mean_anomaly;
+            nm = n;
+        }
+        if nm <= 0.0 {
+            return Err(Sgp4Error::MeanMotion);
+        }
+        let am = (XKE / nm).powf(X2O3) * tempa * tempa;
+        let nm = XKE / am.powf(1.5);
+        em -= tempe;
+        if !(-0.001..1.0).contains(&em) {
+            return Err(Sgp4Error::Eccentricity(em));
+        }
+        em = em.max(1.0e-6);
+        mm += self.mean_motion * templ;
+        let xlm = mm + argpm + nodem;
+        let nodem = nodem % TWO_PI;
+        let argpm = argpm % TWO_PI;
+        let xlm = xlm % TWO_PI;
+        let mm = (xlm - argpm - nodem) % TWO_PI;
+
+        // Long-period periodics, the lunar-solar ones first.
+        let mut elements = Elements {
+            eccentricity: em,
+            inclination: inclm,
+            raan: nodem,
+            argument_of_perigee: argpm,
+            mean_anomaly: mm,
+        };
+        let (mut aycof, mut xlcof) = (self.aycof, self.xlcof);
+        let (mut con41, mut x1mth2, mut x7thm1) = (self.con41, self.x1mth2, self.x7thm1);
+        if let Some(deep_space) = &self.deep_space {
+            elements = deep_space.periodics(t, elements);
+            if elements.inclination < 0.0 {
+                elements.inclination = -elements.inclination;
+                elements.raan += PI;
+                elements.argument_of_perigee -= PI;
+            }
+            if !(0.0..=1.0).contains(&elements.eccentricity) {
+                return Err(Sgp4Error::Eccentricity(elements.eccentricity));
+            }
+            let (sinip, cosip) = elements.inclination.sin_cos();
+            aycof = -0.5 * J3OJ2 * sinip;
+            xlcof = -0.25 * J3OJ2 * sinip * (3.0 + 5.0 * cosip) / xlcof_divisor(cosip);
+            let cosisq = cosip * cosip;
+            con41 = 3.0 * cosisq - 1.0;
+            x1mth2 = 1.0 - cosisq;
+            x7thm1 = 7.0 * cosisq - 1.0;
+        }
+        let Elements {
+            eccentricity: ep,
+            inclination: xincp,
+            raan: nodep,
+            argument_of_perigee: argpp,
+            mean_anomaly: mp,
+        } = elements;
+        let (sinip, cosip) = xincp.sin_cos();
+        let axnl = ep * argpp.cos();
+        let temp = 1.0 / (am * (1.0 - ep * ep));
+        let aynl = ep * argpp.sin() + temp * aycof;
+        let xl = mp + argpp + nodep + temp * xlcof * axnl;
+
+        // Kepler's equation, in terms of the eccentric longitude.
+        let u = (xl - nodep) % TWO_PI;
+        let mut eo1 = u;
+        let (mut sineo1, mut coseo1) = (0.0, 0.0);
+        for _ in 0..10 {
+            sineo1 = eo1.sin();
+            coseo1 = eo1.cos();
+            let mut step =
+                (u - aynl * coseo1 + axnl * sineo1 - eo1) / (1.0 - coseo1 * axnl - sineo1 * aynl);
+            if step.abs() >= 0.95 {
+                step = 0.95 * step.signum();
+            }
+            eo1 += step;
+            if step.abs() < 1.0e-12 {
+                break;
+            }
+        }
+
+        // Short-period periodics.
+        let ecose = axnl * coseo1 + aynl * sineo1;
+        let esine = axnl * sineo1 - aynl * coseo1;
+        let el2 = axnl * axnl + aynl * aynl;
+        let pl = am * (1.0 - el2);
+        if pl < 0.0 {
+            return Err(Sgp4Error::SemiLatusRectum);
+        }
+        let rl = am * (1.0 - ecose);
+        let rdotl = am.sqrt() * esine / rl;
+        let rvdotl = pl.sqrt() / rl;
+        let betal = (1.0 - el2).sqrt();
+        let temp = esine / (1.0 + betal);
+        let sinu = am / rl * (sineo1 - aynl - axnl * temp);
+        let cosu = am / rl * (coseo1 - axnl + aynl * temp);
+        let su = sinu.atan2(cosu);
+        let sin2u = (cosu + cosu) * sinu;
+        let cos2u = 1.0 - 2.0 * sinu * sinu;
+        let temp = 1.0 / pl;
+        let temp1 = 0.5 * J2 * temp;
+        let temp2 = temp1 * temp;
+
+        let mrt = rl * (1.0 - 1.5 * temp2 * betal * con41) + 0.5 * temp1 * x1mth2 * cos2u;
+        let su = su - 0.25 * temp2 * x7thm1 * sin2u;
+        let xnode = nodep + 1.5 * temp2 * cosip * sin2u;
+        let xinc = xincp + 1.5 * temp2 * cosip * sinip * cos2u;
+        let mvt = rdotl - nm * temp1 * x1mth2 * sin2u / XKE;
+        let rvdot = rvdotl + nm * temp1 * (x1mth2 * cos2u + 1.5 * con41) / XKE;
+
+        // Orientation vectors.
+        let (sinsu, cossu) = su.sin_cos();
+        let (snod, cnod) = xnode.sin_cos();
+        let (sini, cosi) = xinc.sin_cos();
+        let xmx = -snod * cosi;
+        let xmy = cnod * cosi;
+        let u = Position::new(
+            xmx * sinsu + cnod * cossu,
+            xmy * sinsu + snod * cossu,
+            sini * sinsu,
+        );
+        let v = Position::new(
+            xmx * cossu - cnod * sinsu,
+            xmy * cossu - snod * sinsu,
+            sini * cossu,
+        );
+
+        if mrt < 1.0 {
+            return Err(Sgp4Error::Decayed);
+        }
+        let velocity_scale = RADIUS * XKE / 60.0;
+        Ok((
+            u.scale(mrt * RADIUS),
+            Position::new(
+                (mvt * u.x + rvdot * v.x) * velocity_scale,
+                (mvt * u.y + rvdot * v.y) * velocity_scale,
+                (mvt * u.z + rvdot * v.z) * velocity_scale,
+            ),
+        ))
+    }
+
+    // Earth-fixed position at `time`.
+    pub fn position_at(&self, time: &Utc) -> Result<Position, Sgp4Error> {
+        let minutes = time.seconds_since(&self.epoch) / 60.0;
+        let (position, _) = self.propagate(minutes)?;
+        Ok(teme_to_ecef(&position, time))
+    }
+}
+
+// 1 + cos i, kept from zero for an inclination of exactly 180 degrees.
+fn xlcof_divisor(cos_i: f64) -> f64 {
+    if (cos_i + 1.0).abs() > 1.5e-12 {
+        1.0 + cos_i
+    } else {
+        1.5e-12
+    }
+}
+
+// Greenwich mean sidereal time (IAU 1982), in radians.
+pub fn gmst(time: &Utc) -> f64 {
+    let t = (time.julian_date() - 2_451_545.0) / 36525.0;
+    let seconds = -6.2e-6 * t * t * t
+        + 0.093_104 * t * t
+        + (876_600.0 * 3600.0 + 8_640_184.812_866) * t
+        + 67_310.548_41;
+    (seconds.to_radians() / 240.0).rem_euclid(TWO_PI)
+}
+
+// TEME to Earth-fixed, rotating by sidereal time. Polar motion is ignored;
+// it moves positions by metres.
+pub fn teme_to_ecef(position: &Position, time: &Utc) -> Position {
+    let (sin, cos) = gmst(time).sin_cos();
+    Position::new(
+        cos * position.x + sin * position.y,
+        -sin * position.x + cos * position.y,
+        position.z,
+    )
+}
+
+impl fmt::Display for Sgp4Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sgp4Error::Eccentricity(e) => write!(f, "mean eccentricity {} is out of range", e),
+            Sgp4Error::MeanMotion => write!(f, "mean motion is not positive"),
+            Sgp4Error::SemiLatusRectum => write!(f, "orbit is no longer elliptical"),
+            Sgp4Error::Decayed => write!(f, "satellite has decayed"),
+        }
+    }
+}
+
+impl std::error::Error for Sgp4Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: &Position, b: &Position, tolerance: f64) {
+        assert!(a.sub(b).len() < tolerance, "{} != {}", a, b);
+    }
+
+    // Vanguard 1, the first case in Vallado's verification set.
+    const VANGUARD: &str = "1 00005U 58002B   00179.78495062  .00000023  00000-0  28098-4 0  4753
+2 00005  34.2682 348.7242 1859667 331.7664  19.3264 10.82419157413667";
+
+    #[test]
+    fn test_vanguard() {
+        let tle: Tle = VANGUARD.parse().unwrap();
+        let sgp4 = Sgp4::new(&tle).unwrap();
+        assert!(!sgp4.is_deep_space());
+        let (r, v) = sgp4.propagate(0.0).unwrap();
+        assert_near(
+            &r,
+            &Position::new(7022.46529266, -1400.08296755, 0.03995155),
+            1e-6,
+        );
+        assert_near(
+            &v,
+            &Position::new(1.893841015, 6.405893759, 4.534807250),
+            1e-8,
+        );
+        let (r, _) = sgp4.propagate(360.0).unwrap();
+        assert_near(
+            &r,
+            &Position::new(-7154.03120202, -3783.17682504, -3536.19412294),
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn test_iss() {
+        // The example from the python-sgp4 documentation.
+        let tle: Tle = "1 25544U 98067A   19343.69339541  .00001764  00000-0  38792-4 0  9991
+2 25544  51.6439 211.2001 0007417  17.6667  85.6398 15.50103472202482"
+            .parse()
+            .unwrap();
+        let sgp4 = Sgp4::new(&tle).unwrap();
+        let time = Utc::from_julian_date(2_458_827.362_605);
+        let minutes = time.seconds_since(&sgp4.epoch()) / 60.0;
+        let (r, v) = sgp4.propagate(minutes).unwrap();
+        assert_near(
+            &r,
+            &Position::new(-6102.443276428913, -986.3320160725, -2820.3130707199225),
+            1e-3,
+        );
+        assert_near(
+            &v,
+            &Position::new(-1.4552527284474308, -5.527413826579074, 5.101042055236172),
+            1e-6,
+        );
+        // Turning Earth-fixed is a rotation about z.
+        let ecef = sgp4.position_at(&time).unwrap();
+        assert!((ecef.len() - r.len()).abs() < 1e-9);
+        assert!((ecef.z - r.z).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_deep_space() {
+        // Spacetrack Report #3's deep-space case, a 10.5 hour orbit with an
+        // eccentricity of 0.73, against Vallado's vectors. Without the
+        // lunar-solar terms it is off by tens of kilometres.
+        let tle: Tle = "1 11801U          80230.29629788  .01431103  00000-0  14311-1 0    13
+2 11801  46.7916 230.4354 7318036  47.4722  10.4117  2.28537848    13"
+            .parse()
+            .unwrap();
+        let sgp4 = Sgp4::new(&tle).unwrap();
+        assert!(sgp4.is_deep_space());
+        let expected = [
+            (
+                0.0,
+                (7473.37066650, 428.95261765, 5828.74786377),
+                (5.10715413, 6.44468284, -0.18613096),
+            ),
+            (
+                360.0,
+                (-3305.22537232, 32410.86328737, -24697.17680583),
+                (-1.30113538, -1.15131518, -0.28333528),
+            ),
+            (
+                720.0,
+                (14271.28759740, 24110.46406941, -4725.76352178),
+                (-0.32050445, 2.67984074, -2.08405289),
+            ),
+            (
+                1080.0,
+                (-9990.05799866, 22717.35185523, -23616.89066681),
+                (-1.01667246, -2.29026759, 0.72892364),
+            ),
+            (
+                1440.0,
+                (9787.86975061, 33753.34243551, -15030.81176700),
+                (-1.09425066, 0.92358845, -1.52230928),
+            ),
+        ];
+        for &(minutes, (x, y, z), (vx, vy, vz)) in &expected {
+            let (r, v) = sgp4.propagate(minutes).unwrap();
+            assert_near(&r, &Position::new(x, y, z), 0.05);
+            assert_near(&v, &Position::new(vx, vy, vz), 1e-5);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::scenario::Band;
+
+// A single `sat <id> beam <n> user <id> color <band>` line of solver output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Assignment {
+    pub satellite: i32,
+    pub beam: usize,
+    pub user: i32,
+    pub band: Band,
+}
+
+// The beams assigned for a scenario, in numeric (satellite, beam) order. Beam
+// numbers are 1-based and fixed when the solver places each beam, so they stay
+// the same however the solution is printed or reloaded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Solution {
+    assignments: Vec<Assignment>,
+    users: HashMap<i32, usize>, // User id to index of its first assignment
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize, // 1-based
+    pub message: String,
+}
+
+impl Solution {
+    pub fn new(mut assignments: Vec<Assignment>) -> Solution {
+        assignments.sort_by_key(|a| (a.satellite, a.beam));
+        let mut users = HashMap::with_capacity(assignments.len());
+        for (i, assignment) in assignments.iter().enumerate() {
+            users.entry(assignment.user).or_insert(i);
+        }
+        Solution { assignments, users }
+    }
+
+    pub fn assignments(&self) -> &[Assignment] {
+        &self.assignments
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Assignment> {
+        self.assignments.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.assignments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assignments.is_empty()
+    }
+
+    // Number of distinct users with a beam.
+    pub fn covered(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn by_user(&self, user: i32) -> Option<&Assignment> {
+        self.users.get(&user).map(|i| &self.assignments[*i])
+    }
+
+    // All beams of one satellite, in beam order.
+    pub fn by_satellite(&self, satellite: i32) -> &[Assignment] {
+        let start = self
+            .assignments
+            .partition_point(|a| a.satellite < satellite);
+        let end = self
+            .assignments
+            .partition_point(|a| a.satellite <= satellite);
+        &self.assignments[start..end]
+    }
+
+    pub fn by_band(&self, band: Band) -> impl Iterator<Item = &Assignment> {
+        self.assignments.iter().filter(move |a| a.band == band)
+    }
+
+    // Beams grouped by satellite, in satellite order.
+    pub fn satellites(&self) -> impl Iterator<Item = &[Assignment]> {
+        self.assignments.chunk_by(|a, b| a.satellite == b.satellite)
+    }
+}
+
+impl FromStr for Solution {
+    type Err = ParseError;
+
+    // Parse solver output, skipping blank lines and comments like evaluate.py.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.contains('#') && !line.trim().is_empty())
+            .map(|(i, line)| {
+                line.parse().map_err(|message| ParseError {
+                    line: i + 1,
+                    message,
+                })
+            })
+            .collect::<Result<Vec<Assignment>, ParseError>>()
+            .map(Solution::new)
+    }
+}
+
+impl FromStr for Assignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["sat", satellite, "beam", beam, "user", user, "color", band] => Ok(Assignment {
+                satellite: satellite
+                    .parse()
+                    .map_err(|_| format!("invalid sat id '{}'", satellite))?,
+                beam: match beam.parse() {
+                    Ok(beam) if beam > 0 => beam,
+                    _ => return Err(format!("invalid beam id '{}'", beam)),
+                },
+                user: user
+                    .parse()
+                    .map_err(|_| format!("invalid user id '{}'", user))?,
+                band: band.parse()?,
+            }),
+            _ => Err(format!("invalid line '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, assignment) in self.assignments.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", assignment)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sat {} beam {} user {} color {}",
+            self.satellite, self.beam, self.user, self.band
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assignment(satellite: i32, beam: usize, user: i32, band: Band) -> Assignment {
+        Assignment {
+            satellite,
+            beam,
+            user,
+            band,
+        }
+    }
+
+    #[test]
+    fn test_parse_solution() {
+        let solution: Solution = "# comment
+sat 10 beam 32 user 3 color D
+
+sat 1 beam 1 user 2 color A"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            solution.assignments(),
+            &[assignment(1, 1, 2, Band::A), assignment(10, 32, 3, Band::D)]
+        );
+
+        assert_eq!(
+            "sat 1 beam 1 user 2 color A\nsat 1 beam 0 user 3 color B".parse::<Solution>(),
+            Err(ParseError {
+                line: 2,
+                message: String::from("invalid beam id '0'")
+            })
+        );
+        assert!("sat 1 beam 1 user 2 color E".parse::<Solution>().is_err());
+        assert!("sat 1 beam 1 user 2".parse::<Solution>().is_err());
+    }
+
+    #[test]
+    fn test_numeric_order() {
+        let solution = Solution::new(vec![
+            assignment(10, 1, 1, Band::A),
+            assignment(2, 10, 2, Band::B),
+            assignment(2, 2, 3, Band::A),
+        ]);
+        assert_eq!(
+            format!("{}", solution),
+            "sat 2 beam 2 user 3 color A
+sat 2 beam 10 user 2 color B
+sat 10 beam 1 user 1 color A"
+        );
+        assert_eq!(format!("{}", solution).parse(), Ok(solution));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let solution = Solution::new(vec![
+            assignment(1, 1, 1, Band::A),
+            assignment(2, 1, 2, Band::A),
+            assignment(2, 2, 3, Band::B),
+            assignment(3, 1, 4, Band::B),
+        ]);
+        assert_eq!(solution.len(), 4);
+        assert_eq!(solution.covered(), 4);
+        assert_eq!(solution.by_user(3), Some(&assignment(2, 2, 3, Band::B)));
+        assert_eq!(solution.by_user(5), None);
+        assert_eq!(
+            solution.by_satellite(2),
+            &[assignment(2, 1, 2, Band::A), assignment(2, 2, 3, Band::B)]
+        );
+        assert!(solution.by_satellite(4).is_empty());
+        assert_eq!(solution.by_band(Band::B).count(), 2);
+        assert_eq!(solution.satellites().count(), 3);
+    }
+}
@@ -1,15 +1,42 @@
-#[cfg(feature = "elevation")]
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
 
-#[cfg(feature = "elevation")]
-use crate::position::ORIGIN;
 use crate::{
+    constraints::Constraints,
+    payload::Payload,
     position::Position,
-    scenario::{Band, Beam, Entity, Satellite, Scenario, BANDS},
+    scenario::{Band, Entity, Satellite, Scenario, BANDS},
+    solution::{Assignment, Solution},
+    terminal::Terminal,
 };
 
-pub const BEAMS: usize = 32;
-pub const BEAM_ANGLE: f32 = 10.0;
+pub use crate::constraints::{BEAMS, BEAM_ANGLE};
+
+// How find_best picks among the satellites that can accept a user.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Strategy {
+    // The first satellite in input order.
+    First,
+    // The satellite highest in the user's sky.
+    #[default]
+    Elevation,
+}
+
+// A satellite and the beams the solver has given it so far.
+pub struct Plan<'a> {
+    satellite: &'a Satellite,
+    payload: Payload,
+    beams: Vec<Beam>,
+}
+
+pub struct Beam {
+    user: Entity,
+    band: Band,
+}
 
 impl Scenario {
     // Simple first-come-first serve packing. It hops across the bands, pulling
@@ -31,152 +58,312 @@ impl Scenario {
     // may required a more exhaustive search approach, including backtracking
     // and spilling users between satellites. This naive approach works in a
     // suprisingly (to me) good manner.
-    pub fn optimize(&mut self) {
-        let interferers = &self.interferers().clone();
-        let mut users = self.users().clone();
-        let start_users = users.len();
-        BANDS.iter().for_each(|band| {
-            for index in (0..users.len()).rev() {
-                let user = users[index];
-                self.find_best(&user, *band, interferers).and_then(|s| {
-                    users.swap_remove(index);
-                    s.beams_mut().push(Beam::new(user, *band));
-                    Some(())
-                });
-            }
-        });
-        self.assigned = start_users - users.len();
-        eprintln!("Assigned {} users", self.assigned);
+    pub fn optimize(&self) -> Solution {
+        self.optimize_with(Strategy::default(), &Constraints::default())
     }
 
-    #[cfg(feature = "first")]
-    pub fn find_best(
-        &mut self,
-        user: &Entity,
-        band: Band,
-        interferers: &Vec<Entity>,
-    ) -> Option<&mut Satellite> {
-        self.satellites_mut()
-            .iter_mut()
-            .filter(|s| s.can_accept(&user, band, interferers))
-            .next()
-    }
-
-    // Find the next best satellite for the user.
-    #[cfg(feature = "elevation")]
-    pub fn find_best(
-        &mut self,
-        user: &Entity,
-        band: Band,
-        interferers: &Vec<Entity>,
-    ) -> Option<&mut Satellite> {
-        let position = &user.position();
-        self.satellites_mut()
-            .iter_mut()
-            .filter(|s| s.can_accept(&user, band, interferers))
-            .max_by(|a, b| {
-                let angle_a = Position::angle_origin(&ORIGIN, a.entity().position(), position);
-                let angle_b = Position::angle_origin(&ORIGIN, b.entity().position(), position);
-                // angleA.cmp(angleB)
-                if angle_a < angle_b {
-                    Ordering::Less
-                } else if angle_a > angle_b {
-                    Ordering::Greater
-                } else {
-                    Ordering::Equal
+    pub fn optimize_with(&self, strategy: Strategy, constraints: &Constraints) -> Solution {
+        self.optimize_from(&Solution::default(), strategy, constraints)
+    }
+
+    // Warm start from an earlier solution, such as the previous epoch's: each
+    // user keeps its satellite and band while that beam can still be placed,
+    // in the earlier solution's beam order, and everyone else is packed as
+    // above. Only users whose beam broke, or who weren't served, are
+    // reassigned, so consecutive plans hand over as few users as they can.
+    //
+    // Users are placed one priority tier at a time, highest first, both those
+    // kept and those packed, so no one is left unserved to make room for a
+    // lower tier; within a tier the heaviest are packed first.
+    pub fn optimize_from(
+        &self,
+        previous: &Solution,
+        strategy: Strategy,
+        constraints: &Constraints,
+    ) -> Solution {
+        let interferers = &self.interference_limits(constraints);
+        let mut plans: Vec<Plan> = self
+            .satellites()
+            .iter()
+            .map(|s| Plan::with_payload(s, self.payload(s.entity().id(), constraints)))
+            .collect();
+        let plan_index: HashMap<i32, usize> = plans
+            .iter()
+            .enumerate()
+            .map(|(i, plan)| (plan.satellite.entity().id(), i))
+            .collect();
+        let users_by_id = self.users_by_id();
+        let mut kept = HashSet::new();
+        for (_, tier) in self.tiers() {
+            let in_tier: HashSet<i32> = tier.iter().map(Entity::id).collect();
+            for a in previous.iter().filter(|a| in_tier.contains(&a.user)) {
+                if let (Some(&i), Some(user)) =
+                    (plan_index.get(&a.satellite), users_by_id.get(&a.user))
+                {
+                    let terminal = self.terminal(a.user);
+                    if !kept.contains(&a.user)
+                        && plans[i].can_accept(user, a.band, &terminal, interferers, constraints)
+                    {
+                        kept.insert(a.user);
+                        plans[i].push(Beam::new(**user, a.band));
+                    }
                 }
-            })
+            }
+
+            let mut users: Vec<Entity> = tier
+                .into_iter()
+                .filter(|user| !kept.contains(&user.id()))
+                .collect();
+            // Users are taken from the back, so the heaviest go last. The sort
+            // is stable, leaving equal weights in input order.
+            users.sort_by(|a, b| {
+                self.weight(a.id())
+                    .partial_cmp(&self.weight(b.id()))
+                    .unwrap_or(Ordering::Equal)
+            });
+            BANDS.iter().for_each(|band| {
+                for index in (0..users.len()).rev() {
+                    let user = users[index];
+                    let terminal = self.terminal(user.id());
+                    if let Some(plan) = find_best(
+                        &mut plans,
+                        &user,
+                        *band,
+                        &terminal,
+                        interferers,
+                        strategy,
+                        constraints,
+                    ) {
+                        users.swap_remove(index);
+                        plan.beams.push(Beam::new(user, *band));
+                    }
+                }
+            });
+        }
+        Solution::new(plans.iter().flat_map(Plan::assignments).collect())
     }
 }
 
-impl Satellite {
-    pub fn beam_intersection(&self, user: &Entity, band: Band) -> bool {
-        self.beams()
+// Find the next best satellite for the user.
+pub fn find_best<'p, 'a>(
+    plans: &'p mut [Plan<'a>],
+    user: &Entity,
+    band: Band,
+    terminal: &Terminal,
+    interferers: &[(Entity, f64)],
+    strategy: Strategy,
+    constraints: &Constraints,
+) -> Option<&'p mut Plan<'a>> {
+    let position = user.position();
+    let mut candidates = plans
+        .iter_mut()
+        .filter(|p| p.can_accept(user, band, terminal, interferers, constraints));
+    match strategy {
+        Strategy::First => candidates.next(),
+        Strategy::Elevation => candidates.max_by(|a, b| {
+            let model = constraints.elevation_model;
+            let elevation_a = position
+                .look_angles(a.satellite.entity().position(), model)
+                .elevation;
+            let elevation_b = position
+                .look_angles(b.satellite.entity().position(), model)
+                .elevation;
+            elevation_a
+                .partial_cmp(&elevation_b)
+                .unwrap_or(Ordering::Equal)
+        }),
+    }
+}
+
+impl<'a> Plan<'a> {
+    pub fn new(satellite: &'a Satellite) -> Plan<'a> {
+        Plan::with_payload(satellite, Payload::default())
+    }
+
+    // A plan bound by the satellite's own limits, from Scenario::payload.
+    pub fn with_payload(satellite: &'a Satellite, payload: Payload) -> Plan<'a> {
+        Plan {
+            satellite,
+            payload,
+            beams: Vec::with_capacity(BEAMS),
+        }
+    }
+
+    pub fn satellite(&self) -> &Satellite {
+        self.satellite
+    }
+
+    pub fn beams(&self) -> &[Beam] {
+        &self.beams
+    }
+
+    pub fn push(&mut self, beam: Beam) {
+        self.beams.push(beam)
+    }
+
+    // Beams are numbered in the order they were placed.
+    pub fn assignments(&self) -> impl Iterator<Item = Assignment> + '_ {
+        self.beams
             .iter()
-            .map(|beam| {
-                beam.band() == band
-                    && Position::separation(
-                        self.entity().position(),
-                        user.position(),
-                        beam.user().position(),
-                    )
-                    .to_degrees()
-                        <= BEAM_ANGLE
+            .enumerate()
+            .map(move |(i, beam)| Assignment {
+                satellite: self.satellite.entity().id(),
+                beam: i + 1,
+                user: beam.user.id(),
+                band: beam.band,
             })
-            .any(|s| s)
     }
 
-    pub fn interference(&self, user: &Entity, interferers: &Vec<Entity>) -> bool {
-        interferers
-            .iter()
-            .map(|interferer| {
-                Position::separation(
+    pub fn beam_intersection(&self, user: &Entity, band: Band, angle: f64) -> bool {
+        self.beams.iter().any(|beam| {
+            beam.band == band
+                && Position::separation(
+                    self.satellite.entity().position(),
                     user.position(),
-                    self.entity().position(),
-                    interferer.position(),
+                    beam.user.position(),
                 )
                 .to_degrees()
-                    <= 20.0
-                // > 20* from non-starlink sats
+                    <= angle
+        })
+    }
+
+    // Each interferer comes with its own limit, from
+    // Scenario::interference_limits, and is skipped when the Earth hides it
+    // from the user and earth_blockage is on. With a geo_arc limit, the beam
+    // must also keep that far from every point of the geostationary arc.
+    pub fn interference(
+        &self,
+        user: &Entity,
+        interferers: &[(Entity, f64)],
+        constraints: &Constraints,
+    ) -> bool {
+        let (user, satellite) = (user.position(), self.satellite.entity().position());
+        interferers.iter().any(|(interferer, angle)| {
+            !(constraints.earth_blockage && user.is_occluded(interferer.position()))
+                && Position::separation(user, satellite, interferer.position()).to_degrees()
+                    <= *angle
+        }) || constraints
+            .geo_arc_limit()
+            .is_some_and(|angle| user.geo_arc_separation(satellite).to_degrees() <= angle)
+    }
+
+    pub fn carries(&self, band: Band) -> bool {
+        self.payload.carries(band)
+    }
+
+    // Whether the satellite's arrays can steer a beam to the user.
+    pub fn within_scan(&self, user: &Entity) -> bool {
+        self.payload.max_scan.map_or(true, |limit| {
+            self.satellite
+                .entity()
+                .position()
+                .off_nadir(user.position())
+                .to_degrees()
+                < limit
+        })
+    }
+
+    // `terminal` is the user's, with its class and horizon mask.
+    pub fn can_accept(
+        &self,
+        user: &Entity,
+        band: Band,
+        terminal: &Terminal,
+        interferers: &[(Entity, f64)],
+        constraints: &Constraints,
+    ) -> bool {
+        self.beams.len() < self.payload.beams(constraints)
+            && self.payload.carries(band)
+            && self.payload.band_beams(band).map_or(true, |cap| {
+                self.beams.iter().filter(|b| b.band == band).count() < cap
             })
-            .any(|s| s)
+            && terminal.allows(band)
+            && terminal.is_visible(
+                user.position(),
+                self.satellite.entity().position(),
+                constraints,
+            )
+            && self.within_scan(user)
+            && !self.beam_intersection(user, band, constraints.beam_angle_limit())
+            && !self.interference(user, interferers, constraints)
+        // Stretch goal: including adjacent satellites
     }
+}
 
-    pub fn can_accept(&self, user: &Entity, band: Band, interferers: &Vec<Entity>) -> bool {
-        //  32 beams per satellite
-        if self.beams().len() >= BEAMS {
-            false
-        // 45 degree visibility
-        } else if !user.position().can_see(self.entity().position()) {
-            false
-        } else if self.beam_intersection(user, band) {
-            false
-        } else if self.interference(user, interferers) {
-            false
-            // Stretch goal: including adjacent satellites
-        } else {
-            true
+impl Beam {
+    pub fn new(user: Entity, band: Band) -> Beam {
+        Beam { user, band }
+    }
+
+    pub fn user(&self) -> &Entity {
+        &self.user
+    }
+
+    pub fn band(&self) -> Band {
+        self.band
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(Strategy::First),
+            "elevation" => Ok(Strategy::Elevation),
+            _ => Err(format!("unknown strategy '{}'", s)),
         }
     }
 }
 
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Strategy::First => "first",
+                Strategy::Elevation => "elevation",
+            }
+        )
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod test {
     use super::*;
+    use crate::validate::Violation;
 
     #[test]
     fn test_beam_intersection() {
-        let mut scenario = Scenario::from_str(
+        let scenario = Scenario::from_str(
             "user 1 6371 0 0
 user 2 6371 10 0
 user 3 6371 400 400
 sat 1 6921 0 0",
         );
-        let user = scenario.users()[0];
-        scenario.satellites_mut()[0]
-            .beams_mut()
-            .push(Beam::new(user, Band::A));
-        let satellite = &scenario.satellites()[0];
+        let mut satellite = Plan::new(&scenario.satellites()[0]);
+        satellite.push(Beam::new(scenario.users()[0], Band::A));
         assert_eq!(
-            satellite.beam_intersection(&scenario.users()[1], Band::A),
+            satellite.beam_intersection(&scenario.users()[1], Band::A, BEAM_ANGLE),
             true
         );
         assert_eq!(
-            satellite.beam_intersection(&scenario.users()[2], Band::A),
+            satellite.beam_intersection(&scenario.users()[2], Band::A, BEAM_ANGLE),
             false
         );
     }
 
     #[test]
     fn test_optimize() {
-        let mut scenario = Scenario::from_str(
+        let scenario = Scenario::from_str(
             "user 1 6371 0 0
 user 2 6371 10 0
 user 3 6371 400 400
 sat 1 6921 0 0",
         );
-        scenario.optimize();
-        let output = format!("{}", scenario);
+        let output = format!("{}", scenario.optimize());
         assert_eq!(
             output,
             "sat 1 beam 1 user 2 color A
@@ -186,7 +373,7 @@ sat 1 beam 2 user 1 color B"
 
     #[test]
     fn test_00_example() {
-        let mut scenario = Scenario::from_str(
+        let scenario = Scenario::from_str(
             "user 1 6371 0 0
 sat 1 6921 0 0
 user 2 0 0 6371
@@ -196,8 +383,7 @@ interferer 1 -42164 0 0
 ",
         );
 
-        scenario.optimize();
-        let output = format!("{}", scenario);
+        let output = format!("{}", scenario.optimize());
 
         assert_eq!(
             output,
@@ -207,6 +393,85 @@ sat 2 beam 2 user 2 color A"
         );
     }
 
+    #[test]
+    fn test_optimize_from() {
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+user 2 0 0 6371
+sat 1 6921 0 0
+sat 2 6900 0 300
+sat 3 0 0 6921",
+        );
+        // Left alone, user 1 goes to sat 1, straight overhead.
+        assert_eq!(scenario.optimize().by_user(1).unwrap().satellite, 1);
+        // Starting from a plan that has it on sat 2 in band C, it stays there.
+        // User 2's beam on sat 1 is out of sight now, so it moves to sat 3.
+        let previous: Solution = "sat 2 beam 1 user 1 color C
+sat 1 beam 1 user 2 color B"
+            .parse()
+            .unwrap();
+        let solution =
+            scenario.optimize_from(&previous, Strategy::default(), &Constraints::default());
+        assert_eq!(
+            solution.to_string(),
+            "sat 2 beam 1 user 1 color C
+sat 3 beam 1 user 2 color A"
+        );
+        assert!(scenario.validate(&solution).is_empty());
+    }
+
+    #[test]
+    fn test_geo_arc() {
+        // A user on the equator looking straight up looks right at the GEO
+        // arc, with no interferer listed there. Kept 20 degrees from the arc,
+        // it has to use the satellite to the north instead.
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+sat 1 6921 0 0
+sat 2 6891 0 300",
+        );
+        let constraints = Constraints {
+            geo_arc: Some(20.0),
+            ..Constraints::default()
+        };
+        assert_eq!(scenario.optimize().by_user(1).unwrap().satellite, 1);
+        let solution = scenario.optimize_with(Strategy::default(), &constraints);
+        assert_eq!(solution.by_user(1).unwrap().satellite, 2);
+        assert_eq!(scenario.validate_with(&solution, &constraints), vec![]);
+        let violations = scenario.validate_with(&scenario.optimize(), &constraints);
+        assert!(matches!(
+            violations[..],
+            [Violation::GeoArc {
+                satellite: 1,
+                user: 1,
+                ..
+            }]
+        ));
+        let slack = scenario.analyze_with(&solution, &constraints).slack;
+        assert!(slack.geo_arc.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_stable_beam_numbers() {
+        // Twelve users spread far enough apart to share a band, all served by
+        // one satellite. Beam numbers follow placement order and the output is
+        // in numeric beam order, so beam 10 comes after beam 9, not beam 1.
+        let users: String = (0..12)
+            .map(|i| {
+                let angle = (i as f64 * 30.0).to_radians();
+                let (y, z) = (angle.cos() * 300.0, angle.sin() * 300.0);
+                let x = (6371.0f64 * 6371.0 - y * y - z * z).sqrt();
+                format!("user {} {} {} {}\n", i + 1, x, y, z)
+            })
+            .collect();
+        let scenario = Scenario::from_str(&format!("{}sat 1 6921 0 0", users));
+        let solution = scenario.optimize();
+        assert_eq!(solution.len(), 12);
+        let beams: Vec<usize> = solution.iter().map(|a| a.beam).collect();
+        assert_eq!(beams, (1..=12).collect::<Vec<usize>>());
+        assert_eq!(solution.by_user(12).map(|a| a.beam), Some(1));
+    }
+
     #[test]
     fn test_11_example_beam_interference() {
         /*
@@ -219,19 +484,16 @@ sat 2 beam 2 user 2 color A"
         user 83636 -4462.399898375494 -1507.4791341925356 4286.176851267787
         user 21283 -4462.341423785467 -1507.5185635095902 4286.223546883291
         */
-        let mut scenario = Scenario::from_str(
+        let scenario = Scenario::from_str(
             "sat 624 -5111.007144121957 -1334.7360828140702 4471.7252332817225
 user 83636 -4462.399898375494 -1507.4791341925356 4286.176851267787
 user 21283 -4462.341423785467 -1507.5185635095902 4286.223546883291",
         );
 
-        let user = scenario.users()[0];
-        scenario.satellites_mut()[0]
-            .beams_mut()
-            .push(Beam::new(user, Band::A));
-        let satellite = &scenario.satellites()[0];
+        let mut satellite = Plan::new(&scenario.satellites()[0]);
+        satellite.push(Beam::new(scenario.users()[0], Band::A));
         let user = &scenario.users()[1];
-        let intersects = satellite.beam_intersection(user, Band::A);
+        let intersects = satellite.beam_intersection(user, Band::A, BEAM_ANGLE);
         assert_eq!(intersects, true);
     }
 }
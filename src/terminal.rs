@@ -0,0 +1,267 @@
+// Terminal classes. Fixed dishes, flat panels, maritime and aviation terminals
+// scan to different elevations and may support only some bands, so a scenario
+// can define classes,
+//   class maritime min_elev=35 bands=A,B
+// and give users one with a `class=maritime` attribute. A class's min_elev
+// takes the place of min_elevation for its users (the elevation margin still
+// applies), and its bands are the only ones they can be served on. Keys a
+// class doesn't set, and users without a class, fall back to the global
+// constraints; a user naming a class the scenario doesn't define is an input
+// error.
+use std::fmt;
+
+use crate::{
+    constraints::Constraints,
+    horizon::HorizonMask,
+    position::Position,
+    scenario::{Attributes, Band, EntityKind, Scenario},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalClass {
+    name: String,
+    min_elevation: Option<f64>,
+    bands: Option<Vec<Band>>,
+    attributes: Attributes, // As given, for writing the class back out
+}
+
+// Everything that limits one user's links beyond the shared constraints.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Terminal<'a> {
+    pub class: Option<&'a TerminalClass>,
+    pub mask: Option<&'a HorizonMask>,
+}
+
+impl TerminalClass {
+    // A class from its `key=value` attributes; min_elev is in degrees, from
+    // -90 to 90, and bands a comma-separated list. Other keys are kept but
+    // ignored.
+    pub fn new(name: &str, attributes: Attributes) -> Result<TerminalClass, String> {
+        let mut class = TerminalClass {
+            name: name.to_string(),
+            min_elevation: None,
+            bands: None,
+            attributes,
+        };
+        for (key, value) in &class.attributes {
+            match key.as_str() {
+                "min_elev" => {
+                    class.min_elevation = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|elevation: &f64| (-90.0..=90.0).contains(elevation))
+                            .ok_or_else(|| {
+                                format!("class {}: invalid min_elev '{}'", name, value)
+                            })?,
+                    )
+                }
+                "bands" => {
+                    let bands = value
+                        .split(',')
+                        .map(|band| band.parse())
+                        .collect::<Result<Vec<Band>, String>>()
+                        .map_err(|e| format!("class {}: {}", name, e))?;
+                    class.bands = Some(bands);
+                }
+                _ => (),
+            }
+        }
+        Ok(class)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn min_elevation(&self) -> Option<f64> {
+        self.min_elevation
+    }
+
+    pub fn bands(&self) -> Option<&[Band]> {
+        self.bands.as_deref()
+    }
+
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+}
+
+impl Terminal<'_> {
+    // The class's min_elev, or min_elevation, with the elevation margin.
+    pub fn elevation_limit(&self, constraints: &Constraints) -> f64 {
+        match self.class.and_then(TerminalClass::min_elevation) {
+            Some(min_elevation) => min_elevation + constraints.margins.elevation,
+            None => constraints.elevation_limit(),
+        }
+    }
+
+    // Degrees `target` stands above the lowest elevation this terminal may
+    // use toward it: the elevation limit, raised to the horizon mask (plus the
+    // elevation margin) in that direction. Positive means visible. For a user
+    // with neither class nor mask this is exactly the elevation test in
+    // `Position::can_see_above_with`.
+    pub fn clearance(&self, user: &Position, target: &Position, constraints: &Constraints) -> f64 {
+        let elevation = user.elevation(target, constraints.elevation_model);
+        let limit = match self.mask {
+            Some(mask) => {
+                let azimuth = user
+                    .look_angles(target, constraints.elevation_model)
+                    .azimuth;
+                let masked = mask.elevation(azimuth) + constraints.margins.elevation;
+                self.elevation_limit(constraints).max(masked)
+            }
+            None => self.elevation_limit(constraints),
+        };
+        elevation - limit
+    }
+
+    pub fn is_visible(
+        &self,
+        user: &Position,
+        target: &Position,
+        constraints: &Constraints,
+    ) -> bool {
+        self.clearance(user, target, constraints) > 0.0
+    }
+
+    pub fn allows(&self, band: Band) -> bool {
+        match self.class.and_then(TerminalClass::bands) {
+            Some(bands) => bands.contains(&band),
+            None => true,
+        }
+    }
+}
+
+impl Scenario {
+    // Classes in the order they were defined.
+    pub fn classes(&self) -> &[TerminalClass] {
+        &self.classes
+    }
+
+    pub fn class(&self, name: &str) -> Option<&TerminalClass> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+
+    // The user's class, if its `class` attribute names one the scenario
+    // defines.
+    pub fn terminal_class(&self, user: i32) -> Option<&TerminalClass> {
+        self.attribute(EntityKind::User, user, "class")
+            .and_then(|name| self.class(name))
+    }
+
+    pub fn terminal(&self, user: i32) -> Terminal<'_> {
+        Terminal {
+            class: self.terminal_class(user),
+            mask: self.horizon(user),
+        }
+    }
+
+    pub(crate) fn check_class(&self, user: i32) -> Result<(), String> {
+        match self.attribute(EntityKind::User, user, "class") {
+            Some(name) if self.class(name).is_none() => {
+                Err(format!("user {}: undefined class '{}'", user, name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // A later definition of a class replaces an earlier one.
+    pub(crate) fn add_class(&mut self, class: TerminalClass) {
+        match self.classes.iter_mut().find(|c| c.name == class.name) {
+            Some(existing) => *existing = class,
+            None => self.classes.push(class),
+        }
+    }
+}
+
+impl fmt::Display for TerminalClass {
+    // As a `class` line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "class {}", self.name)?;
+        for (key, value) in &self.attributes {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{solution::Solution, validate::Violation};
+
+    #[test]
+    fn test_class() {
+        let attributes = vec![
+            (String::from("min_elev"), String::from("35")),
+            (String::from("bands"), String::from("A,B")),
+        ];
+        let class = TerminalClass::new("maritime", attributes).unwrap();
+        assert_eq!(class.min_elevation(), Some(35.0));
+        assert_eq!(class.bands(), Some(&[Band::A, Band::B][..]));
+        assert_eq!(class.to_string(), "class maritime min_elev=35 bands=A,B");
+
+        let terminal = Terminal {
+            class: Some(&class),
+            mask: None,
+        };
+        assert!(terminal.allows(Band::B) && !terminal.allows(Band::C));
+        assert!(Terminal::default().allows(Band::C));
+        let constraints = Constraints::default();
+        assert_eq!(terminal.elevation_limit(&constraints), 35.0);
+        assert_eq!(Terminal::default().elevation_limit(&constraints), 45.0);
+
+        let bad = vec![(String::from("bands"), String::from("A,E"))];
+        assert!(TerminalClass::new("broken", bad).is_err());
+        for elevation in ["abc", "NaN", "inf", "91", "-90.5"].iter() {
+            let bad = vec![(String::from("min_elev"), elevation.to_string())];
+            assert_eq!(
+                TerminalClass::new("broken", bad),
+                Err(format!("class broken: invalid min_elev '{}'", elevation))
+            );
+        }
+    }
+
+    #[test]
+    fn test_terminal_classes() {
+        // The satellite is 40 degrees up: only the maritime terminal, which
+        // scans to 35, can use it, and only on bands A and B.
+        let input = "class maritime min_elev=35 bands=A,B
+user 1 6371 0 0 class=maritime
+user 2 6371 0 0 class=maritime
+user 3 6371 0 0 class=maritime
+user 4 6371 0 0
+sat 1 6621 0 298";
+        let scenario = Scenario::from_str(input);
+        assert_eq!(scenario.terminal_class(1).unwrap().name(), "maritime");
+        assert_eq!(scenario.terminal_class(4), None);
+        let solution = scenario.optimize();
+        assert_eq!(solution.covered(), 2);
+        assert!(solution.iter().all(|a| a.user != 4 && a.band != Band::C));
+        assert_eq!(scenario.validate(&solution), vec![]);
+        assert!(scenario
+            .to_input()
+            .starts_with("class maritime min_elev=35 bands=A,B\nuser 1"));
+
+        let forced: Solution = "sat 1 beam 1 user 1 color C\nsat 1 beam 2 user 4 color A"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            scenario.validate(&forced)[..],
+            [
+                Violation::BandNotSupported { user: 1, .. },
+                Violation::NotVisible { user: 4, .. }
+            ]
+        ));
+
+        let analysis = scenario.analyze(&solution);
+        assert_eq!(analysis.uncovered, 1);
+        let classes: Vec<_> = analysis
+            .classes
+            .iter()
+            .map(|c| (c.name.as_str(), c.users, c.covered, c.uncovered))
+            .collect();
+        assert_eq!(classes, [("maritime", 3, 2, 0), ("default", 1, 0, 1)]);
+    }
+}
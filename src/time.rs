@@ -0,0 +1,165 @@
+// UTC instants, kept as Julian dates. An f64 Julian date resolves about 40
+// microseconds, far finer than anything planned here. Leap seconds and the
+// UT1 - UTC difference are ignored, as they are in TLE epochs.
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Utc {
+    julian_date: f64,
+}
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+impl Utc {
+    pub fn from_julian_date(julian_date: f64) -> Utc {
+        Utc { julian_date }
+    }
+
+    // Valid from 1900 to 2100, which covers every TLE epoch.
+    pub fn from_calendar(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: f64,
+    ) -> Utc {
+        let (year, month) = (year as f64, month as f64);
+        let julian_date = 367.0 * year
+            - (7.0 * (year + ((month + 9.0) / 12.0).floor()) * 0.25).floor()
+            + (275.0 * month / 9.0).floor()
+            + day as f64
+            + 1_721_013.5
+            + ((second / 60.0 + minute as f64) / 60.0 + hour as f64) / 24.0;
+        Utc { julian_date }
+    }
+
+    // `day` is 1-based and fractional: 1.5 is noon on January 1st.
+    pub fn from_day_of_year(year: i32, day: f64) -> Utc {
+        Utc::from_julian_date(Utc::from_calendar(year, 1, 1, 0, 0, 0.0).julian_date + day - 1.0)
+    }
+
+    pub fn julian_date(&self) -> f64 {
+        self.julian_date
+    }
+
+    pub fn add_seconds(&self, seconds: f64) -> Utc {
+        Utc::from_julian_date(self.julian_date + seconds / SECONDS_PER_DAY)
+    }
+
+    pub fn seconds_since(&self, earlier: &Utc) -> f64 {
+        (self.julian_date - earlier.julian_date) * SECONDS_PER_DAY
+    }
+
+    // Year, month, day, hour, minute and second.
+    pub fn to_calendar(&self) -> (i32, u32, u32, u32, u32, f64) {
+        // Round to the millisecond first, so 59.9996 seconds doesn't print as
+        // 60.000.
+        let milliseconds = ((self.julian_date + 0.5) * SECONDS_PER_DAY * 1000.0).round();
+        let days = (milliseconds / (SECONDS_PER_DAY * 1000.0)).floor();
+        let mut remainder = milliseconds - days * SECONDS_PER_DAY * 1000.0;
+
+        // Fliegel and Van Flandern's Julian day to Gregorian date.
+        let l = days as i64 + 68569;
+        let n = 4 * l / 146_097;
+        let l = l - (146_097 * n + 3) / 4;
+        let i = 4000 * (l + 1) / 1_461_001;
+        let l = l - 1461 * i / 4 + 31;
+        let j = 80 * l / 2447;
+        let day = l - 2447 * j / 80;
+        let l = j / 11;
+        let month = j + 2 - 12 * l;
+        let year = 100 * (n - 49) + i + l;
+
+        let hour = (remainder / 3_600_000.0).floor();
+        remainder -= hour * 3_600_000.0;
+        let minute = (remainder / 60_000.0).floor();
+        remainder -= minute * 60_000.0;
+        (
+            year as i32,
+            month as u32,
+            day as u32,
+            hour as u32,
+            minute as u32,
+            remainder / 1000.0,
+        )
+    }
+}
+
+impl FromStr for Utc {
+    type Err = String;
+
+    // ISO 8601 in UTC: `2024-03-01T12:00:00Z`, with optional fractional
+    // seconds, a space in place of the `T`, and no `Z`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid time '{}'; expected YYYY-MM-DDTHH:MM:SS[Z]", s);
+        let trimmed = s.strip_suffix('Z').unwrap_or(s);
+        let (date, time) = trimmed
+            .split_once('T')
+            .or_else(|| trimmed.split_once(' '))
+            .unwrap_or((trimmed, "00:00:00"));
+        let date: Vec<&str> = date.split('-').collect();
+        let time: Vec<&str> = time.split(':').collect();
+        let (year, month, day, hour, minute, second) = match (&date[..], &time[..]) {
+            ([year, month, day], [hour, minute, second]) => (
+                year.parse::<i32>().map_err(|_| invalid())?,
+                month.parse::<u32>().map_err(|_| invalid())?,
+                day.parse::<u32>().map_err(|_| invalid())?,
+                hour.parse::<u32>().map_err(|_| invalid())?,
+                minute.parse::<u32>().map_err(|_| invalid())?,
+                second.parse::<f64>().map_err(|_| invalid())?,
+            ),
+            _ => return Err(invalid()),
+        };
+        if !(1900..=2100).contains(&year)
+            || !(1..=12).contains(&month)
+            || !(1..=31).contains(&day)
+            || hour > 23
+            || minute > 59
+            || !(0.0..60.0).contains(&second)
+        {
+            return Err(invalid());
+        }
+        Ok(Utc::from_calendar(year, month, day, hour, minute, second))
+    }
+}
+
+impl fmt::Display for Utc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day, hour, minute, second) = self.to_calendar();
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:06.3}Z",
+            year, month, day, hour, minute, second
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_julian_date() {
+        // J2000.0 is 2000-01-01 12:00 TT; near enough for UTC here.
+        let j2000: Utc = "2000-01-01T12:00:00Z".parse().unwrap();
+        assert_eq!(j2000.julian_date(), 2_451_545.0);
+        assert_eq!(Utc::from_day_of_year(2000, 1.5), j2000);
+        let later = j2000.add_seconds(86400.0 * 366.0 + 3723.5);
+        assert_eq!(later.to_string(), "2001-01-01T13:02:03.500Z");
+        assert!((later.seconds_since(&j2000) - (86400.0 * 366.0 + 3723.5)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_parse() {
+        let time: Utc = "2024-02-29 23:59:59.25".parse().unwrap();
+        assert_eq!(time.to_string(), "2024-02-29T23:59:59.250Z");
+        assert_eq!(
+            "2024-03-01".parse::<Utc>().unwrap().to_string(),
+            "2024-03-01T00:00:00.000Z"
+        );
+        assert!("2024-13-01T00:00:00Z".parse::<Utc>().is_err());
+        assert!("2024-01-01T24:00:00Z".parse::<Utc>().is_err());
+        assert!("yesterday".parse::<Utc>().is_err());
+    }
+}
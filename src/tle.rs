@@ -0,0 +1,413 @@
+// Two-line element sets, and satellites and interferers placed from them. In
+// the line format, `sat_tle <id>` or `interferer_tle <id>` is followed by the
+// element set's two lines; `sat_norad <id> <catalog number>` and
+// `interferer_norad ...` instead name an element set to be found in a TLE file
+// passed to `Scenario::load_tles`. Positions come from SGP4 at the scenario's
+// epoch: its `epoch <UTC>` line, or else the newest element set's epoch.
+use std::{fmt, str::FromStr};
+
+use crate::{
+    position::Position,
+    scenario::{EntityKind, Scenario},
+    sgp4::{Sgp4, Sgp4Error},
+    time::Utc,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tle {
+    pub catalog_number: u32,
+    pub epoch: Utc,
+    pub bstar: f64,               // Drag term, per Earth radius
+    pub inclination: f64,         // Degrees
+    pub raan: f64,                // Degrees
+    pub eccentricity: f64,        //
+    pub argument_of_perigee: f64, // Degrees
+    pub mean_anomaly: f64,        // Degrees
+    pub mean_motion: f64,         // Revolutions per day
+    lines: [String; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TleError {
+    MissingLine { line: u8 },
+    Length { line: u8 },
+    Checksum { line: u8, expected: u32, found: u32 },
+    Field { line: u8, field: &'static str },
+    CatalogMismatch,
+    Propagation(Sgp4Error),
+}
+
+impl Tle {
+    pub fn parse(line1: &str, line2: &str) -> Result<Tle, TleError> {
+        let line1 = check(line1, 1)?;
+        let line2 = check(line2, 2)?;
+        let catalog_number = number(line1, 1, 2..7, "catalog number")?;
+        if number::<u32>(line2, 2, 2..7, "catalog number")? != catalog_number {
+            return Err(TleError::CatalogMismatch);
+        }
+        let year: i32 = number(line1, 1, 18..20, "epoch year")?;
+        let year = if year < 57 { 2000 + year } else { 1900 + year };
+        let day: f64 = number(line1, 1, 20..32, "epoch day")?;
+        Ok(Tle {
+            catalog_number,
+            epoch: Utc::from_day_of_year(year, day),
+            bstar: implied_decimal(&line1[53..61]).ok_or(TleError::Field {
+                line: 1,
+                field: "bstar",
+            })?,
+            inclination: number(line2, 2, 8..16, "inclination")?,
+            raan: number(line2, 2, 17..25, "raan")?,
+            eccentricity: format!("0.{}", line2[26..33].trim()).parse().map_err(|_| {
+                TleError::Field {
+                    line: 2,
+                    field: "eccentricity",
+                }
+            })?,
+            argument_of_perigee: number(line2, 2, 34..42, "argument of perigee")?,
+            mean_anomaly: number(line2, 2, 43..51, "mean anomaly")?,
+            mean_motion: number(line2, 2, 52..63, "mean motion")?,
+            lines: [line1.to_string(), line2.to_string()],
+        })
+    }
+
+    pub fn lines(&self) -> (&str, &str) {
+        (&self.lines[0], &self.lines[1])
+    }
+
+    // Earth-fixed position at `time`.
+    pub fn position_at(&self, time: &Utc) -> Result<Position, Sgp4Error> {
+        Sgp4::new(self)?.position_at(time)
+    }
+}
+
+// Every element set in a TLE file, with or without name lines.
+pub fn parse_catalog(s: &str) -> Result<Vec<Tle>, TleError> {
+    let lines: Vec<&str> = s
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let mut tles = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].starts_with("1 ") {
+            let line2 = lines.get(i + 1).ok_or(TleError::MissingLine { line: 2 })?;
+            tles.push(Tle::parse(lines[i], line2)?);
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    Ok(tles)
+}
+
+// Trim a line and verify its length, line number and checksum: the last
+// column is the sum of the other digits, with each minus sign counting one,
+// modulo 10.
+fn check(line: &str, number: u8) -> Result<&str, TleError> {
+    let line = line.trim_end();
+    if line.len() != 69 || !line.is_ascii() {
+        return Err(TleError::Length { line: number });
+    }
+    if !line.starts_with(&format!("{} ", number)) {
+        return Err(TleError::Field {
+            line: number,
+            field: "line number",
+        });
+    }
+    let expected = line[..68]
+        .chars()
+        .map(|c| match c {
+            '-' => 1,
+            c => c.to_digit(10).unwrap_or(0),
+        })
+        .sum::<u32>()
+        % 10;
+    let found = line[68..].parse().map_err(|_| TleError::Field {
+        line: number,
+        field: "checksum",
+    })?;
+    if found != expected {
+        return Err(TleError::Checksum {
+            line: number,
+            expected,
+            found,
+        });
+    }
+    Ok(line)
+}
+
+fn number<T: FromStr>(
+    line: &str,
+    number: u8,
+    columns: std::ops::Range<usize>,
+    field: &'static str,
+) -> Result<T, TleError> {
+    line[columns].trim().parse().map_err(|_| TleError::Field {
+        line: number,
+        field,
+    })
+}
+
+// Fields like ` 28098-4`, meaning 0.28098e-4.
+fn implied_decimal(field: &str) -> Option<f64> {
+    let field = field.trim();
+    let split = field.rfind(['-', '+']).filter(|&i| i > 0)?;
+    let (mantissa, exponent) = field.split_at(split);
+    let (sign, digits) = match mantissa.strip_prefix('-') {
+        Some(digits) => (-1.0, digits),
+        None => (1.0, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    let mantissa: f64 = format!("0.{}", digits).parse().ok()?;
+    let exponent: i32 = exponent.parse().ok()?;
+    Some(sign * mantissa * 10f64.powi(exponent))
+}
+
+impl Scenario {
+    pub fn tle(&self, kind: EntityKind, id: i32) -> Option<&Tle> {
+        self.tles.get(&(kind, id))
+    }
+
+    // The instant the scenario's positions are for, if it has one: the
+    // `epoch` line, or the newest element set's epoch.
+    pub fn epoch(&self) -> Option<Utc> {
+        self.epoch.or_else(|| {
+            self.tles
+                .values()
+                .map(|tle| tle.epoch)
+                .fold(None, |newest: Option<Utc>, epoch| match newest {
+                    Some(newest) if newest >= epoch => Some(newest),
+                    _ => Some(epoch),
+                })
+        })
+    }
+
+    // Element sets that couldn't be read or propagated to the epoch. Their
+    // entities are left out of the scenario.
+    pub fn tle_errors(&self) -> &[(EntityKind, i32, TleError)] {
+        &self.tle_errors
+    }
+
+    // `_norad` entities still waiting for their element set, with the catalog
+    // number each needs.
+    pub fn unresolved(&self) -> Vec<(EntityKind, i32, u32)> {
+        let mut unresolved: Vec<(EntityKind, i32, u32)> = self
+            .norad
+            .iter()
+            .map(|((kind, id), norad)| (*kind, *id, *norad))
+            .collect();
+        unresolved.sort_by_key(|(kind, id, _)| (*kind as u8, *id));
+        unresolved
+    }
+
+    // Resolve `_norad` entities from a TLE file, returning how many were
+    // found.
+    pub fn load_tles(&mut self, catalog: &str) -> Result<usize, TleError> {
+        let tles = parse_catalog(catalog)?;
+        let mut found = 0;
+        for (kind, id, norad) in self.unresolved() {
+            if let Some(tle) = tles.iter().find(|tle| tle.catalog_number == norad) {
+                self.norad.remove(&(kind, id));
+                self.tles.insert((kind, id), tle.clone());
+                found += 1;
+            }
+        }
+        self.place_tles();
+        Ok(found)
+    }
+
+    // Move every entity with an element set to where SGP4 puts it at the
+    // epoch, dropping any that fail.
+    pub(crate) fn place_tles(&mut self) {
+        let epoch = match self.epoch() {
+            Some(epoch) => epoch,
+            None => return,
+        };
+        let mut failed = Vec::new();
+        let mut positions = Vec::new();
+        for (&(kind, id), tle) in &self.tles {
+            match tle.position_at(&epoch) {
+                Ok(position) => positions.push((kind, id, position)),
+                Err(error) => failed.push((kind, id, TleError::Propagation(error))),
+            }
+        }
+        for (kind, id, position) in positions {
+            if let Some(entity) = self.entity_mut(kind, id) {
+                entity.set_position(position);
+            }
+        }
+        failed.sort_by_key(|(kind, id, _)| (*kind as u8, *id));
+        for (kind, id, error) in failed {
+            self.tles.remove(&(kind, id));
+            self.remove_entity(kind, id);
+            self.tle_errors.push((kind, id, error));
+        }
+    }
+}
+
+impl FromStr for Tle {
+    type Err = TleError;
+
+    // The two element lines, optionally after a name line.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.lines().filter(|l| !l.trim().is_empty()).collect();
+        let start = lines
+            .iter()
+            .position(|l| l.starts_with("1 "))
+            .ok_or(TleError::MissingLine { line: 1 })?;
+        let line2 = lines
+            .get(start + 1)
+            .ok_or(TleError::MissingLine { line: 2 })?;
+        Tle::parse(lines[start], line2)
+    }
+}
+
+impl fmt::Display for Tle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n{}", self.lines[0], self.lines[1])
+    }
+}
+
+impl fmt::Display for TleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TleError::MissingLine { line } => write!(f, "TLE line {} is missing", line),
+            TleError::Length { line } => write!(f, "TLE line {} is not 69 characters", line),
+            TleError::Checksum {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "TLE line {} checksum is {} but should be {}",
+                line, found, expected
+            ),
+            TleError::Field { line, field } => {
+                write!(f, "TLE line {} has an invalid {}", line, field)
+            }
+            TleError::CatalogMismatch => write!(f, "TLE lines are for different satellites"),
+            TleError::Propagation(error) => write!(f, "SGP4 failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for TleError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ISS: &str = "ISS (ZARYA)
+1 25544U 98067A   19343.69339541  .00001764  00000-0  38792-4 0  9991
+2 25544  51.6439 211.2001 0007417  17.6667  85.6398 15.50103472202482";
+
+    // A geostationary satellite over 75 degrees east.
+    const GEO: &str = "1 99999U 19999A   19343.00000000  .00000000  00000-0  00000-0 0  9992
+2 99999   0.0100 100.0000 0001000 180.0000 232.4519  1.00273791    05";
+
+    #[test]
+    fn test_parse() {
+        let tle: Tle = ISS.parse().unwrap();
+        assert_eq!(tle.catalog_number, 25544);
+        assert_eq!(tle.epoch.to_string(), "2019-12-09T16:38:29.363Z");
+        assert!((tle.bstar - 0.38792e-4).abs() < 1e-12);
+        assert_eq!(tle.eccentricity, 0.0007417);
+        assert_eq!(tle.mean_motion, 15.50103472);
+        assert_eq!(
+            tle.to_string(),
+            ISS.lines().skip(1).collect::<Vec<_>>().join("\n")
+        );
+
+        let (line1, line2) = tle.lines();
+        let corrupted = line2.replace("51.6439", "51.6438");
+        assert_eq!(
+            Tle::parse(line1, &corrupted),
+            Err(TleError::Checksum {
+                line: 2,
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(
+            Tle::parse(line1, &line2[..60]),
+            Err(TleError::Length { line: 2 })
+        );
+        assert_eq!(
+            Tle::parse(line1, GEO.lines().nth(1).unwrap()),
+            Err(TleError::CatalogMismatch)
+        );
+        assert_eq!(implied_decimal("-11606-4"), Some(-0.11606e-4));
+        assert_eq!(implied_decimal(" 00000+0"), Some(0.0));
+    }
+
+    #[test]
+    fn test_scenario() {
+        let mut lines = vec!["shell iss", "user 1 6371 0 0", "sat_tle 7 shell=iss"];
+        lines.extend(ISS.lines().skip(1));
+        lines.extend(&["interferer_norad 1 99999", "sat 8 6921 0 0"]);
+        let mut scenario = Scenario::from_str(&lines.join("\n"));
+        // Without its shell, the error points at the `sat_tle` line.
+        let error = Scenario::parse(&lines[1..].join("\n")).err().unwrap();
+        assert_eq!(
+            (error.line, error.message.as_str()),
+            (2, "sat 7: undefined shell 'iss'")
+        );
+        assert_eq!(scenario.epoch(), Some(ISS.parse::<Tle>().unwrap().epoch));
+        assert_eq!(
+            scenario.unresolved(),
+            vec![(EntityKind::Interferer, 1, 99999)]
+        );
+        assert_eq!(scenario.satellites().len(), 2);
+        let iss = scenario.satellites()[0].entity().position().len();
+        assert!((6700.0..6850.0).contains(&iss), "{}", iss);
+        assert_eq!(
+            scenario.attribute(EntityKind::Satellite, 7, "shell"),
+            Some("iss")
+        );
+
+        // The GEO element set resolves the interferer. Propagated to the
+        // ISS epoch, 17 hours on, it is still over 75 degrees east.
+        assert_eq!(scenario.load_tles(GEO), Ok(1));
+        assert!(scenario.unresolved().is_empty());
+        let geo = *scenario.interferers()[0].position();
+        assert!((geo.len() - 42164.0).abs() < 50.0, "{}", geo.len());
+        assert!(geo.z.abs() < 100.0);
+        let longitude = geo.y.atan2(geo.x).to_degrees();
+        assert!((longitude - 75.0).abs() < 1.0, "{}", longitude);
+
+        // Written back out, element sets stay inline.
+        let input = scenario.to_input();
+        assert!(input.starts_with("epoch 2019-12-09T16:38:29.363Z\n"));
+        assert!(input.contains("\nsat_tle 7 shell=iss\n1 25544U"));
+        assert!(input.contains("\ninterferer_tle 1\n1 99999U"));
+        let round_trip = Scenario::from_str(&input);
+        assert_eq!(round_trip.to_input(), input);
+    }
+
+    #[test]
+    fn test_errors() {
+        let scenario = Scenario::from_str(
+            "sat_tle 1
+1 25544U 98067A   19343.69339541  .00001764  00000-0  38792-4 0  9990
+2 25544  51.6439 211.2001 0007417  17.6667  85.6398 15.50103472202482
+sat_tle 2
+sat 3 6921 0 0",
+        );
+        assert_eq!(scenario.satellites().len(), 1);
+        assert_eq!(
+            scenario.tle_errors(),
+            &[
+                (
+                    EntityKind::Satellite,
+                    1,
+                    TleError::Checksum {
+                        line: 1,
+                        expected: 1,
+                        found: 0
+                    }
+                ),
+                (EntityKind::Satellite, 2, TleError::MissingLine { line: 1 }),
+            ]
+        );
+    }
+}
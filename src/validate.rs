@@ -0,0 +1,477 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    constraints::Constraints,
+    scenario::{Band, Entity, Scenario, BANDS},
+    solution::Solution,
+};
+
+// Mirrors the checks in beam-planning/evaluate.py. The evaluator stops at the
+// first failure; this collects every violation so a bad solution can be
+// diagnosed in one pass. Thresholds are strict inequalities, matching the
+// evaluator, while the solver itself rejects at the boundary.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    UnknownSatellite {
+        satellite: i32,
+    },
+    UnknownUser {
+        user: i32,
+    },
+    InvalidBeam {
+        satellite: i32,
+        beam: usize,
+        max: usize,
+    },
+    DuplicateBeam {
+        satellite: i32,
+        beam: usize,
+    },
+    DuplicateUser {
+        user: i32,
+    },
+    NotVisible {
+        satellite: i32,
+        user: i32,
+        elevation: f64,
+    },
+    SelfInterference {
+        satellite: i32,
+        band: Band,
+        users: (i32, i32),
+        angle: f64,
+    },
+    Interference {
+        satellite: i32,
+        user: i32,
+        interferer: i32,
+        angle: f64,
+    },
+    GeoArc {
+        satellite: i32,
+        user: i32,
+        angle: f64,
+    },
+    BandNotSupported {
+        satellite: i32,
+        user: i32,
+        band: Band,
+    },
+    ScanAngle {
+        satellite: i32,
+        user: i32,
+        angle: f64,
+    },
+    SatelliteBand {
+        satellite: i32,
+        beam: usize,
+        band: Band,
+    },
+    BandBeams {
+        satellite: i32,
+        band: Band,
+        count: usize,
+        max: usize,
+    },
+}
+
+impl Scenario {
+    // Check a solution for this scenario. An empty list means the solution
+    // would pass the evaluator.
+    pub fn validate(&self, solution: &Solution) -> Vec<Violation> {
+        self.validate_with(solution, &Constraints::default())
+    }
+
+    // Any margins in `constraints` are checked as part of each limit; use
+    // `Constraints::without_margins` to check exactly what the evaluator does.
+    pub fn validate_with(&self, solution: &Solution, constraints: &Constraints) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let users = self.users_by_id();
+        let satellites = self.satellites_by_id();
+        let interferers = self.interference_limits(constraints);
+        let mut seen = HashSet::new();
+
+        for assignments in solution.satellites() {
+            let id = assignments[0].satellite;
+            let position = match satellites.get(&id) {
+                Some(satellite) => satellite.entity().position(),
+                None => {
+                    violations.push(Violation::UnknownSatellite { satellite: id });
+                    continue;
+                }
+            };
+            let payload = self.payload(id, constraints);
+
+            // Beams whose user exists, for the pairwise same-band check.
+            let mut beams: Vec<(&Entity, Band)> = Vec::with_capacity(assignments.len());
+            let mut previous = None;
+            for assignment in assignments {
+                if assignment.beam > payload.beams(constraints) {
+                    violations.push(Violation::InvalidBeam {
+                        satellite: id,
+                        beam: assignment.beam,
+                        max: payload.beams(constraints),
+                    });
+                }
+                if !payload.carries(assignment.band) {
+                    violations.push(Violation::SatelliteBand {
+                        satellite: id,
+                        beam: assignment.beam,
+                        band: assignment.band,
+                    });
+                }
+                if previous == Some(assignment.beam) {
+                    violations.push(Violation::DuplicateBeam {
+                        satellite: id,
+                        beam: assignment.beam,
+                    });
+                }
+                previous = Some(assignment.beam);
+
+                if !seen.insert(assignment.user) {
+                    violations.push(Violation::DuplicateUser {
+                        user: assignment.user,
+                    });
+                }
+                match users.get(&assignment.user) {
+                    Some(user) => beams.push((user, assignment.band)),
+                    None => violations.push(Violation::UnknownUser {
+                        user: assignment.user,
+                    }),
+                }
+            }
+            for band in BANDS.iter() {
+                let count = assignments.iter().filter(|a| a.band == *band).count();
+                match payload.band_beams(*band) {
+                    Some(max) if count > max => violations.push(Violation::BandBeams {
+                        satellite: id,
+                        band: *band,
+                        count,
+                        max,
+                    }),
+                    _ => (),
+                }
+            }
+
+            for (i, (user, band)) in beams.iter().enumerate() {
+                // Below the terminal's elevation limit, or its horizon mask.
+                let terminal = self.terminal(user.id());
+                if !terminal.allows(*band) {
+                    violations.push(Violation::BandNotSupported {
+                        satellite: id,
+                        user: user.id(),
+                        band: *band,
+                    });
+                }
+                if terminal.clearance(user.position(), position, constraints) <= 0.0 {
+                    violations.push(Violation::NotVisible {
+                        satellite: id,
+                        user: user.id(),
+                        elevation: user
+                            .position()
+                            .elevation(position, constraints.elevation_model),
+                    });
+                }
+                if let Some(limit) = payload.max_scan {
+                    let angle = position.off_nadir(user.position()).to_degrees();
+                    if angle > limit {
+                        violations.push(Violation::ScanAngle {
+                            satellite: id,
+                            user: user.id(),
+                            angle,
+                        });
+                    }
+                }
+
+                for (other, _) in beams[i + 1..].iter().filter(|(_, b)| b == band) {
+                    let angle = position
+                        .separation(user.position(), other.position())
+                        .to_degrees();
+                    if angle < constraints.beam_angle_limit() {
+                        violations.push(Violation::SelfInterference {
+                            satellite: id,
+                            band: *band,
+                            users: (user.id(), other.id()),
+                            angle,
+                        });
+                    }
+                }
+
+                for (interferer, limit) in &interferers {
+                    if constraints.earth_blockage
+                        && user.position().is_occluded(interferer.position())
+                    {
+                        continue;
+                    }
+                    let angle = user
+                        .position()
+                        .separation(position, interferer.position())
+                        .to_degrees();
+                    if angle < *limit {
+                        violations.push(Violation::Interference {
+                            satellite: id,
+                            user: user.id(),
+                            interferer: interferer.id(),
+                            angle,
+                        });
+                    }
+                }
+
+                if let Some(limit) = constraints.geo_arc_limit() {
+                    let angle = user.position().geo_arc_separation(position).to_degrees();
+                    if angle < limit {
+                        violations.push(Violation::GeoArc {
+                            satellite: id,
+                            user: user.id(),
+                            angle,
+                        });
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::UnknownSatellite { satellite } => {
+                write!(f, "Referenced an invalid sat id {}", satellite)
+            }
+            Violation::UnknownUser { user } => {
+                write!(f, "Referenced an invalid user id {}", user)
+            }
+            Violation::InvalidBeam {
+                satellite,
+                beam,
+                max,
+            } => write!(
+                f,
+                "Sat {} references invalid beam id {} (max {})",
+                satellite, beam, max
+            ),
+            Violation::DuplicateBeam { satellite, beam } => {
+                write!(
+                    f,
+                    "Sat {} beam {} is assigned more than once",
+                    satellite, beam
+                )
+            }
+            Violation::DuplicateUser { user } => {
+                write!(f, "User {} is covered multiple times", user)
+            }
+            Violation::NotVisible {
+                satellite,
+                user,
+                elevation,
+            } => write!(
+                f,
+                "Sat {} outside of user {}'s field of view ({} degrees elevation)",
+                satellite, user, elevation
+            ),
+            Violation::SelfInterference {
+                satellite,
+                band,
+                users,
+                angle,
+            } => write!(
+                f,
+                "Sat {} beams to users {} and {} interfere on band {} ({} degrees)",
+                satellite, users.0, users.1, band, angle
+            ),
+            Violation::Interference {
+                satellite,
+                user,
+                interferer,
+                angle,
+            } => write!(
+                f,
+                "Sat {} beam to user {} interferes with non-Starlink sat {} ({} degrees)",
+                satellite, user, interferer, angle
+            ),
+            Violation::GeoArc {
+                satellite,
+                user,
+                angle,
+            } => write!(
+                f,
+                "Sat {} beam to user {} comes within {} degrees of the GEO arc",
+                satellite, user, angle
+            ),
+            Violation::BandBeams {
+                satellite,
+                band,
+                count,
+                max,
+            } => write!(
+                f,
+                "Sat {} has {} beams on band {} (max {})",
+                satellite, count, band, max
+            ),
+            Violation::SatelliteBand {
+                satellite,
+                beam,
+                band,
+            } => write!(
+                f,
+                "Sat {} beam {} is on band {}, which the satellite doesn't carry",
+                satellite, beam, band
+            ),
+            Violation::ScanAngle {
+                satellite,
+                user,
+                angle,
+            } => write!(
+                f,
+                "Sat {} beam to user {} is {} degrees off nadir, beyond its scan limit",
+                satellite, user, angle
+            ),
+            Violation::BandNotSupported {
+                satellite,
+                user,
+                band,
+            } => write!(
+                f,
+                "Sat {} beam to user {} is on band {}, which the user's terminal class lacks",
+                satellite, user, band
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::orbit::OrbitModel;
+
+    #[test]
+    fn test_validate_optimized() {
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+sat 1 6921 0 0
+user 2 0 0 6371
+user 3 111.189281412 0 6370.02966584
+sat 2 0 0 6921
+interferer 1 -42164 0 0
+",
+        );
+        assert_eq!(scenario.validate(&scenario.optimize()), vec![]);
+    }
+
+    #[test]
+    fn test_validate_violations() {
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+user 2 6371 10 0
+user 3 0 0 6371
+sat 1 6921 0 0",
+        );
+        let solution: Solution = "sat 1 beam 1 user 1 color A
+sat 1 beam 2 user 2 color A
+sat 1 beam 3 user 3 color B
+sat 1 beam 3 user 3 color C
+sat 1 beam 33 user 4 color D
+sat 2 beam 1 user 1 color A"
+            .parse()
+            .unwrap();
+
+        // User 3 is on the far side of the Earth, and the beam to it listed
+        // twice is reported twice.
+        let users: Vec<_> = scenario.users().iter().map(|u| u.position()).collect();
+        let satellite = scenario.satellites()[0].entity().position();
+        let below = Violation::NotVisible {
+            satellite: 1,
+            user: 3,
+            elevation: users[2].elevation(satellite, Constraints::default().elevation_model),
+        };
+        assert_eq!(
+            scenario.validate(&solution),
+            vec![
+                Violation::DuplicateBeam {
+                    satellite: 1,
+                    beam: 3
+                },
+                Violation::DuplicateUser { user: 3 },
+                Violation::InvalidBeam {
+                    satellite: 1,
+                    beam: 33,
+                    max: 32
+                },
+                Violation::UnknownUser { user: 4 },
+                Violation::SelfInterference {
+                    satellite: 1,
+                    band: Band::A,
+                    users: (1, 2),
+                    angle: satellite.separation(users[0], users[1]).to_degrees(),
+                },
+                below.clone(),
+                below,
+                Violation::UnknownSatellite { satellite: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_moving_interferer() {
+        // Another operator's satellite 1200km up starts straight above the
+        // user, in line with ours, and five minutes later has moved well
+        // clear. Each epoch's plan is checked against where the interferer
+        // is at that epoch.
+        let scenario = Scenario::from_str(
+            "user 1 6371 0 0
+sat 1 6921 0 0
+interferer_orbit 1 0 0 0 1200",
+        );
+        assert!(scenario.optimize().is_empty());
+        let later = scenario.propagate(300.0, OrbitModel::J2);
+        assert_ne!(
+            later.interferers()[0].position(),
+            scenario.interferers()[0].position()
+        );
+        let solution = later.optimize();
+        assert_eq!(solution.covered(), 1);
+        assert_eq!(later.validate(&solution), vec![]);
+        assert!(matches!(
+            scenario.validate(&solution)[..],
+            [Violation::Interference { interferer: 1, .. }]
+        ));
+        assert!(later.to_input().contains("\ninterferer_orbit 1 0 "));
+    }
+
+    #[test]
+    fn test_protection_angles() {
+        // The interferer is 15 degrees from the user's line of sight to the
+        // satellite: inside the default 20, but clear of its own 10.
+        let (x, y) = (15f64.to_radians().cos(), 15f64.to_radians().sin());
+        let input = format!(
+            "user 1 6371 0 0
+sat 1 6921 0 0
+interferer 1 {} {} 0",
+            6371.0 + 1000.0 * x,
+            1000.0 * y
+        );
+        let strict = Scenario::from_str(&input);
+        assert_eq!(strict.protection_angle(1), None);
+        assert!(strict.optimize().is_empty());
+
+        let relaxed = Scenario::from_str(&format!("{} protect=10", input));
+        assert_eq!(relaxed.protection_angle(1), Some(10.0));
+        let solution = relaxed.optimize();
+        assert_eq!(solution.covered(), 1);
+        assert_eq!(relaxed.validate(&solution), vec![]);
+        assert_eq!(strict.validate(&solution).len(), 1);
+        let slack = relaxed.analyze(&solution).slack.interference_angle.unwrap();
+        assert!((slack - 5.0).abs() < 1e-9, "{}", slack);
+
+        for angle in ["abc", "-5", "181", "NaN", "inf"].iter() {
+            assert_eq!(
+                Scenario::parse(&format!("{} protect={}", input, angle))
+                    .err()
+                    .map(|e| e.to_string()),
+                Some(format!("line 3: interferer 1: invalid protect '{}'", angle))
+            );
+        }
+    }
+}